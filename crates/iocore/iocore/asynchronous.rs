@@ -0,0 +1,363 @@
+//! `asynchronous` provides async variants of the [`Path`] I/O
+//! methods, of the functions and [`Cmd`] methods of [`crate::sh`] and
+//! a [`Stream`] based walker, enabled with the `async` feature.
+//!
+//! Blocking operations run in a dedicated thread which wakes the
+//! awaiting task upon completion, hence these APIs depend on no
+//! particular async runtime and return the same [`Error`] type as
+//! their synchronous counterparts. Every call starts its own OS
+//! thread, there is no pool, so callers awaiting many operations at
+//! once should bound their concurrency or use the blocking pool of
+//! their runtime with the synchronous APIs instead.
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+pub use futures_core::Stream;
+
+use crate::walk::WalkDir;
+use crate::{traceback, Cmd, CommandOutput, Error, MaxDepth, Path, Size, WalkProgressHandler};
+
+/// `Channel` is the state shared between a background thread and the
+/// [`Blocking`] future or [`WalkDirStream`] awaiting its items
+struct Channel<T> {
+    items: VecDeque<T>,
+    closed: bool,
+    cancelled: bool,
+    waker: Option<Waker>,
+}
+impl<T> std::fmt::Debug for Channel<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Channel")
+            .field("items", &self.items.len())
+            .field("closed", &self.closed)
+            .field("cancelled", &self.cancelled)
+            .finish()
+    }
+}
+type SharedChannel<T> = Arc<Mutex<Channel<T>>>;
+
+fn channel<T>() -> SharedChannel<T> {
+    Arc::new(Mutex::new(Channel {
+        items: VecDeque::new(),
+        closed: false,
+        cancelled: false,
+        waker: None,
+    }))
+}
+
+fn lock<T>(channel: &SharedChannel<T>) -> std::sync::MutexGuard<'_, Channel<T>> {
+    channel.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+/// `send` returns false once the receiving end was dropped
+fn send<T>(channel: &SharedChannel<T>, item: T) -> bool {
+    let mut channel = lock(channel);
+    if channel.cancelled {
+        return false;
+    }
+    channel.items.push_back(item);
+    if let Some(waker) = channel.waker.take() {
+        waker.wake();
+    }
+    true
+}
+
+fn close<T>(channel: &SharedChannel<T>) {
+    let mut channel = lock(channel);
+    channel.closed = true;
+    if let Some(waker) = channel.waker.take() {
+        waker.wake();
+    }
+}
+
+fn poll_channel<T>(channel: &SharedChannel<T>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+    let mut channel = lock(channel);
+    match channel.items.pop_front() {
+        Some(item) => Poll::Ready(Some(item)),
+        None if channel.closed => Poll::Ready(None),
+        None => {
+            channel.waker = Some(cx.waker().clone());
+            Poll::Pending
+        },
+    }
+}
+
+fn cancel<T>(channel: &SharedChannel<T>) {
+    let mut channel = lock(channel);
+    channel.cancelled = true;
+    channel.items.clear();
+}
+
+/// `Blocking` is the [`Future`] returned by [`spawn_blocking`]
+#[derive(Debug)]
+pub struct Blocking<T> {
+    channel: SharedChannel<Result<T, Error>>,
+}
+impl<T> Future for Blocking<T> {
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T, Error>> {
+        match poll_channel(&self.channel, cx) {
+            Poll::Ready(Some(result)) => Poll::Ready(result),
+            Poll::Ready(None) =>
+                Poll::Ready(Err(traceback!(ThreadGroupError, "blocking operation panicked"))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+impl<T> Drop for Blocking<T> {
+    fn drop(&mut self) {
+        cancel(&self.channel);
+    }
+}
+
+/// `spawn_blocking` runs `operation` in a new thread returning a
+/// [`Future`] of its result which can be awaited from any async
+/// runtime.
+///
+/// Each call spawns and tears down one OS thread, which costs a
+/// stack allocation and a system call, and threads are not reused
+/// nor limited in number. Dropping the [`Future`] does not stop
+/// `operation`, its thread runs to completion regardless.
+///
+/// Example
+///
+/// ```
+/// use iocore::asynchronous::spawn_blocking;
+/// let result = pollster::block_on(spawn_blocking(|| Ok(6 * 7)));
+/// assert_eq!(result.unwrap(), 42);
+/// ```
+pub fn spawn_blocking<T: Send + 'static>(
+    operation: impl FnOnce() -> Result<T, Error> + Send + 'static,
+) -> Blocking<T> {
+    let channel = channel::<Result<T, Error>>();
+    let sender = channel.clone();
+    let spawned =
+        std::thread::Builder::new().name("iocore-blocking".to_string()).spawn(move || {
+            // closes the channel even if `operation` panics
+            struct Closer<T>(SharedChannel<T>);
+            impl<T> Drop for Closer<T> {
+                fn drop(&mut self) {
+                    close(&self.0);
+                }
+            }
+            let closer = Closer(sender);
+            send(&closer.0, operation());
+        });
+    if let Err(error) = spawned {
+        send(&channel, Err(traceback!(ThreadGroupError, "spawning thread: {}", error)));
+        close(&channel);
+    }
+    Blocking { channel }
+}
+
+impl Path {
+    pub fn read_async(&self) -> Blocking<String> {
+        let path = self.clone();
+        spawn_blocking(move || path.read())
+    }
+
+    pub fn read_bytes_async(&self) -> Blocking<Vec<u8>> {
+        let path = self.clone();
+        spawn_blocking(move || path.read_bytes())
+    }
+
+    pub fn read_lines_async(&self) -> Blocking<Vec<String>> {
+        let path = self.clone();
+        spawn_blocking(move || path.read_lines())
+    }
+
+    pub fn write_async(&self, contents: impl AsRef<[u8]>) -> Blocking<Path> {
+        let path = self.clone();
+        let contents = contents.as_ref().to_vec();
+        spawn_blocking(move || path.write(&contents))
+    }
+
+    pub fn append_async(&self, contents: impl AsRef<[u8]>) -> Blocking<usize> {
+        let path = self.clone();
+        let contents = contents.as_ref().to_vec();
+        spawn_blocking(move || path.append(&contents))
+    }
+
+    pub fn delete_async(&self) -> Blocking<Path> {
+        let path = self.clone();
+        spawn_blocking(move || path.delete())
+    }
+
+    pub fn list_async(&self) -> Blocking<Vec<Path>> {
+        let path = self.clone();
+        spawn_blocking(move || path.list())
+    }
+
+    pub fn mkdir_async(&self) -> Blocking<Path> {
+        let path = self.clone();
+        spawn_blocking(move || path.mkdir())
+    }
+
+    pub fn mkdir_parents_async(&self) -> Blocking<Path> {
+        let path = self.clone();
+        spawn_blocking(move || path.mkdir_parents())
+    }
+
+    pub fn size_async(&self) -> Blocking<Size> {
+        let path = self.clone();
+        spawn_blocking(move || path.size())
+    }
+
+    pub fn canonicalize_async(&self) -> Blocking<Path> {
+        let path = self.clone();
+        spawn_blocking(move || path.canonicalize())
+    }
+}
+
+impl Cmd {
+    /// `output_async` is the async variant of [`Cmd::output`]
+    pub fn output_async(&self) -> Blocking<std::process::Output> {
+        let cmd = self.clone();
+        spawn_blocking(move || cmd.output())
+    }
+
+    /// `status_async` is the async variant of [`Cmd::status`]
+    pub fn status_async(&self) -> Blocking<i32> {
+        let cmd = self.clone();
+        spawn_blocking(move || cmd.status())
+    }
+
+    /// `run_async` is the async variant of [`Cmd::run`]
+    pub fn run_async(&self) -> Blocking<CommandOutput> {
+        let cmd = self.clone();
+        spawn_blocking(move || cmd.run())
+    }
+
+    /// `check_async` is the async variant of [`Cmd::check`]
+    pub fn check_async(&self) -> Blocking<CommandOutput> {
+        let cmd = self.clone();
+        spawn_blocking(move || cmd.check())
+    }
+}
+
+/// `shell_command_async` is the async variant of
+/// [`crate::shell_command`]
+pub fn shell_command_async(
+    command: impl std::fmt::Display,
+    current_dir: impl Into<Path>,
+) -> Blocking<i32> {
+    let (command, current_dir) = (command.to_string(), current_dir.into());
+    spawn_blocking(move || crate::shell_command(command, current_dir))
+}
+
+/// `shell_command_stdout_async` is the async variant of
+/// [`crate::shell_command_stdout`]
+pub fn shell_command_stdout_async(
+    command: impl std::fmt::Display,
+    current_dir: impl Into<Path>,
+) -> Blocking<String> {
+    let (command, current_dir) = (command.to_string(), current_dir.into());
+    spawn_blocking(move || crate::shell_command_stdout(command, current_dir))
+}
+
+/// `shell_command_string_output_async` is the async variant of
+/// [`crate::shell_command_string_output`]
+pub fn shell_command_string_output_async(
+    command: impl std::fmt::Display,
+    current_dir: impl Into<Path>,
+) -> Blocking<(i32, String, String)> {
+    let (command, current_dir) = (command.to_string(), current_dir.into());
+    spawn_blocking(move || crate::shell_command_string_output(command, current_dir))
+}
+
+/// `shell_command_vec_output_async` is the async variant of
+/// [`crate::shell_command_vec_output`]
+pub fn shell_command_vec_output_async(
+    command: impl std::fmt::Display,
+    current_dir: impl Into<Path>,
+) -> Blocking<(i32, Vec<u8>, Vec<u8>)> {
+    let (command, current_dir) = (command.to_string(), current_dir.into());
+    spawn_blocking(move || crate::shell_command_vec_output(command, current_dir))
+}
+
+/// `WalkDirStream` is the [`Stream`] returned by [`walk_dir_stream`]
+#[derive(Debug)]
+pub struct WalkDirStream {
+    channel: SharedChannel<Result<Path, Error>>,
+}
+impl WalkDirStream {
+    /// `next` returns the next path, or error, of the walk, `None`
+    /// once it is over
+    pub async fn next(&mut self) -> Option<Result<Path, Error>> {
+        std::future::poll_fn(|cx| poll_channel(&self.channel, cx)).await
+    }
+
+    /// `collect` returns every path of the walk sorted, as
+    /// [`crate::walk_dir`] does, or the first error
+    pub async fn collect(mut self) -> Result<Vec<Path>, Error> {
+        let mut paths = Vec::<Path>::new();
+        while let Some(path) = self.next().await {
+            paths.push(path?);
+        }
+        paths.sort();
+        Ok(paths)
+    }
+}
+impl Stream for WalkDirStream {
+    type Item = Result<Path, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        poll_channel(&self.channel, cx)
+    }
+}
+impl Drop for WalkDirStream {
+    fn drop(&mut self) {
+        cancel(&self.channel);
+    }
+}
+
+/// `walk_dir_stream` is the [`Stream`] based variant of
+/// [`crate::walk_dir`] which yields paths as they are found, in
+/// depth-first order, through the very same traversal, obeying the
+/// same [`WalkProgressHandler`] protocol. The walk runs in a
+/// background thread which stops at the first path found after the
+/// stream is dropped, once an error is yielded or once
+/// [`crate::shutdown_requested`].
+///
+/// Example
+///
+/// ```
+/// use iocore::asynchronous::walk_dir_stream;
+/// use iocore::{walk_dir, NoopProgressHandler};
+/// let paths = pollster::block_on(walk_dir_stream("iocore", NoopProgressHandler, None).collect());
+/// assert_eq!(paths.unwrap(), walk_dir("iocore", NoopProgressHandler, None).unwrap());
+/// ```
+pub fn walk_dir_stream(
+    path: impl Into<Path>,
+    handler: impl WalkProgressHandler,
+    max_depth: Option<MaxDepth>,
+) -> WalkDirStream {
+    let path = path.into();
+    let channel = channel::<Result<Path, Error>>();
+    let sender = channel.clone();
+    let spawned = std::thread::Builder::new()
+        .name(format!("walk_dir_stream:{}", path))
+        .spawn(move || {
+            match WalkDir::new(&path, handler, max_depth) {
+                Ok(walk) =>
+                    for path in walk {
+                        if !send(&sender, path) {
+                            break;
+                        }
+                    },
+                Err(error) => {
+                    send(&sender, Err(error));
+                },
+            }
+            close(&sender);
+        });
+    if let Err(error) = spawned {
+        send(&channel, Err(traceback!(WalkDirError, "spawning thread: {}", error)));
+        close(&channel);
+    }
+    WalkDirStream { channel }
+}
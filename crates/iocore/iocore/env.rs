@@ -1,4 +1,9 @@
-use crate::Error;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{traceback, ByteUnit, Error, Path, PathDateTime, Size};
 
 /// returns [`std::env::args`] as [`Vec<String>`]
 pub fn args() -> Vec<String> {
@@ -13,6 +18,506 @@ pub fn var(key: impl std::fmt::Display) -> Result<String, Error> {
     })?)
 }
 
+/// `EnvVarErrorKind` describes why an environment variable
+/// could not be obtained
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EnvVarErrorKind {
+    NotPresent,
+    NotUnicode,
+    Invalid(String),
+}
+
+/// `EnvVarError` is the structured error of the typed
+/// environment variable functions such as [`var_as`] and converts
+/// into `Error::EnvironmentVarError`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnvVarError {
+    pub key: String,
+    pub value: Option<String>,
+    pub kind: EnvVarErrorKind,
+}
+impl EnvVarError {
+    fn invalid(key: &str, value: &str, reason: impl Display) -> EnvVarError {
+        EnvVarError {
+            key: key.to_string(),
+            value: Some(value.to_string()),
+            kind: EnvVarErrorKind::Invalid(reason.to_string()),
+        }
+    }
+}
+impl Display for EnvVarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.kind {
+            EnvVarErrorKind::NotPresent =>
+                write!(f, "environment variable {:#?} is not set", self.key),
+            EnvVarErrorKind::NotUnicode =>
+                write!(f, "environment variable {:#?} is not valid unicode", self.key),
+            EnvVarErrorKind::Invalid(reason) => write!(
+                f,
+                "invalid value {:#?} of environment variable {:#?}: {}",
+                self.value.clone().unwrap_or_default(),
+                self.key,
+                reason
+            ),
+        }
+    }
+}
+impl std::error::Error for EnvVarError {}
+
+/// `try_var` returns the value of the environment variable `key`
+/// without printing anything in case of error
+pub fn try_var(key: impl Display) -> Result<String, EnvVarError> {
+    let key = key.to_string();
+    std::env::var(&key).map_err(|error| EnvVarError {
+        kind: match error {
+            std::env::VarError::NotPresent => EnvVarErrorKind::NotPresent,
+            std::env::VarError::NotUnicode(_) => EnvVarErrorKind::NotUnicode,
+        },
+        key,
+        value: None,
+    })
+}
+
+/// `var_as` parses the environment variable `key` into `T`
+///
+/// Example
+///
+/// ```
+/// use iocore::env::var_as;
+/// std::env::set_var("IOCORE_VAR_AS_EXAMPLE", "42");
+/// assert_eq!(var_as::<u16>("IOCORE_VAR_AS_EXAMPLE").unwrap(), 42);
+/// ```
+pub fn var_as<T: FromStr>(key: impl Display) -> Result<T, EnvVarError>
+where
+    T::Err: Display,
+{
+    let key = key.to_string();
+    let value = try_var(&key)?;
+    T::from_str(value.trim()).map_err(|error| EnvVarError::invalid(&key, &value, error))
+}
+
+/// `var_or` parses the environment variable `key` into `T`
+/// returning `default` if the variable is not set, a value that
+/// fails to parse is an error rather than silently replaced by
+/// `default`.
+pub fn var_or<T: FromStr>(key: impl Display, default: T) -> Result<T, EnvVarError>
+where
+    T::Err: Display,
+{
+    match var_as(key) {
+        Err(EnvVarError { kind: EnvVarErrorKind::NotPresent, .. }) => Ok(default),
+        result => result,
+    }
+}
+
+/// `var_bool` parses the environment variable `key` as a boolean
+/// where `1`, `true`, `yes` and `on` are true and `0`, `false`,
+/// `no`, `off` and empty values are false, case-insensitively.
+pub fn var_bool(key: impl Display) -> Result<bool, EnvVarError> {
+    let key = key.to_string();
+    let value = try_var(&key)?;
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" | "" => Ok(false),
+        _ => Err(EnvVarError::invalid(&key, &value, "not a boolean")),
+    }
+}
+
+/// `var_list` splits the `PATH`-like environment variable `key` on
+/// `:` discarding empty items
+pub fn var_list(key: impl Display) -> Result<Vec<String>, EnvVarError> {
+    Ok(try_var(key)?
+        .split(':')
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+        .collect())
+}
+
+/// `var_path` returns the environment variable `key` as a [`Path`]
+/// expanding `~` to the current user's home
+pub fn var_path(key: impl Display) -> Result<Path, EnvVarError> {
+    let key = key.to_string();
+    let value = try_var(&key)?;
+    Path::safe(&value).map_err(|error| EnvVarError::invalid(&key, &value, error))
+}
+
+/// `var_paths` splits the `PATH`-like environment variable `key`
+/// into a list of [`Path`]
+pub fn var_paths(key: impl Display) -> Result<Vec<Path>, EnvVarError> {
+    let key = key.to_string();
+    let mut paths = Vec::<Path>::new();
+    for item in var_list(&key)? {
+        paths.push(
+            Path::safe(&item).map_err(|error| EnvVarError::invalid(&key, &item, error))?,
+        );
+    }
+    Ok(paths)
+}
+
+/// `var_size` parses the environment variable `key` as a [`Size`]
+/// in bytes optionally followed by a unit, e.g.: `"512"`, `"64K"`,
+/// `"10Mb"` or `"1G"`
+pub fn var_size(key: impl Display) -> Result<Size, EnvVarError> {
+    let key = key.to_string();
+    let value = try_var(&key)?;
+    let trimmed = value.trim();
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    let (number, unit) = trimmed.split_at(digits);
+    let number =
+        u64::from_str(number).map_err(|error| EnvVarError::invalid(&key, &value, error))?;
+    let unit = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => ByteUnit::Byte,
+        "k" | "kb" => ByteUnit::Kilo,
+        "m" | "mb" => ByteUnit::Mega,
+        "g" | "gb" => ByteUnit::Giga,
+        "t" | "tb" => ByteUnit::Tera,
+        "p" | "pb" => ByteUnit::Peta,
+        unit =>
+            return Err(EnvVarError::invalid(
+                &key,
+                &value,
+                format!("unknown unit {:#?}", unit),
+            )),
+    };
+    number
+        .checked_mul(unit.as_u64())
+        .map(Size::from)
+        .ok_or_else(|| EnvVarError::invalid(&key, &value, "size overflow"))
+}
+
+/// `var_datetime` parses the environment variable `key` as a
+/// [`PathDateTime`] from either RFC 3339 or a unix timestamp in
+/// seconds
+pub fn var_datetime(key: impl Display) -> Result<PathDateTime, EnvVarError> {
+    let key = key.to_string();
+    let value = try_var(&key)?;
+    let trimmed = value.trim();
+    if let Ok(seconds) = i64::from_str(trimmed) {
+        return chrono::DateTime::from_timestamp(seconds, 0)
+            .map(|datetime| PathDateTime::from_datetime_utc(&datetime))
+            .ok_or_else(|| EnvVarError::invalid(&key, &value, "timestamp out of range"));
+    }
+    chrono::DateTime::parse_from_rfc3339(trimmed)
+        .map(|datetime| PathDateTime::from_datetime_fixed_offset(&datetime))
+        .map_err(|error| EnvVarError::invalid(&key, &value, error))
+}
+
+/// `vars` returns the environment variables of the current process
+/// sorted by name, variables which are not valid unicode are
+/// skipped.
+pub fn vars() -> BTreeMap<String, String> {
+    std::env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+/// `EnvSnapshot` captures the environment of the current process and
+/// restores it when dropped such that variables can be overridden
+/// temporarily.
+///
+/// Example
+///
+/// ```
+/// use iocore::env::EnvSnapshot;
+/// {
+///     let _snapshot = EnvSnapshot::capture();
+///     std::env::set_var("IOCORE_ENV_SNAPSHOT_EXAMPLE", "temporary");
+/// }
+/// assert!(std::env::var("IOCORE_ENV_SNAPSHOT_EXAMPLE").is_err());
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub struct EnvSnapshot {
+    vars: BTreeMap<OsString, OsString>,
+}
+impl EnvSnapshot {
+    pub fn capture() -> EnvSnapshot {
+        EnvSnapshot {
+            vars: std::env::vars_os().collect(),
+        }
+    }
+
+    pub fn vars(&self) -> &BTreeMap<OsString, OsString> {
+        &self.vars
+    }
+
+    /// `restore` removes variables set after the snapshot was
+    /// captured and resets the values of the captured ones
+    pub fn restore(&self) {
+        for (key, _) in std::env::vars_os() {
+            if !self.vars.contains_key(&key) {
+                std::env::remove_var(&key);
+            }
+        }
+        for (key, value) in &self.vars {
+            if std::env::var_os(key).as_ref() != Some(value) {
+                std::env::set_var(key, value);
+            }
+        }
+    }
+}
+impl Drop for EnvSnapshot {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// `parse_dotenv` parses the contents of a `.env` file into a list of
+/// variables in order of declaration.
+///
+/// Lines might be prefixed with `export`, values might be single
+/// quoted (literal), double quoted (supporting escapes and spanning
+/// multiple lines) or unquoted in which case trailing ` #` comments
+/// are removed. Unquoted and double quoted values interpolate
+/// `$VAR`, `${VAR}` and `${VAR:-default}` with
+/// [`crate::expand_variables`] from previously declared variables or
+/// else the environment, referencing a variable which is defined in
+/// neither and has no default is an error.
+///
+/// Example
+///
+/// ```
+/// use iocore::env::parse_dotenv;
+/// let vars = parse_dotenv("# comment\nexport HOST=localhost\nURL=\"http://${HOST}:${PORT:-80}\"\n").unwrap();
+/// assert_eq!(vars[1], ("URL".to_string(), "http://localhost:80".to_string()));
+/// ```
+pub fn parse_dotenv(contents: &str) -> Result<Vec<(String, String)>, Error> {
+    parse_dotenv_with_precedence(contents, false)
+}
+
+/// `parse_dotenv_with_precedence` is [`parse_dotenv`] where
+/// `environment_first` makes interpolation prefer the value of
+/// variables already set in the environment over the ones declared
+/// in `contents`, matching what [`load_dotenv`] ends up setting.
+fn parse_dotenv_with_precedence(
+    contents: &str,
+    environment_first: bool,
+) -> Result<Vec<(String, String)>, Error> {
+    let chars = contents.chars().collect::<Vec<char>>();
+    let mut vars = Vec::<(String, String)>::new();
+    let mut index = 0;
+    let mut line = 1;
+    loop {
+        while index < chars.len() && chars[index].is_whitespace() {
+            if chars[index] == '\n' {
+                line += 1;
+            }
+            index += 1;
+        }
+        if index >= chars.len() {
+            break;
+        }
+        if chars[index] == '#' {
+            while index < chars.len() && chars[index] != '\n' {
+                index += 1;
+            }
+            continue;
+        }
+        let declaration_line = line;
+        let mut key = read_dotenv_key(&chars, &mut index);
+        if key == "export" && index < chars.len() && [' ', '\t'].contains(&chars[index]) {
+            skip_dotenv_blanks(&chars, &mut index);
+            key = read_dotenv_key(&chars, &mut index);
+        }
+        skip_dotenv_blanks(&chars, &mut index);
+        if !is_dotenv_key(&key) || index >= chars.len() || chars[index] != '=' {
+            return Err(traceback!(
+                ParseError,
+                "line {}: expected KEY=VALUE, got {:#?}",
+                declaration_line,
+                chars[index.min(chars.len())..]
+                    .iter()
+                    .take_while(|c| **c != '\n')
+                    .collect::<String>()
+            ));
+        }
+        index += 1;
+        skip_dotenv_blanks(&chars, &mut index);
+        let value = match chars.get(index) {
+            Some('\'') => {
+                index += 1;
+                let mut value = String::new();
+                loop {
+                    match chars.get(index) {
+                        Some('\'') => break,
+                        Some(c) => {
+                            if *c == '\n' {
+                                line += 1;
+                            }
+                            value.push(*c);
+                        },
+                        None =>
+                            return Err(traceback!(
+                                ParseError,
+                                "line {}: unterminated single quoted value of {:#?}",
+                                declaration_line,
+                                key
+                            )),
+                    }
+                    index += 1;
+                }
+                index += 1;
+                value
+            },
+            Some('"') => {
+                index += 1;
+                let mut value = String::new();
+                let mut unescaped = String::new();
+                loop {
+                    match chars.get(index) {
+                        Some('"') => break,
+                        Some('\\') => {
+                            value.push_str(&interpolate_dotenv(
+                                &unescaped,
+                                &vars,
+                                environment_first,
+                                declaration_line,
+                                &key,
+                            )?);
+                            unescaped.clear();
+                            index += 1;
+                            match chars.get(index) {
+                                Some('n') => value.push('\n'),
+                                Some('t') => value.push('\t'),
+                                Some('r') => value.push('\r'),
+                                Some(c) => value.push(*c),
+                                None => continue,
+                            }
+                            index += 1;
+                        },
+                        Some(c) => {
+                            if *c == '\n' {
+                                line += 1;
+                            }
+                            unescaped.push(*c);
+                            index += 1;
+                        },
+                        None =>
+                            return Err(traceback!(
+                                ParseError,
+                                "line {}: unterminated double quoted value of {:#?}",
+                                declaration_line,
+                                key
+                            )),
+                    }
+                }
+                index += 1;
+                value.push_str(&interpolate_dotenv(
+                    &unescaped,
+                    &vars,
+                    environment_first,
+                    declaration_line,
+                    &key,
+                )?);
+                value
+            },
+            _ => {
+                let mut raw = Vec::<char>::new();
+                while index < chars.len() && chars[index] != '\n' {
+                    if chars[index] == '#' && raw.last().map(|c| c.is_whitespace()).unwrap_or(true)
+                    {
+                        break;
+                    }
+                    raw.push(chars[index]);
+                    index += 1;
+                }
+                interpolate_dotenv(
+                    raw.iter().collect::<String>().trim_end(),
+                    &vars,
+                    environment_first,
+                    declaration_line,
+                    &key,
+                )?
+            },
+        };
+        skip_dotenv_blanks(&chars, &mut index);
+        match chars.get(index) {
+            None | Some('\n') => {},
+            Some('#') =>
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
+                },
+            Some(_) =>
+                return Err(traceback!(
+                    ParseError,
+                    "line {}: unexpected characters after value of {:#?}",
+                    line,
+                    key
+                )),
+        }
+        vars.push((key, value));
+    }
+    Ok(vars)
+}
+
+/// `load_dotenv` parses the `.env` file at `path` and sets the
+/// variables which are not already set in the environment, returning
+/// every parsed variable.
+///
+/// Since variables which are already set are kept, interpolation
+/// prefers them over the values declared in the file, e.g.: with
+/// `HOST=b` in the environment, `HOST=a` followed by
+/// `URL=http://${HOST}` sets `URL` to `http://b`.
+pub fn load_dotenv(path: impl Into<Path>) -> Result<Vec<(String, String)>, Error> {
+    let vars = parse_dotenv_with_precedence(&Into::<Path>::into(path).read()?, true)?;
+    for (key, value) in &vars {
+        if std::env::var_os(key).is_none() {
+            std::env::set_var(key, value);
+        }
+    }
+    Ok(vars)
+}
+
+/// `load_dotenv_override` parses the `.env` file at `path` and sets
+/// every variable in the environment, overriding existing values.
+pub fn load_dotenv_override(path: impl Into<Path>) -> Result<Vec<(String, String)>, Error> {
+    let vars = parse_dotenv(&Into::<Path>::into(path).read()?)?;
+    for (key, value) in &vars {
+        std::env::set_var(key, value);
+    }
+    Ok(vars)
+}
+
+fn read_dotenv_key(chars: &[char], index: &mut usize) -> String {
+    let mut key = String::new();
+    while *index < chars.len() && !chars[*index].is_whitespace() && chars[*index] != '=' {
+        key.push(chars[*index]);
+        *index += 1;
+    }
+    key
+}
+
+fn is_dotenv_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn skip_dotenv_blanks(chars: &[char], index: &mut usize) {
+    while *index < chars.len() && [' ', '\t'].contains(&chars[*index]) {
+        *index += 1;
+    }
+}
+
+fn interpolate_dotenv(
+    raw: &str,
+    vars: &[(String, String)],
+    environment_first: bool,
+    line: usize,
+    key: &str,
+) -> Result<String, Error> {
+    crate::expand_variables(raw, |name| {
+        let declared = || {
+            vars.iter().rev().find(|(key, _)| key == name).map(|(_, value)| value.to_string())
+        };
+        if environment_first {
+            std::env::var(name).ok().or_else(declared)
+        } else {
+            declared().or_else(|| std::env::var(name).ok())
+        }
+    })
+    .map_err(|error| traceback!(ParseError, "line {}: value of {:#?}: {}", line, key, error))
+}
+
 /// `args_from_string` returns [`Vec<String>`] from split [`String`].
 ///
 /// Example
@@ -29,6 +534,47 @@ pub fn args_from_string(args: impl std::fmt::Display) -> Vec<String> {
         .map(|args| args.to_string())
         .collect::<Vec<String>>()
 }
+
+/// `args_from_string_quoted` is the quote-aware variant of
+/// [`args_from_string`] which splits with POSIX shell quoting rules
+/// via [`crate::sh::split_strict`].
+///
+/// Example
+///
+/// ```
+/// use iocore::args_from_string_quoted;
+/// assert_eq!(
+///     args_from_string_quoted("a 'b c'  --flag=\"d e\" ").unwrap(),
+///     vec!["a", "b c", "--flag=d e"]
+/// );
+/// assert!(args_from_string_quoted("a 'b").is_err());
+/// ```
+pub fn args_from_string_quoted(args: impl std::fmt::Display) -> Result<Vec<String>, Error> {
+    crate::sh::split_strict(args)
+}
+/// `try_env_var` macro calls [`try_var`] or, given a type, [`var_as`]
+/// returning [`Result`] with a structured [`EnvVarError`]
+/// without printing anything.
+///
+/// Example:
+///
+/// ```
+/// use iocore::try_env_var;
+/// std::env::set_var("IOCORE_TRY_ENV_VAR_EXAMPLE", "8080");
+/// assert_eq!(try_env_var!("IOCORE_TRY_ENV_VAR_EXAMPLE").unwrap(), "8080");
+/// assert_eq!(try_env_var!("IOCORE_TRY_ENV_VAR_EXAMPLE", u16).unwrap(), 8080);
+/// assert!(try_env_var!("IOCORE_TRY_ENV_VAR_EXAMPLE_UNSET").is_err());
+/// ```
+#[macro_export]
+macro_rules! try_env_var {
+    ($name:expr) => {{
+        $crate::env::try_var($name)
+    }};
+    ($name:expr, $type:ty) => {{
+        $crate::env::var_as::<$type>($name)
+    }};
+}
+
 /// `env_var` macro calls `iocore::env::var`, returns empty string and prints warning to stderr if the given environment variable is not set.
 ///
 /// Example:
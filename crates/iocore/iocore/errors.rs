@@ -12,7 +12,16 @@ pub enum Error {
     /// `Error::EnvironmentVarError` represents error while obtaining a environment variable
     EnvironmentVarError(String),
     IOCoreException(String),
-    SubprocessError(String),
+    /// `Error::SubprocessError` represents a subprocess which could
+    /// not be spawned or waited for, or which exited with a non-zero
+    /// code or was terminated by a signal, in which case
+    /// [`crate::SubprocessFailure::output`] carries its
+    /// [`crate::CommandOutput`], e.g.: from [`crate::Cmd::check`] and
+    /// [`crate::shell_command_stdout`].
+    SubprocessError(crate::SubprocessFailure),
+    /// `Error::SubprocessTimeout` represents a subprocess terminated
+    /// for exceeding its timeout along with its partial output
+    SubprocessTimeout(crate::SubprocessTimeout),
     SystemError(String),
     ChannelError(String),
     PathConversionError(String),
@@ -26,6 +35,9 @@ pub enum Error {
     ShellCommandError(String),
     ParseError(String),
     PatternMismatch(String),
+    /// `Error::ShutdownRequested` represents an operation stopped
+    /// early because [`crate::shutdown_requested`]
+    ShutdownRequested(String),
 }
 
 impl std::fmt::Display for Error {
@@ -41,6 +53,7 @@ impl std::fmt::Display for Error {
                 write!(f, "PathDeserializationError: {}", error),
             Error::IOCoreException(error) => write!(f, "IOCoreException: {}", error),
             Error::SubprocessError(error) => write!(f, "SubprocessError: {}", error),
+            Error::SubprocessTimeout(error) => write!(f, "SubprocessTimeout: {}", error),
             Error::SystemError(error) => write!(f, "SystemError: {}", error),
             Error::ChannelError(error) => write!(f, "ChannelError: {}", error),
             Error::PathConversionError(error) => write!(f, "PathConversionError: {}", error),
@@ -54,6 +67,7 @@ impl std::fmt::Display for Error {
             Error::ShellCommandError(error) => write!(f, "ShellCommandError: {}", error),
             Error::ParseError(error) => write!(f, "ParseError: {}", error),
             Error::PatternMismatch(error) => write!(f, "PatternMismatch: {}", error),
+            Error::ShutdownRequested(error) => write!(f, "ShutdownRequested: {}", error),
         }
     }
 }
@@ -80,6 +94,11 @@ impl From<std::num::ParseIntError> for Error {
         Error::ParseError(e.to_string())
     }
 }
+impl From<crate::env::EnvVarError> for Error {
+    fn from(e: crate::env::EnvVarError) -> Self {
+        Error::EnvironmentVarError(e.to_string())
+    }
+}
 impl From<regex::Error> for Error {
     fn from(e: regex::Error) -> Self {
         Error::ParseError(e.to_string())
@@ -104,7 +123,9 @@ macro_rules! traceback {
         }
         let name = type_name_of(f);
         let name = name.strip_suffix("::f").unwrap();
-        $crate::Error::$variant(format!("{} [{}:[{}:{}]]\n", $error, name, file!(), line!()))
+        $crate::Error::$variant(
+            format!("{} [{}:[{}:{}]]\n", $error, name, file!(), line!()).into(),
+        )
     }};
     ($variant:ident, $format:literal, $arg:expr  ) => {{
         $crate::traceback!($variant, format!($format, $arg))
@@ -1,3 +1,4 @@
+pub(crate) mod ls_entry;
 pub(crate) mod ls_path_type;
 pub(crate) mod opts;
 pub(crate) mod path_cmp;
@@ -15,7 +16,7 @@ use std::fmt::{Debug, Display};
 use std::fs::File;
 use std::hash::{Hash, Hasher};
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
 use std::path::{PathBuf, MAIN_SEPARATOR_STR};
 use std::process::Stdio;
 use std::str::FromStr;
@@ -32,13 +33,13 @@ use path_utils::{
     add_trailing_separator, expand_home_regex, path_str_to_relative_subpath,
     remove_duplicate_separators, remove_start, remove_trailing_slash, repl_beg,
 };
-use perms::PathPermissions;
+use perms::{PathPermissions, PermissionsMode};
 use sanitation::SString;
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use size::Size;
 
-use crate::{traceback, Error, PathStatus, PathTimestamps, PathType};
+use crate::{traceback, Error, Group, PathStatus, PathTimestamps, PathType, User};
 
 pub const FILENAME_MAX: usize = if cfg!(target_os = "macos") { 255 } else { 1024 };
 pub const USERS_PATH: &'static str = if cfg!(target_os = "macos") { "/Users" } else { "/home" };
@@ -73,13 +74,13 @@ impl Path {
 
     /// `safe` creates a new [`Path`] expanding `~` to the current unix user HOME
     ///
-    /// > NOTE: the current user `HOME` is obtained once at the
-    /// > library initialization and stored in the heap.
+    /// > NOTE: `~` expands to [`crate::home_dir`] according to the
+    /// > current [`crate::HomePolicy`].
     pub fn safe(path: impl std::fmt::Display) -> Result<Path, Error> {
         let path = path.to_string();
         let string = remove_duplicate_separators(path);
         let string = if string.starts_with("~/") {
-            string.replacen("~/", &crate::TILDE.to_string(), 1)
+            string.replacen("~/", &crate::tilde()?, 1)
         } else {
             string.to_string()
         };
@@ -150,7 +151,9 @@ impl Path {
 
     /// `tildify` returns a new [`Path`] where the current unix user HOME is replaced with "~/"
     pub fn tildify(&self) -> Path {
-        let t = crate::TILDE.to_string();
+        let Ok(t) = crate::tilde() else {
+            return self.clone();
+        };
         let s = self.to_string();
         if s.starts_with(&t) {
             Path::raw(s.replacen(&t, &format!("~{}", MAIN_SEPARATOR_STR), 1))
@@ -169,16 +172,10 @@ impl Path {
         }
     }
 
+    /// `kind` returns the [`PathType`] of the calling [`Path`], same
+    /// as [`Path::path_type`], symlinks are not followed.
     pub fn kind(&self) -> PathType {
-        if self.is_file() {
-            PathType::File
-        } else if self.is_dir() {
-            PathType::Directory
-        } else if self.is_symlink() {
-            PathType::Symlink
-        } else {
-            PathType::None
-        }
+        self.path_type()
     }
 
     pub fn inner_string(&self) -> String {
@@ -476,15 +473,35 @@ impl Path {
     }
 
     pub fn set_mode(&mut self, mode: u32) -> Result<Path, Error> {
-        Ok(self.set_permissions(&PathPermissions::from_u32(mode)?)?)
+        self.set_permissions(PathPermissions::from_u32(mode)?)
     }
 
-    pub fn set_permissions(&mut self, permissions: &PathPermissions) -> Result<Path, Error> {
+    /// `set_permissions` sets the permissions of the path according
+    /// to the given [`PermissionsMode`] which is either absolute
+    /// [`PathPermissions`] or a `chmod`-like octal or symbolic mode
+    /// expression.
+    ///
+    /// Example
+    ///
+    /// ```
+    /// use iocore::Path;
+    /// let mut path = Path::tmp().join("set-permissions").write(b"").unwrap();
+    /// path.set_permissions("0644").unwrap();
+    /// path.set_permissions("u+x,go-r").unwrap();
+    /// assert_eq!(path.permissions().to_string_octal(), "700");
+    /// path.delete().unwrap();
+    /// ```
+    pub fn set_permissions(
+        &mut self,
+        permissions: impl Into<PermissionsMode>,
+    ) -> Result<Path, Error> {
+        let mode = Into::<PermissionsMode>::into(permissions);
         let info = std::fs::metadata(self.path()).map_err(|error| {
             traceback!(FileSystemError, "obtaining metadata of {:#?}: {}", self.to_string(), error)
         })?;
+        let permissions = mode.resolve(&PathPermissions::from_u32(info.mode())?, info.is_dir())?;
         let mut info_permissions = info.permissions();
-        info_permissions.set_mode((*permissions).into());
+        info_permissions.set_mode(permissions.into());
         std::fs::set_permissions(self.path(), info_permissions).map_err(|error| {
             traceback!(
                 FileSystemError,
@@ -497,6 +514,49 @@ impl Path {
         Ok(self.clone())
     }
 
+    /// `set_permissions_recursive` applies the given
+    /// [`PermissionsMode`] to the path and, in the case of
+    /// directories, to every path within it, symlinks are neither
+    /// modified nor followed.
+    ///
+    /// As `chmod -R` does, a directory is modified before its
+    /// contents when the resulting mode lets its owner read and
+    /// search it, e.g.: `"u+rwX"` on a directory with mode `000`, and
+    /// after its contents otherwise, such that removing permissions
+    /// does not prevent reaching them.
+    ///
+    /// Symbolic modes are resolved against the current permissions
+    /// of each path such that, for instance, `"a+rX"` only grants
+    /// execute permission to directories and to files which are
+    /// already executable.
+    pub fn set_permissions_recursive(
+        &mut self,
+        permissions: impl Into<PermissionsMode>,
+    ) -> Result<Path, Error> {
+        let mode = Into::<PermissionsMode>::into(permissions);
+        if !self.is_dir() {
+            return self.set_permissions(mode);
+        }
+        let info = std::fs::metadata(self.path()).map_err(|error| {
+            traceback!(FileSystemError, "obtaining metadata of {:#?}: {}", self.to_string(), error)
+        })?;
+        let resolved: u32 = mode.resolve(&PathPermissions::from_u32(info.mode())?, true)?.into();
+        let change_first = resolved & 0o500 == 0o500;
+        if change_first {
+            self.set_permissions(mode.clone())?;
+        }
+        for mut path in self.list()? {
+            if path.is_symlink() {
+                continue;
+            }
+            path.set_permissions_recursive(mode.clone())?;
+        }
+        if !change_first {
+            self.set_permissions(mode)?;
+        }
+        Ok(self.clone())
+    }
+
     pub fn timestamps(&self) -> Result<PathTimestamps, Error> {
         let metadata = self.path_metadata().map_err(|error| {
             traceback!(
@@ -532,7 +592,7 @@ impl Path {
     }
 
     pub fn is_symlink(&self) -> bool {
-        match self.meta() {
+        match self.symlink_meta() {
             Ok(meta) => meta.is_symlink(),
             Err(_) => false,
         }
@@ -626,17 +686,49 @@ impl Path {
     /// `expand` checks whether the given path starts with "~/" or is
     /// exactly equal to "~" then expands to [`User::home`]
     /// accordingly.
+    ///
+    /// The path is returned unexpanded when the home directory cannot
+    /// be resolved, [`Path::expand_full`] returns the error instead.
     pub fn expand(&self) -> Path {
         if self.to_string().starts_with("~") {
-            Path::raw(expand_home_regex(&self.to_string(), &crate::TILDE.to_string()))
+            match crate::tilde() {
+                Ok(tilde) => Path::raw(expand_home_regex(&self.to_string(), &tilde)),
+                Err(_) => self.clone(),
+            }
         } else {
             self.clone()
         }
     }
 
+    /// `expand_full` expands a leading `~` or `~user` as well as
+    /// `$VAR`, `${VAR}` and `${VAR:-default}` references to
+    /// environment variables, failing on undefined variables.
+    ///
+    /// Example
+    ///
+    /// ```
+    /// use iocore::Path;
+    /// std::env::set_var("IOCORE_EXPAND_FULL_EXAMPLE", "/var/cache");
+    /// let path = Path::raw("$IOCORE_EXPAND_FULL_EXAMPLE/${APP_NAME:-app}").expand_full().unwrap();
+    /// assert_eq!(path.to_string(), "/var/cache/app");
+    /// assert!(Path::raw("${IOCORE_EXPAND_FULL_UNDEFINED}/app").expand_full().is_err());
+    /// ```
+    pub fn expand_full(&self) -> Result<Path, Error> {
+        self.expand_full_with(|name| crate::env::try_var(name).ok())
+    }
+
+    /// `expand_full_with` is the counterpart of [`Path::expand_full`]
+    /// which looks up variables through `var`
+    pub fn expand_full_with(&self, var: impl Fn(&str) -> Option<String>) -> Result<Path, Error> {
+        Ok(Path::raw(path_utils::expand_variables(
+            &path_utils::expand_user_home(&self.to_string())?,
+            var,
+        )?))
+    }
+
     pub fn absolute(&self) -> Result<Path, Error> {
         let name = self.name();
-        if self.kind() == PathType::Symlink {
+        if self.is_symlink() && !self.exists() {
             if let Some(ancestor) = self.parent() {
                 Ok(ancestor.try_canonicalize().join(name))
             } else {
@@ -683,7 +775,7 @@ impl Path {
     }
 
     pub fn read_symlink(&self) -> Result<Path, Error> {
-        if self.kind() != PathType::Symlink {
+        if !self.is_symlink() {
             return Err(traceback!(FileSystemError, "not a symlink: {:#?}", self.to_string()));
         }
         Ok(std::fs::read_link(self)
@@ -716,6 +808,65 @@ impl Path {
         Ok(to)
     }
 
+    /// `hard_link_to` creates a hard link at `to` pointing to the
+    /// same file as the calling [`Path`]
+    pub fn hard_link_to(&self, to: impl Into<Path>) -> Result<Path, Error> {
+        let to = to.into();
+        if to.exists() {
+            return Err(traceback!(
+                FileSystemError,
+                "creating hard link from {:#?} to {:#?}: destination exists",
+                self.to_string(),
+                to.to_string()
+            ));
+        }
+        std::fs::hard_link(self.path(), to.path()).map_err(|e| {
+            traceback!(
+                FileSystemError,
+                "creating hard link from {:#?} to {:#?}: {}",
+                self.to_string(),
+                to.to_string(),
+                e
+            )
+        })?;
+        Ok(to)
+    }
+
+    /// `inode` returns the inode number of the file referenced by the calling [`Path`]
+    pub fn inode(&self) -> Result<u64, Error> {
+        Ok(self.meta()?.ino())
+    }
+
+    /// `device` returns the ID of the device containing the file referenced by the calling [`Path`]
+    pub fn device(&self) -> Result<u64, Error> {
+        Ok(self.meta()?.dev())
+    }
+
+    /// `nlink` returns the number of hard links to the file referenced by the calling [`Path`]
+    pub fn nlink(&self) -> Result<u64, Error> {
+        Ok(self.meta()?.nlink())
+    }
+
+    /// `same_file_as` returns true if both paths refer to the same
+    /// file, that is, if both have the same device and inode numbers.
+    ///
+    /// Example
+    /// ```
+    /// {
+    /// use iocore::Path;
+    /// let path = Path::tmp().join("file").write(b"data").unwrap();
+    /// let link = path.hard_link_to(path.with_filename("hard-link")).unwrap();
+    /// assert!(path.same_file_as(&link));
+    /// assert!(!path.same_file_as(&Path::tmp_file()));
+    /// }
+    /// ```
+    pub fn same_file_as(&self, other: &Path) -> bool {
+        match (self.meta(), other.meta()) {
+            (Ok(this), Ok(other)) => (this.dev(), this.ino()) == (other.dev(), other.ino()),
+            _ => false,
+        }
+    }
+
     pub fn name(&self) -> String {
         match self.path().file_name() {
             Some(ext) => SString::new(ext.as_encoded_bytes()).unchecked_safe(),
@@ -897,6 +1048,17 @@ impl Path {
         std::fs::Permissions::from_mode(self.mode())
     }
 
+    /// `ls_entry` returns the [`ls_entry::LsEntry`] of the path
+    pub fn ls_entry(&self) -> Result<ls_entry::LsEntry, Error> {
+        ls_entry::LsEntry::from_path(self)
+    }
+
+    /// `long_listing` returns the `ls -l` line of the path, e.g.:
+    /// `"drwxr-xr-x 2 user group 4096 Oct 17 02:28 name"`
+    pub fn long_listing(&self) -> Result<String, Error> {
+        Ok(self.ls_entry()?.to_string())
+    }
+
     pub fn status(&self) -> PathStatus {
         let permissions = self.fs_permissions();
         let readonly = permissions.readonly();
@@ -926,6 +1088,30 @@ impl Path {
                 } else {
                     PathStatus::WritableSetuid
                 },
+            PathType::Fifo =>
+                if readonly {
+                    PathStatus::ReadOnlyFifo
+                } else {
+                    PathStatus::WritableFifo
+                },
+            PathType::Socket =>
+                if readonly {
+                    PathStatus::ReadOnlySocket
+                } else {
+                    PathStatus::WritableSocket
+                },
+            PathType::BlockDevice =>
+                if readonly {
+                    PathStatus::ReadOnlyBlockDevice
+                } else {
+                    PathStatus::WritableBlockDevice
+                },
+            PathType::CharDevice =>
+                if readonly {
+                    PathStatus::ReadOnlyCharDevice
+                } else {
+                    PathStatus::WritableCharDevice
+                },
             PathType::None => PathStatus::None,
         }
     }
@@ -942,28 +1128,184 @@ impl Path {
         Ok(metadata)
     }
 
+    /// `symlink_meta` returns the [`std::fs::Metadata`] of the
+    /// calling [`Path`] without following symlinks
+    pub fn symlink_meta(&self) -> Result<std::fs::Metadata, Error> {
+        std::fs::symlink_metadata(self.path()).map_err(|error| {
+            traceback!(
+                FileSystemError,
+                "obtaining std::fs::Metadata of symlink {:#?}: {}",
+                self.to_string(),
+                error
+            )
+        })
+    }
+
+    /// `path_type` returns the [`PathType`] of the calling [`Path`]
+    /// without following symlinks. Regular files with the setuid bit
+    /// set are reported as [`PathType::Setuid`].
     pub fn path_type(&self) -> PathType {
-        match self.meta() {
+        match self.symlink_meta() {
             Err(_) => PathType::None,
-            Ok(meta) =>
-                if meta.is_file() {
-                    PathType::File
-                } else if meta.is_dir() {
-                    PathType::Directory
-                } else if meta.is_symlink() {
+            Ok(meta) => {
+                let file_type = meta.file_type();
+                if file_type.is_symlink() {
                     PathType::Symlink
+                } else if file_type.is_dir() {
+                    PathType::Directory
+                } else if file_type.is_file() {
+                    if meta.mode() & path_type::S_ISUID != 0 {
+                        PathType::Setuid
+                    } else {
+                        PathType::File
+                    }
+                } else if file_type.is_fifo() {
+                    PathType::Fifo
+                } else if file_type.is_socket() {
+                    PathType::Socket
+                } else if file_type.is_block_device() {
+                    PathType::BlockDevice
+                } else if file_type.is_char_device() {
+                    PathType::CharDevice
                 } else {
                     PathType::None
-                },
+                }
+            },
+        }
+    }
+
+    /// `is_setuid` returns true if the set-user-ID bit of the calling [`Path`] is set
+    pub fn is_setuid(&self) -> bool {
+        self.has_mode_bits(path_type::S_ISUID)
+    }
+
+    /// `is_setgid` returns true if the set-group-ID bit of the calling [`Path`] is set
+    pub fn is_setgid(&self) -> bool {
+        self.has_mode_bits(path_type::S_ISGID)
+    }
+
+    /// `is_sticky` returns true if the sticky bit of the calling [`Path`] is set
+    pub fn is_sticky(&self) -> bool {
+        self.has_mode_bits(path_type::S_ISVTX)
+    }
+
+    fn has_mode_bits(&self, bits: u32) -> bool {
+        match self.meta() {
+            Ok(meta) => meta.mode() & bits == bits,
+            Err(_) => false,
         }
     }
 
-    pub fn uid(&self) -> u32 {
-        self.meta().expect("Path::uid").uid()
+    /// `uid` returns the user id of the owner of the path
+    pub fn uid(&self) -> Result<u32, Error> {
+        Ok(self.meta()?.uid())
+    }
+
+    /// `gid` returns the group id of the group owning the path
+    pub fn gid(&self) -> Result<u32, Error> {
+        Ok(self.meta()?.gid())
+    }
+
+    /// `owner` returns the [`User`] which owns the path, the user
+    /// name falls back to the uid when not found in the user database
+    pub fn owner(&self) -> Result<User, Error> {
+        let uid = self.meta()?.uid();
+        Ok(User::by_uid(uid).unwrap_or_else(|_| User {
+            uid,
+            gid: None,
+            name: uid.to_string(),
+            group: None,
+            groups: Vec::new(),
+            gecos: None,
+            home_dir: None,
+            shell: None,
+        }))
+    }
+
+    /// `group` returns the [`Group`] which owns the path, the group
+    /// name falls back to the gid when not found in the group database
+    pub fn group(&self) -> Result<Group, Error> {
+        let gid = self.meta()?.gid();
+        Ok(Group::by_gid(gid).unwrap_or_else(|_| Group {
+            gid,
+            name: gid.to_string(),
+        }))
+    }
+
+    /// `chown` changes the owner and/or group of the path, `None`
+    /// leaves the respective id unchanged. Symlinks are followed,
+    /// see [`Path::lchown`].
+    ///
+    /// Example
+    ///
+    /// ```
+    /// use iocore::Path;
+    /// let path = Path::tmp().join("chown").write(b"").unwrap();
+    /// path.chown(Some(path.uid().unwrap()), None).unwrap();
+    /// path.delete().unwrap();
+    /// ```
+    pub fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> Result<Path, Error> {
+        std::os::unix::fs::chown(self.path(), uid, gid).map_err(|error| {
+            traceback!(
+                FileSystemError,
+                "changing ownership of {:#?} to {}:{}: {}",
+                self.to_string(),
+                uid.map(|uid| uid.to_string()).unwrap_or_default(),
+                gid.map(|gid| gid.to_string()).unwrap_or_default(),
+                error
+            )
+        })?;
+        Ok(self.clone())
+    }
+
+    /// `lchown` changes the owner and/or group of the path itself
+    /// rather than of the path it points to in case of symlinks
+    pub fn lchown(&self, uid: Option<u32>, gid: Option<u32>) -> Result<Path, Error> {
+        std::os::unix::fs::lchown(self.path(), uid, gid).map_err(|error| {
+            traceback!(
+                FileSystemError,
+                "changing ownership of symlink {:#?} to {}:{}: {}",
+                self.to_string(),
+                uid.map(|uid| uid.to_string()).unwrap_or_default(),
+                gid.map(|gid| gid.to_string()).unwrap_or_default(),
+                error
+            )
+        })?;
+        Ok(self.clone())
+    }
+
+    /// `chown_by_name` changes the owner and/or group of the path
+    /// resolving the given user and group names into ids
+    pub fn chown_by_name(&self, user: Option<&str>, group: Option<&str>) -> Result<Path, Error> {
+        let (uid, gid) = resolve_owner_names(user, group)?;
+        self.chown(uid, gid)
+    }
+
+    /// `chown_recursive` changes the owner and/or group of the path
+    /// and, in the case of directories, of every path within it.
+    /// Symlinks within are changed themselves rather than followed,
+    /// symlinked directories are not descended into.
+    pub fn chown_recursive(&self, uid: Option<u32>, gid: Option<u32>) -> Result<Path, Error> {
+        self.chown(uid, gid)?;
+        if self.is_dir() {
+            for path in crate::walk_dir(self.clone(), NoFollowHandler, None)? {
+                if path.to_string() != self.to_string() {
+                    path.lchown(uid, gid)?;
+                }
+            }
+        }
+        Ok(self.clone())
     }
 
-    pub fn gid(&self) -> u32 {
-        self.meta().expect("Path::gid").gid()
+    /// `chown_by_name_recursive` is the counterpart of
+    /// [`Path::chown_recursive`] which resolves user and group names
+    pub fn chown_by_name_recursive(
+        &self,
+        user: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<Path, Error> {
+        let (uid, gid) = resolve_owner_names(user, group)?;
+        self.chown_recursive(uid, gid)
     }
 
     pub fn mkdir_parents(&self) -> Result<Path, Error> {
@@ -1113,6 +1455,42 @@ impl Path {
     }
 }
 
+/// `NoFollowHandler` walks a directory tree without descending into
+/// symlinked directories, such that recursive modifications stay
+/// within the tree, and aggregates dangling symlinks as well.
+#[derive(Clone)]
+struct NoFollowHandler;
+impl crate::WalkProgressHandler for NoFollowHandler {
+    fn path_matching(&mut self, path: &Path) -> Result<bool, Error> {
+        Ok(path.symlink_meta().is_ok())
+    }
+
+    fn should_scan_directory(&mut self, path: &Path) -> Result<bool, Error> {
+        Ok(path.symlink_meta().is_ok_and(|meta| meta.is_dir() && !meta.is_symlink()))
+    }
+}
+
+fn resolve_owner_names(
+    user: Option<&str>,
+    group: Option<&str>,
+) -> Result<(Option<u32>, Option<u32>), Error> {
+    let uid = match user {
+        Some(user) => Some(
+            crate::sys::unix_user_id(user)
+                .ok_or_else(|| traceback!(SystemError, "user not found: {:#?}", user))?,
+        ),
+        None => None,
+    };
+    let gid = match group {
+        Some(group) => Some(
+            crate::sys::unix_group_id(group)
+                .ok_or_else(|| traceback!(SystemError, "group not found: {:#?}", group))?,
+        ),
+        None => None,
+    };
+    Ok((uid, gid))
+}
+
 impl PartialEq for Path {
     fn eq(&self, other: &Self) -> bool {
         self.exists() == other.exists()
@@ -0,0 +1,325 @@
+use std::os::unix::fs::MetadataExt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fs::ls_path_type::LsPathType;
+use crate::fs::path_datetime::PathDateTime;
+use crate::fs::path_type::{self, PathType};
+use crate::fs::perms::PathPermissions;
+use crate::fs::size::{ByteUnit, Size};
+use crate::sys::{unix_group_name, unix_user_name};
+use crate::{traceback, Error, Path};
+
+/// `LsSort` determines the order in which [`LsEntry::render`] lists
+/// entries, `Size` and `Modified` list the largest and most recently
+/// modified entries first respectively, as `ls -S` and `ls -t` do.
+#[derive(
+    Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+pub enum LsSort {
+    #[default]
+    Name,
+    Size,
+    Modified,
+    None,
+}
+
+/// `LsOptions` holds the options of [`LsEntry::render`] and
+/// [`LsEntry::to_line`]
+#[derive(
+    Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+pub struct LsOptions {
+    pub human_readable: bool,
+    pub sort: LsSort,
+    pub reverse: bool,
+}
+impl LsOptions {
+    pub fn human_readable(mut self, human_readable: bool) -> LsOptions {
+        self.human_readable = human_readable;
+        self
+    }
+
+    pub fn sort(mut self, sort: LsSort) -> LsOptions {
+        self.sort = sort;
+        self
+    }
+
+    pub fn reverse(mut self, reverse: bool) -> LsOptions {
+        self.reverse = reverse;
+        self
+    }
+}
+
+/// `LsEntry` gathers the metadata of a path which `ls -l` displays,
+/// symlinks are not followed.
+///
+/// Example
+///
+/// ```
+/// use iocore::{LsEntry, Path};
+/// let path = Path::tmp().join("ls-entry").write(b"ls").unwrap();
+/// let entry = LsEntry::from_path(&path).unwrap();
+/// assert_eq!(entry.size, 2);
+/// assert!(entry.to_string().ends_with(" ls-entry"));
+/// path.delete().unwrap();
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct LsEntry {
+    pub path: Path,
+    pub name: String,
+    pub path_type: LsPathType,
+    pub mode: u32,
+    pub nlink: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub owner: String,
+    pub group: String,
+    pub size: u64,
+    pub modified: PathDateTime,
+    pub target: Option<Path>,
+}
+impl LsEntry {
+    pub fn from_path(path: &Path) -> Result<LsEntry, Error> {
+        let metadata = path.symlink_meta()?;
+        let modified = metadata.modified().map_err(|error| {
+            traceback!(
+                FileSystemError,
+                "obtaining modification time of {:#?}: {}",
+                path.to_string(),
+                error
+            )
+        })?;
+        let path_type = path.path_type();
+        let target = match path_type {
+            PathType::Symlink => Some(path.read_symlink()?),
+            _ => None,
+        };
+        let name = match path.name() {
+            name if name.is_empty() => path.to_string(),
+            name => name,
+        };
+        Ok(LsEntry {
+            path: path.clone(),
+            name,
+            path_type: LsPathType::from(path_type),
+            mode: metadata.mode() & 0o7777,
+            nlink: metadata.nlink(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            owner: unix_user_name(metadata.uid()).unwrap_or_else(|| metadata.uid().to_string()),
+            group: unix_group_name(metadata.gid()).unwrap_or_else(|| metadata.gid().to_string()),
+            size: metadata.size(),
+            modified: PathDateTime::from(modified),
+            target,
+        })
+    }
+
+    pub fn permissions(&self) -> PathPermissions {
+        PathPermissions::from_u32(self.mode).unwrap_or_default()
+    }
+
+    /// `mode_string` returns the file type and permissions in the
+    /// fashion of `ls -l`, e.g.: `"drwxr-xr-x"` or `"-rwsr-xr-x"`.
+    pub fn mode_string(&self) -> String {
+        let mut mode = String::from(Into::<char>::into(self.path_type));
+        for (shift, special, special_char) in [
+            (6, path_type::S_ISUID, 's'),
+            (3, path_type::S_ISGID, 's'),
+            (0, path_type::S_ISVTX, 't'),
+        ] {
+            let triad = (self.mode >> shift) & 0o7;
+            mode.push(if triad & 0o4 != 0 { 'r' } else { '-' });
+            mode.push(if triad & 0o2 != 0 { 'w' } else { '-' });
+            mode.push(match (triad & 0o1 != 0, self.mode & special != 0) {
+                (true, true) => special_char,
+                (false, true) => special_char.to_ascii_uppercase(),
+                (true, false) => 'x',
+                (false, false) => '-',
+            });
+        }
+        mode
+    }
+
+    /// `size_string` returns the size in bytes or, when
+    /// `human_readable` is true, rounded up to one decimal place in
+    /// the fashion of `ls -lh`, e.g.: `"4.0K"` or `"12M"`.
+    pub fn size_string(&self, human_readable: bool) -> String {
+        if !human_readable || self.size < 1024 {
+            return self.size.to_string();
+        }
+        let units = ByteUnit::variants();
+        let size = Size::from(self.size);
+        let mut index = units
+            .iter()
+            .rposition(|unit| unit.as_u64() <= size.as_u64())
+            .unwrap_or_default();
+        loop {
+            let unit = units[index];
+            let scaled = size.as_u64() as f64 / unit.as_u64() as f64;
+            let tenths = (scaled * 10.0).ceil() / 10.0;
+            let rounded = if tenths < 10.0 { tenths } else { scaled.ceil() };
+            if rounded >= 1024.0 && index + 1 < units.len() {
+                index += 1;
+                continue;
+            }
+            let symbol = &unit.as_str()[..1];
+            return if rounded < 10.0 {
+                format!("{:.1}{}", rounded, symbol)
+            } else {
+                format!("{}{}", rounded as u64, symbol)
+            };
+        }
+    }
+
+    /// `to_line` returns the `ls -l` line of the entry without
+    /// column alignment
+    pub fn to_line(&self, options: &LsOptions) -> String {
+        self.columns(options).join(" ")
+    }
+
+    fn columns(&self, options: &LsOptions) -> [String; 7] {
+        [
+            self.mode_string(),
+            self.nlink.to_string(),
+            self.owner.to_string(),
+            self.group.to_string(),
+            self.size_string(options.human_readable),
+            self.modified.human_friendly(None),
+            match &self.target {
+                Some(target) => format!("{} -> {}", self.name, target),
+                None => self.name.to_string(),
+            },
+        ]
+    }
+
+    /// `sort` sorts the given entries according to `options`
+    pub fn sort(entries: &mut [LsEntry], options: &LsOptions) {
+        match options.sort {
+            LsSort::Name => entries.sort_by(|a, b| a.name.cmp(&b.name)),
+            LsSort::Size =>
+                entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name))),
+            LsSort::Modified => entries
+                .sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| a.name.cmp(&b.name))),
+            LsSort::None => {},
+        }
+        if options.reverse {
+            entries.reverse();
+        }
+    }
+
+    /// `render` sorts the given entries according to `options` and
+    /// returns their `ls -l` lines with aligned columns
+    pub fn render(entries: &[LsEntry], options: &LsOptions) -> Vec<String> {
+        let mut entries = entries.to_vec();
+        LsEntry::sort(&mut entries, options);
+        let rows = entries.iter().map(|entry| entry.columns(options)).collect::<Vec<_>>();
+        let mut widths = [0usize; 7];
+        for row in &rows {
+            for (width, column) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(column.chars().count());
+            }
+        }
+        rows.iter()
+            .map(|row| {
+                format!(
+                    "{} {:>nlink$} {:<owner$} {:<group$} {:>size$} {} {}",
+                    row[0],
+                    row[1],
+                    row[2],
+                    row[3],
+                    row[4],
+                    row[5],
+                    row[6],
+                    nlink = widths[1],
+                    owner = widths[2],
+                    group = widths[3],
+                    size = widths[4],
+                )
+            })
+            .collect()
+    }
+}
+impl std::fmt::Display for LsEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_line(&LsOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LsEntry, LsOptions, LsPathType, LsSort, Path, PathDateTime};
+
+    fn entry(name: &str, path_type: LsPathType, mode: u32, size: u64) -> LsEntry {
+        LsEntry {
+            path: Path::raw(name),
+            name: name.to_string(),
+            path_type,
+            mode,
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            owner: "root".to_string(),
+            group: "wheel".to_string(),
+            size,
+            modified: PathDateTime::from_timestamp(1_000_000_000, 0),
+            target: None,
+        }
+    }
+
+    #[test]
+    fn test_ls_entry_mode_string() {
+        assert_eq!(entry("d", LsPathType::Directory, 0o755, 0).mode_string(), "drwxr-xr-x");
+        assert_eq!(entry("f", LsPathType::File, 0o640, 0).mode_string(), "-rw-r-----");
+        assert_eq!(entry("s", LsPathType::Setuid, 0o4755, 0).mode_string(), "-rwsr-xr-x");
+        assert_eq!(entry("g", LsPathType::File, 0o2644, 0).mode_string(), "-rw-r-Sr--");
+        assert_eq!(entry("t", LsPathType::Directory, 0o1777, 0).mode_string(), "drwxrwxrwt");
+        assert_eq!(entry("T", LsPathType::Directory, 0o1776, 0).mode_string(), "drwxrwxrwT");
+        assert_eq!(entry("l", LsPathType::Symlink, 0o777, 0).mode_string(), "lrwxrwxrwx");
+    }
+
+    #[test]
+    fn test_ls_entry_size_string() {
+        assert_eq!(entry("f", LsPathType::File, 0o644, 1023).size_string(true), "1023");
+        assert_eq!(entry("f", LsPathType::File, 0o644, 4096).size_string(true), "4.0K");
+        assert_eq!(entry("f", LsPathType::File, 0o644, 4097).size_string(true), "4.1K");
+        assert_eq!(entry("f", LsPathType::File, 0o644, 4097).size_string(false), "4097");
+        assert_eq!(entry("f", LsPathType::File, 0o644, 12 * 1024 * 1024).size_string(true), "12M");
+    }
+
+    #[test]
+    fn test_ls_entry_size_string_unit_boundaries() {
+        assert_eq!(entry("f", LsPathType::File, 0o644, 1024).size_string(true), "1.0K");
+        assert_eq!(entry("f", LsPathType::File, 0o644, 10239).size_string(true), "10K");
+        assert_eq!(entry("f", LsPathType::File, 0o644, 1048575).size_string(true), "1.0M");
+        assert_eq!(entry("f", LsPathType::File, 0o644, 1048576).size_string(true), "1.0M");
+        assert_eq!(entry("f", LsPathType::File, 0o644, 1073741823).size_string(true), "1.0G");
+    }
+
+    #[test]
+    fn test_ls_entry_render() {
+        let entries = vec![
+            entry("small", LsPathType::File, 0o644, 1),
+            entry("large", LsPathType::File, 0o644, 4096),
+        ];
+        let date = entries[0].modified.human_friendly(None);
+        assert_eq!(
+            LsEntry::render(&entries, &LsOptions::default()),
+            vec![
+                format!("-rw-r--r-- 1 root wheel 4096 {} large", date),
+                format!("-rw-r--r-- 1 root wheel    1 {} small", date),
+            ]
+        );
+        assert_eq!(
+            LsEntry::render(&entries, &LsOptions::default().human_readable(true).reverse(true)),
+            vec![
+                format!("-rw-r--r-- 1 root wheel    1 {} small", date),
+                format!("-rw-r--r-- 1 root wheel 4.0K {} large", date),
+            ]
+        );
+        assert_eq!(
+            LsEntry::render(&entries, &LsOptions::default().sort(LsSort::None))[0],
+            format!("-rw-r--r-- 1 root wheel    1 {} small", date),
+        );
+    }
+}
@@ -8,6 +8,10 @@ pub enum LsPathType {
     Symlink,
     Setuid,
     Directory,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
     None,
 }
 
@@ -18,6 +22,10 @@ impl Into<PathType> for LsPathType {
             Self::Symlink => PathType::Symlink,
             Self::Setuid => PathType::Setuid,
             Self::Directory => PathType::Directory,
+            Self::Fifo => PathType::Fifo,
+            Self::Socket => PathType::Socket,
+            Self::BlockDevice => PathType::BlockDevice,
+            Self::CharDevice => PathType::CharDevice,
             Self::None => PathType::None,
         }
     }
@@ -29,17 +37,28 @@ impl From<PathType> for LsPathType {
             PathType::Symlink => Self::Symlink,
             PathType::Setuid => Self::Setuid,
             PathType::Directory => Self::Directory,
+            PathType::Fifo => Self::Fifo,
+            PathType::Socket => Self::Socket,
+            PathType::BlockDevice => Self::BlockDevice,
+            PathType::CharDevice => Self::CharDevice,
             PathType::None => Self::None,
         }
     }
 }
 impl LsPathType {
+    /// `into_char` returns the character which `ls -l` uses to
+    /// represent the file type, setuid files are regular files
+    /// according to `ls`.
     fn into_char(self) -> char {
         match self {
             Self::File => '-',
             Self::Symlink => 'l',
-            Self::Setuid => 's',
+            Self::Setuid => '-',
             Self::Directory => 'd',
+            Self::Fifo => 'p',
+            Self::Socket => 's',
+            Self::BlockDevice => 'b',
+            Self::CharDevice => 'c',
             Self::None => '?',
         }
     }
@@ -65,22 +84,34 @@ mod tests {
         assert_eq!(LsPathType::from(PathType::Symlink), LsPathType::Symlink);
         assert_eq!(LsPathType::from(PathType::Setuid), LsPathType::Setuid);
         assert_eq!(LsPathType::from(PathType::Directory), LsPathType::Directory);
+        assert_eq!(LsPathType::from(PathType::Fifo), LsPathType::Fifo);
+        assert_eq!(LsPathType::from(PathType::Socket), LsPathType::Socket);
+        assert_eq!(LsPathType::from(PathType::BlockDevice), LsPathType::BlockDevice);
+        assert_eq!(LsPathType::from(PathType::CharDevice), LsPathType::CharDevice);
         assert_eq!(LsPathType::from(PathType::None), LsPathType::None);
     }
     #[test]
     fn test_ls_path_type_into_char() {
         assert_eq!(LsPathType::File.into_char(), '-');
         assert_eq!(LsPathType::Symlink.into_char(), 'l');
-        assert_eq!(LsPathType::Setuid.into_char(), 's');
+        assert_eq!(LsPathType::Setuid.into_char(), '-');
         assert_eq!(LsPathType::Directory.into_char(), 'd');
+        assert_eq!(LsPathType::Fifo.into_char(), 'p');
+        assert_eq!(LsPathType::Socket.into_char(), 's');
+        assert_eq!(LsPathType::BlockDevice.into_char(), 'b');
+        assert_eq!(LsPathType::CharDevice.into_char(), 'c');
         assert_eq!(LsPathType::None.into_char(), '?');
     }
     #[test]
     fn test_ls_path_type_to_string() {
         assert_eq!(LsPathType::File.to_string(), "-");
         assert_eq!(LsPathType::Symlink.to_string(), "l");
-        assert_eq!(LsPathType::Setuid.to_string(), "s");
+        assert_eq!(LsPathType::Setuid.to_string(), "-");
         assert_eq!(LsPathType::Directory.to_string(), "d");
+        assert_eq!(LsPathType::Fifo.to_string(), "p");
+        assert_eq!(LsPathType::Socket.to_string(), "s");
+        assert_eq!(LsPathType::BlockDevice.to_string(), "b");
+        assert_eq!(LsPathType::CharDevice.to_string(), "c");
         assert_eq!(LsPathType::None.to_string(), "?");
     }
 }
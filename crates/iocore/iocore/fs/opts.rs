@@ -9,6 +9,7 @@ pub struct OpenOptions {
     f_write: bool,
     f_create: bool,
     f_append: bool,
+    f_truncate: bool,
     f_mode: u32,
 }
 impl Display for OpenOptions {
@@ -26,6 +27,9 @@ impl Display for OpenOptions {
         if self.f_append {
             opts.push(String::from("append"));
         }
+        if self.f_truncate {
+            opts.push(String::from("truncate"));
+        }
         if self.f_mode > 0o0000 {
             opts.push(format!("mode:{:40o}", self.f_mode));
         }
@@ -38,6 +42,7 @@ impl OpenOptions {
             f_read: bool::default(),
             f_create: bool::default(),
             f_append: bool::default(),
+            f_truncate: bool::default(),
             f_write: bool::default(),
             f_mode: 0o0600,
         }
@@ -58,6 +63,11 @@ impl OpenOptions {
         self
     }
 
+    pub fn truncate(&mut self, truncate: bool) -> &mut OpenOptions {
+        self.f_truncate = truncate;
+        self
+    }
+
     pub fn write(&mut self, write: bool) -> &mut OpenOptions {
         self.f_write = write;
         self
@@ -76,6 +86,7 @@ impl OpenOptions {
                 .read(self.f_read)
                 .write(self.f_write)
                 .append(self.f_append)
+                .truncate(self.f_truncate)
                 .mode(self.f_mode)
                 .open(&path)
         } else {
@@ -84,6 +95,7 @@ impl OpenOptions {
                 .read(self.f_read)
                 .write(self.f_write)
                 .append(self.f_append)
+                .truncate(self.f_truncate)
                 .open(&path)
         }
         .map_err(|e| {
@@ -10,10 +10,18 @@ pub enum PathStatus {
     ReadOnlyFile,
     ReadOnlySetuid,
     ReadOnlySymlink,
+    ReadOnlyFifo,
+    ReadOnlySocket,
+    ReadOnlyBlockDevice,
+    ReadOnlyCharDevice,
     WritableDirectory,
     WritableFile,
     WritableSetuid,
     WritableSymlink,
+    WritableFifo,
+    WritableSocket,
+    WritableBlockDevice,
+    WritableCharDevice,
 }
 
 impl PathStatus {
@@ -23,11 +31,19 @@ impl PathStatus {
             Self::ReadOnlyFile => "read-only file",
             Self::ReadOnlySetuid => "read-only setuid",
             Self::ReadOnlySymlink => "read-only symlink",
+            Self::ReadOnlyFifo => "read-only fifo",
+            Self::ReadOnlySocket => "read-only socket",
+            Self::ReadOnlyBlockDevice => "read-only block device",
+            Self::ReadOnlyCharDevice => "read-only character device",
             Self::WritableFile => "writable file",
             Self::None => "none",
             Self::WritableDirectory => "writable directory",
             Self::WritableSetuid => "writable setuid",
             Self::WritableSymlink => "writable symlink",
+            Self::WritableFifo => "writable fifo",
+            Self::WritableSocket => "writable socket",
+            Self::WritableBlockDevice => "writable block device",
+            Self::WritableCharDevice => "writable character device",
         }
     }
 }
@@ -4,12 +4,23 @@ use std::string::ToString;
 
 use serde::{Deserialize, Serialize};
 
+/// set-user-ID bit of a unix file mode
+pub const S_ISUID: u32 = 0o4000;
+/// set-group-ID bit of a unix file mode
+pub const S_ISGID: u32 = 0o2000;
+/// sticky bit of a unix file mode
+pub const S_ISVTX: u32 = 0o1000;
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Copy)]
 pub enum PathType {
     File,
     Symlink,
     Setuid,
     Directory,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
     None,
 }
 impl Hash for PathType {
@@ -32,6 +43,10 @@ impl PathType {
             Self::Symlink => "symlink",
             Self::Setuid => "setuid",
             Self::Directory => "directory",
+            Self::Fifo => "fifo",
+            Self::Socket => "socket",
+            Self::BlockDevice => "block device",
+            Self::CharDevice => "character device",
             Self::None => "none",
         }
     }
@@ -60,6 +75,10 @@ impl From<&str> for PathType {
             "symlink" => Self::Symlink,
             "setuid" => Self::Setuid,
             "directory" => Self::Directory,
+            "fifo" => Self::Fifo,
+            "socket" => Self::Socket,
+            "block device" => Self::BlockDevice,
+            "character device" => Self::CharDevice,
             _ => Self::None,
         }
     }
@@ -3,6 +3,7 @@ use std::path::MAIN_SEPARATOR_STR;
 use regex::Regex;
 
 use crate::fs::Path;
+use crate::{traceback, Error};
 
 pub fn remove_trailing_slash(haystack: &str) -> String {
     let regex = Regex::new(r"/+$").unwrap();
@@ -12,6 +13,108 @@ pub fn expand_home_regex(haystack: &str, expansion: &str) -> String {
     let regex = Regex::new(format!("^~({}|$)", MAIN_SEPARATOR_STR).as_str()).unwrap();
     regex.replace(haystack, expansion).to_string()
 }
+/// `expand_user_home` expands a leading `~` or `~/` to the current
+/// user's home and a leading `~name` or `~name/` to the home of the
+/// user `name` according to the user database.
+pub fn expand_user_home(haystack: &str) -> Result<String, Error> {
+    let Some(rest) = haystack.strip_prefix('~') else {
+        return Ok(haystack.to_string());
+    };
+    let (name, tail) = match rest.find(MAIN_SEPARATOR_STR) {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    if name.is_empty() {
+        return Ok(expand_home_regex(haystack, &crate::tilde()?));
+    }
+    let home = crate::User::by_name(name)
+        .ok()
+        .and_then(|user| user.home_dir)
+        .filter(|home| !home.is_empty())
+        .ok_or_else(|| {
+            traceback!(HomePathError, "cannot expand {:#?}: unknown user {:#?}", haystack, name)
+        })?;
+    let home = remove_trailing_slash(&home);
+    if home.is_empty() && tail.is_empty() {
+        return Ok(MAIN_SEPARATOR_STR.to_string());
+    }
+    Ok(format!("{}{}", home, tail))
+}
+
+/// `expand_variables` expands `$VAR`, `${VAR}` and `${VAR:-default}`
+/// looking up variables through `var`, referencing a variable which
+/// is not defined and has no default is an error. A `$` which does
+/// not start a variable reference is kept as is.
+///
+/// Example
+///
+/// ```
+/// use iocore::expand_variables;
+/// let var = |name: &str| (name == "CACHE").then(|| "/var/cache".to_string());
+/// assert_eq!(expand_variables("$CACHE/${APP:-app}", var).unwrap(), "/var/cache/app");
+/// assert!(expand_variables("${UNDEFINED}/app", var).is_err());
+/// ```
+pub fn expand_variables(
+    haystack: &str,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<String, Error> {
+    let chars = haystack.chars().collect::<Vec<char>>();
+    let mut expanded = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index] != '$' {
+            expanded.push(chars[index]);
+            index += 1;
+            continue;
+        }
+        let (name, default, end) = match chars.get(index + 1) {
+            Some('{') => {
+                let start = index + 2;
+                let end = chars[start..]
+                    .iter()
+                    .position(|c| *c == '}')
+                    .map(|end| start + end)
+                    .ok_or_else(|| {
+                        traceback!(ParseError, "unterminated \"${{\" in {:#?}", haystack)
+                    })?;
+                let expression = chars[start..end].iter().collect::<String>();
+                match expression.split_once(":-") {
+                    Some((name, default)) => (name.to_string(), Some(default.to_string()), end + 1),
+                    None => (expression, None, end + 1),
+                }
+            },
+            Some(c) if c.is_ascii_alphabetic() || *c == '_' => {
+                let start = index + 1;
+                let end = chars[start..]
+                    .iter()
+                    .position(|c| !(c.is_ascii_alphanumeric() || *c == '_'))
+                    .map(|end| start + end)
+                    .unwrap_or(chars.len());
+                (chars[start..end].iter().collect::<String>(), None, end)
+            },
+            _ => {
+                expanded.push('$');
+                index += 1;
+                continue;
+            },
+        };
+        let value = match (var(&name), default) {
+            (Some(value), Some(default)) if value.is_empty() => default,
+            (Some(value), _) => value,
+            (None, Some(default)) => default,
+            (None, None) =>
+                return Err(traceback!(
+                    EnvironmentVarError,
+                    "undefined variable {:#?} in {:#?}",
+                    name,
+                    haystack
+                )),
+        };
+        expanded.push_str(&value);
+        index = end;
+    }
+    Ok(expanded)
+}
 pub fn add_trailing_separator(path: impl std::fmt::Display) -> String {
     let path = path.to_string();
     let path = remove_trailing_slash(&path);
@@ -1,34 +1,61 @@
 use trilobyte::{high_water_mark_u8_to_trilobyte, TriloByte};
 
 use crate::traceback;
-/// `PathPermissions`
+/// `PathPermissions` represents the permission bits of unix
+/// file-system paths along with the setuid, setgid and sticky bits
+/// which are accessible through [`PathPermissions::special`].
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Default)]
 pub struct PathPermissions {
     pub user: TriloByte,
     pub group: TriloByte,
     pub other: TriloByte,
+    special: TriloByte,
 }
 impl PathPermissions {
+    /// `new` returns [`PathPermissions`] without any of the setuid,
+    /// setgid and sticky bits set
+    pub fn new(user: TriloByte, group: TriloByte, other: TriloByte) -> PathPermissions {
+        PathPermissions {
+            user,
+            group,
+            other,
+            special: TriloByte::default(),
+        }
+    }
+
     pub fn to_array(self) -> [u8; 3] {
         [self.user.into_u8(), self.group.into_u8(), self.other.into_u8()]
     }
 
+    /// `to_string_octal` returns the octal representation of the
+    /// permissions, the special bits are only prepended when set,
+    /// e.g.: `"755"` or `"4755"`.
     pub fn to_string_octal(self) -> String {
-        [self.user, self.group, self.other]
-            .iter()
-            .map(|t| t.to_string_octal())
-            .collect::<String>()
+        let mut triads = vec![self.user, self.group, self.other];
+        if self.special.into_u8() > 0 {
+            triads.insert(0, self.special);
+        }
+        triads.iter().map(|t| t.to_string_octal()).collect::<String>()
     }
 
     pub fn into_u32(self) -> u32 {
-        u32::from_str_radix(&self.to_string_octal(), 8).unwrap()
+        (self.special.into_u32() << 9)
+            | (self.user.into_u32() << 6)
+            | (self.group.into_u32() << 3)
+            | self.other.into_u32()
     }
 
     pub fn from_u32(val: u32) -> Result<PathPermissions, crate::Error> {
-        let user = TriloByte::from(high_water_mark_u8_to_trilobyte((val >> 6) as u8));
-        let group = TriloByte::from(high_water_mark_u8_to_trilobyte((val >> 3) as u8));
-        let other = TriloByte::from(high_water_mark_u8_to_trilobyte(val as u8));
-        Ok(PathPermissions { user, group, other })
+        let special = TriloByte::from(high_water_mark_u8_to_trilobyte(((val >> 9) & 0o7) as u8));
+        let user = TriloByte::from(high_water_mark_u8_to_trilobyte(((val >> 6) & 0o7) as u8));
+        let group = TriloByte::from(high_water_mark_u8_to_trilobyte(((val >> 3) & 0o7) as u8));
+        let other = TriloByte::from(high_water_mark_u8_to_trilobyte((val & 0o7) as u8));
+        Ok(PathPermissions {
+            user,
+            group,
+            other,
+            special,
+        })
     }
 
     pub fn from_string_octal(repr: &str) -> Result<PathPermissions, crate::Error> {
@@ -98,6 +125,69 @@ impl PathPermissions {
         self.other.2 = executable;
     }
 
+    /// `special` returns the setuid, setgid and sticky bits
+    /// respectively
+    pub fn special(&self) -> TriloByte {
+        self.special
+    }
+
+    pub fn setuid(&self) -> bool {
+        self.special.0
+    }
+
+    pub fn setgid(&self) -> bool {
+        self.special.1
+    }
+
+    pub fn sticky(&self) -> bool {
+        self.special.2
+    }
+
+    pub fn set_setuid(&mut self, setuid: bool) {
+        self.special.0 = setuid;
+    }
+
+    pub fn set_setgid(&mut self, setgid: bool) {
+        self.special.1 = setgid;
+    }
+
+    pub fn set_sticky(&mut self, sticky: bool) {
+        self.special.2 = sticky;
+    }
+
+    /// `apply_symbolic` returns new [`PathPermissions`] resulting
+    /// from applying a symbolic mode expression in the fashion of
+    /// `chmod`, e.g.: `"u+x,g-w,o=r"`, `"a+rX"` or `"g=u"`.
+    ///
+    /// The `is_directory` argument is taken into account by `X`
+    /// which only adds execute permission to directories or to paths
+    /// which are already executable by anyone and by `=` which
+    /// preserves the setuid and setgid bits of directories.
+    ///
+    /// Clauses without any of `u`, `g`, `o` or `a` apply to all
+    /// classes, unlike `chmod` the process umask is not taken into
+    /// account.
+    ///
+    /// Example
+    /// ```
+    /// use iocore::PathPermissions;
+    /// let permissions = PathPermissions::from_u32(0o644).unwrap();
+    /// assert_eq!(permissions.apply_symbolic("u+x,g-r,o=", false).unwrap().to_string_octal(), "700");
+    /// ```
+    pub fn apply_symbolic(
+        &self,
+        expression: &str,
+        is_directory: bool,
+    ) -> Result<PathPermissions, crate::Error> {
+        let mut mode = self.into_u32();
+        for clause in expression.split(',') {
+            mode = apply_symbolic_clause(mode, clause, is_directory).map_err(|error| {
+                traceback!(ParseError, "invalid symbolic mode {:#?}: {}", expression, error)
+            })?;
+        }
+        PathPermissions::from_u32(mode)
+    }
+
     pub fn executable(&self) -> bool {
         self.user().executable() || self.group().executable()
     }
@@ -126,11 +216,134 @@ impl Into<u32> for PathPermissions {
         self.into_u32()
     }
 }
+
+const WHO_USER: u32 = 0o4700;
+const WHO_GROUP: u32 = 0o2070;
+const WHO_OTHER: u32 = 0o1007;
+
+fn apply_symbolic_clause(mode: u32, clause: &str, is_directory: bool) -> Result<u32, String> {
+    let mut chars = clause.chars().peekable();
+    let mut who = 0;
+    while let Some(c) = chars.peek() {
+        who |= match c {
+            'u' => WHO_USER,
+            'g' => WHO_GROUP,
+            'o' => WHO_OTHER,
+            'a' => WHO_USER | WHO_GROUP | WHO_OTHER,
+            _ => break,
+        };
+        chars.next();
+    }
+    if who == 0 {
+        who = WHO_USER | WHO_GROUP | WHO_OTHER;
+    }
+    if chars.peek().is_none() {
+        return Err(format!("missing operator in {:#?}", clause));
+    }
+    let mut mode = mode;
+    while let Some(op) = chars.next() {
+        if !['+', '-', '='].contains(&op) {
+            return Err(format!("unexpected {:#?} in {:#?}", op, clause));
+        }
+        let mut bits = 0;
+        while let Some(c) = chars.peek() {
+            bits |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                'X' =>
+                    if is_directory || mode & 0o111 != 0 {
+                        0o111
+                    } else {
+                        0
+                    },
+                's' => 0o6000,
+                't' => 0o1000,
+                'u' => replicate_triad((mode >> 6) & 0o7),
+                'g' => replicate_triad((mode >> 3) & 0o7),
+                'o' => replicate_triad(mode & 0o7),
+                '+' | '-' | '=' => break,
+                c => return Err(format!("unexpected {:#?} in {:#?}", c, clause)),
+            };
+            chars.next();
+        }
+        let bits = bits & who;
+        match op {
+            '+' => mode |= bits,
+            '-' => mode &= !bits,
+            _ => {
+                let preserved = if is_directory { 0o6000 } else { 0 };
+                mode = (mode & !(who & !preserved)) | bits;
+            },
+        }
+    }
+    Ok(mode)
+}
+
+fn replicate_triad(triad: u32) -> u32 {
+    (triad << 6) | (triad << 3) | triad
+}
+
+/// `PermissionsMode` represents either absolute [`PathPermissions`]
+/// or a `chmod`-like mode expression which might be octal (e.g.:
+/// `"0755"`) or symbolic (e.g.: `"u+x,go-w"`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PermissionsMode {
+    Absolute(PathPermissions),
+    Expression(String),
+}
+impl PermissionsMode {
+    /// `resolve` returns the [`PathPermissions`] resulting from
+    /// applying the mode to the `current` permissions of a path.
+    pub fn resolve(
+        &self,
+        current: &PathPermissions,
+        is_directory: bool,
+    ) -> Result<PathPermissions, crate::Error> {
+        match self {
+            PermissionsMode::Absolute(permissions) => Ok(*permissions),
+            PermissionsMode::Expression(expression) =>
+                if !expression.is_empty() && expression.chars().all(|c| c.is_digit(8)) {
+                    PathPermissions::from_string_octal(expression)
+                } else {
+                    current.apply_symbolic(expression, is_directory)
+                },
+        }
+    }
+}
+impl std::fmt::Display for PermissionsMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PermissionsMode::Absolute(permissions) => write!(f, "{}", permissions),
+            PermissionsMode::Expression(expression) => write!(f, "{}", expression),
+        }
+    }
+}
+impl From<PathPermissions> for PermissionsMode {
+    fn from(permissions: PathPermissions) -> PermissionsMode {
+        PermissionsMode::Absolute(permissions)
+    }
+}
+impl From<&PathPermissions> for PermissionsMode {
+    fn from(permissions: &PathPermissions) -> PermissionsMode {
+        PermissionsMode::Absolute(*permissions)
+    }
+}
+impl From<&str> for PermissionsMode {
+    fn from(expression: &str) -> PermissionsMode {
+        PermissionsMode::Expression(expression.to_string())
+    }
+}
+impl From<String> for PermissionsMode {
+    fn from(expression: String) -> PermissionsMode {
+        PermissionsMode::Expression(expression)
+    }
+}
 #[cfg(test)]
 mod tests_path_permissions {
     use trilobyte::TriloByte;
 
-    use crate::{Error, PathPermissions};
+    use crate::{Error, PathPermissions, PermissionsMode};
 
     #[test]
     fn test_permissions_to_array() {
@@ -138,6 +351,7 @@ mod tests_path_permissions {
             user: TriloByte::from(7),
             group: TriloByte::from(5),
             other: TriloByte::from(4),
+            special: TriloByte::from(0),
         };
         assert_eq!(permissions.to_array(), [7, 5, 4]);
     }
@@ -147,6 +361,7 @@ mod tests_path_permissions {
             user: TriloByte::from(7),
             group: TriloByte::from(5),
             other: TriloByte::from(4),
+            special: TriloByte::from(0),
         };
         assert_eq!(permissions.to_string_octal(), "754");
     }
@@ -170,7 +385,7 @@ mod tests_path_permissions {
         assert_eq!(result.is_err(), true);
         assert_eq!(
             result,
-            Err(Error::FileSystemError("cannot parse u32 from 909 base 8: invalid digit found in string [iocore::fs::perms::PathPermissions::from_string_octal::{{closure}}:[crates/iocore/iocore/fs/perms.rs:36]]\n".to_string()))
+            Err(Error::FileSystemError("cannot parse u32 from 909 base 8: invalid digit found in string [iocore::fs::perms::PathPermissions::from_string_octal::{{closure}}:[crates/iocore/iocore/fs/perms.rs:63]]\n".to_string()))
         );
     }
     #[test]
@@ -179,6 +394,7 @@ mod tests_path_permissions {
             user: TriloByte::from(7),
             group: TriloByte::from(5),
             other: TriloByte::from(4),
+            special: TriloByte::from(0),
         };
         assert_eq!(permissions.into_u32(), 0o754);
     }
@@ -201,6 +417,80 @@ mod tests_path_permissions {
         permissions.set_other_readable(false);
         assert_eq!(permissions.to_string(), "200");
     }
+    #[test]
+    fn test_permissions_special_bits() {
+        let mut permissions = PathPermissions::from_u32(0o104755).unwrap();
+        assert!(permissions.setuid());
+        assert!(!permissions.setgid());
+        assert!(!permissions.sticky());
+        assert_eq!(permissions.to_string_octal(), "4755");
+        assert_eq!(permissions.into_u32(), 0o4755);
+        permissions.set_setuid(false);
+        permissions.set_setgid(true);
+        permissions.set_sticky(true);
+        assert_eq!(permissions.to_string_octal(), "3755");
+        permissions.set_setgid(false);
+        permissions.set_sticky(false);
+        assert_eq!(permissions.to_string_octal(), "755");
+        assert_eq!(PathPermissions::from_string_octal("1777").unwrap().into_u32(), 0o1777);
+    }
+    #[test]
+    fn test_permissions_apply_symbolic() {
+        let permissions = PathPermissions::from_u32(0o644).unwrap();
+        let apply = |expression: &str, is_directory: bool| {
+            permissions.apply_symbolic(expression, is_directory).unwrap().to_string_octal()
+        };
+        assert_eq!(apply("u+x", false), "744");
+        assert_eq!(apply("+x", false), "755");
+        assert_eq!(apply("a+x", false), "755");
+        assert_eq!(apply("go-r", false), "600");
+        assert_eq!(apply("u=rwx,g=rx,o=", false), "750");
+        assert_eq!(apply("u+x,g-w,o-r", false), "740");
+        assert_eq!(apply("g=u", false), "664");
+        assert_eq!(apply("o=g", false), "644");
+        assert_eq!(apply("a+X", false), "644");
+        assert_eq!(apply("a+X", true), "755");
+        assert_eq!(apply("u+s", false), "4644");
+        assert_eq!(apply("g+s", false), "2644");
+        assert_eq!(apply("+t", false), "1644");
+        assert_eq!(apply("u+t", false), "644");
+        assert_eq!(apply("u-w+x", false), "544");
+    }
+    #[test]
+    fn test_permissions_apply_symbolic_equals_preserves_directory_setgid() {
+        let permissions = PathPermissions::from_u32(0o2775).unwrap();
+        assert_eq!(permissions.apply_symbolic("g=rx", true).unwrap().to_string_octal(), "2755");
+        assert_eq!(permissions.apply_symbolic("g=rx", false).unwrap().to_string_octal(), "755");
+        assert_eq!(permissions.apply_symbolic("a+X", false).unwrap().to_string_octal(), "2775");
+    }
+    #[test]
+    fn test_permissions_apply_symbolic_uses_running_mode() {
+        let permissions = PathPermissions::from_u32(0o755).unwrap();
+        assert_eq!(permissions.apply_symbolic("a=r+X", false).unwrap().to_string_octal(), "444");
+        assert_eq!(permissions.apply_symbolic("a=r+X", true).unwrap().to_string_octal(), "555");
+        assert_eq!(permissions.apply_symbolic("u=rw,g=u", false).unwrap().to_string_octal(), "665");
+    }
+    #[test]
+    fn test_permissions_apply_symbolic_error() {
+        let permissions = PathPermissions::from_u32(0o644).unwrap();
+        assert!(permissions.apply_symbolic("u", false).is_err());
+        assert!(permissions.apply_symbolic("u+q", false).is_err());
+        assert!(permissions.apply_symbolic("z+x", false).is_err());
+        assert!(permissions.apply_symbolic("u+x,", false).is_err());
+    }
+    #[test]
+    fn test_permissions_mode_resolve() {
+        let current = PathPermissions::from_u32(0o644).unwrap();
+        let resolve =
+            |mode: PermissionsMode| mode.resolve(&current, false).unwrap().to_string_octal();
+        assert_eq!(resolve(PermissionsMode::from("0755")), "755");
+        assert_eq!(resolve(PermissionsMode::from("4750")), "4750");
+        assert_eq!(resolve(PermissionsMode::from("go-r")), "600");
+        assert_eq!(
+            resolve(PermissionsMode::from(PathPermissions::from_u32(0o700).unwrap())),
+            "700"
+        );
+    }
 }
 
 pub struct Permission(TriloByte);
@@ -24,15 +24,20 @@
 #![cfg(target_family = "unix")]
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod env;
 pub(crate) mod errors;
 pub(crate) mod fs;
-pub(crate) mod sh;
+pub mod sh;
 pub(crate) mod sys;
 pub(crate) mod walk;
 
-pub use env::{args, args_from_string, var};
+pub use env::{
+    EnvVarError, EnvVarErrorKind, args, args_from_string, args_from_string_quoted, var,
+};
 pub use errors::{Error, Result};
+pub use fs::ls_entry::{LsEntry, LsOptions, LsSort};
 pub use fs::ls_path_type::LsPathType;
 pub use fs::opts::OpenOptions;
 pub use fs::path_datetime::PathDateTime;
@@ -40,25 +45,61 @@ pub use fs::path_status::PathStatus;
 pub use fs::path_timestamps::PathTimestamps;
 pub use fs::path_type::PathType;
 pub use fs::path_utils::{
-    add_trailing_separator, expand_home_regex, path_str_to_relative_subpath, remove_absolute_path,
-    remove_duplicate_separators, remove_end, remove_equal_prefix_from_path_strings,
-    remove_redundant_current_path, remove_start, remove_trailing_slash, repl_beg, repl_end,
-    split_str_into_relative_subpath_parts,
+    add_trailing_separator, expand_home_regex, expand_user_home, expand_variables,
+    path_str_to_relative_subpath, remove_absolute_path, remove_duplicate_separators, remove_end,
+    remove_equal_prefix_from_path_strings, remove_redundant_current_path, remove_start,
+    remove_trailing_slash, repl_beg, repl_end, split_str_into_relative_subpath_parts,
 };
-pub use fs::perms::PathPermissions;
+pub use fs::perms::{PathPermissions, PermissionsMode};
 pub use fs::size::{ByteUnit, Size};
 pub use fs::{Path, ROOT_PATH_STR, USERS_PATH};
+pub use sh::cmd::{Cmd, CmdInput, CmdRedirect};
+pub use sh::output::{CommandOutput, SubprocessFailure};
+pub use sh::pipeline::{Connector, Pipeline, PipelineOutput, Script, ScriptOutput, exit_code};
+pub use sh::pty::{PtyChild, PtyMatch, WindowSize};
+pub use sh::stream::{OutputLine, OutputStream, StreamedOutput, StreamingChild};
+pub use sh::timeout::{DEFAULT_KILL_AFTER, SubprocessTimeout};
 pub use sh::{
     shell_command, shell_command_stdout, shell_command_string_output, shell_command_vec_output,
 };
+pub use sys::app_dirs::{AppDirs, XdgDirs};
+pub use sys::daemon::{Daemon, DaemonRedirect, Forked, PidFile};
+pub use sys::home::{
+    HomePolicy, home_dir, home_override, home_policy, set_home_override, set_home_policy, tilde,
+};
+pub use sys::info::{
+    Cpu, CpuInfo, LoadAverage, MemoryInfo, OsRelease, SystemInfo, Uname, hostname, uptime,
+};
+pub use sys::passwd::{
+    DatabaseEntry, DatabaseFile, DatabaseLine, GroupEntry, GroupFile, PasswdEntry, PasswdFile,
+    ShadowEntry, ShadowFile,
+};
+pub use sys::process::{PROC_PATH, Process, ProcessFd, ProcessState, ProcessStatus};
+pub use sys::signals::{
+    SHUTDOWN_SIGNALS, Signals, check_shutdown, clear_shutdown_request, request_shutdown,
+    shutdown_requested,
+};
+pub use sys::which::{
+    DEFAULT_SEARCH_PATH, is_executable, search_path, split_search_path, which, which_all,
+    which_all_in, which_in,
+};
 pub use sys::{
     Group, User, XPC, best_guess_home, get_stdout_string, get_subprocess_output, guess_unix_home,
     parse_u32, safe_string, unix_user_info_home,
 };
-pub use walk::{Depth, NoopProgressHandler, WalkProgressHandler, glob, walk_dir, walk_globs};
+pub use walk::{
+    Depth, MaxDepth, NoopProgressHandler, WalkProgressHandler, glob, hard_link_clusters, walk_dir,
+    walk_globs,
+};
 
 lazy_static! {
     pub static ref XPC_INFO: XPC = XPC::from_env();
     pub static ref USER: User = User::id().unwrap_or_default();
-    pub static ref TILDE: String = format!("{}/", USER.home().expect("current UNIX user HOME"));
 }
+/// `TILDE` is the expansion of `"~/"` resolved at first use, it
+/// remains `"~/"` when the home directory cannot be resolved and
+/// ignores later calls to [`set_home_policy`] and
+/// [`set_home_override`].
+#[deprecated(note = "use `tilde()` which honors the current home policy and reports errors")]
+pub static TILDE: std::sync::LazyLock<String> =
+    std::sync::LazyLock::new(|| tilde().unwrap_or_else(|_| "~/".to_string()));
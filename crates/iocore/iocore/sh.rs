@@ -1,9 +1,15 @@
-use std::process::{Command, Stdio};
+pub(crate) mod cmd;
+pub(crate) mod output;
+pub(crate) mod pipeline;
+pub(crate) mod pty;
+pub(crate) mod stream;
+pub(crate) mod timeout;
 
 use sanitation::SString;
 
-use crate::errors::{Error, Result};
+use crate::errors::Result;
 use crate::fs::Path;
+use crate::{exit_code, traceback, Cmd, CmdRedirect};
 
 /// Utility function to spawn a command from a string rather than
 /// array of arguments.
@@ -13,16 +19,8 @@ pub fn shell_command_vec_output(
     command: impl std::fmt::Display,
     current_dir: impl Into<Path>,
 ) -> Result<(i32, Vec<u8>, Vec<u8>)> {
-    let args = split_args(&command.to_string());
-    let mut cmd = Command::new(args[0].clone());
-    let cmd = cmd.current_dir(Into::<Path>::into(current_dir));
-    let cmd = cmd.args(args[1..].to_vec());
-    let cmd = cmd.stdin(Stdio::null());
-    let cmd = cmd.stdout(Stdio::piped());
-    let cmd = cmd.stderr(Stdio::piped());
-    let child = cmd.spawn()?;
-    let output = child.wait_with_output()?;
-    let status = output.status.code().unwrap_or_default();
+    let output = command_from_string(command, current_dir)?.output()?;
+    let status = exit_code(&output.status);
     Ok((status, output.stdout.to_vec(), output.stderr.to_vec()))
 }
 
@@ -46,39 +44,109 @@ pub fn shell_command_string_output(
 /// array of arguments and returns the exit code. Stdout and Stderr
 /// are inherited from the current process.
 pub fn shell_command(command: impl std::fmt::Display, current_dir: impl Into<Path>) -> Result<i32> {
-    let args = split_args(&command.to_string());
-    let mut cmd = Command::new(args[0].clone());
-    let cmd = cmd.current_dir(Into::<Path>::into(current_dir));
-    let cmd = cmd.args(args[1..].to_vec());
-    let cmd = cmd.stdin(Stdio::null());
-    let cmd = cmd.stdout(Stdio::inherit());
-    let cmd = cmd.stderr(Stdio::inherit());
-    let child = cmd.spawn()?;
-    let output = child.wait_with_output()?;
-    let status = output.status.code().unwrap_or_default();
-    Ok(status)
+    command_from_string(command, current_dir)?
+        .stdout(CmdRedirect::Inherit)
+        .stderr(CmdRedirect::Inherit)
+        .status()
 }
 
 /// `shell_command_stdout` executes the given command and returns the
-/// stdout of the process.
+/// stdout of the process or [`crate::Error::SubprocessError`] carrying
+/// its [`crate::CommandOutput`] if it exits with a non-zero code or is
+/// terminated by a signal.
 pub fn shell_command_stdout(
     command: impl std::fmt::Display,
     current_dir: impl Into<Path>,
 ) -> Result<String> {
-    let (exit_code, stdout, _) = shell_command_string_output(command.to_string(), current_dir)?;
-    match exit_code {
-        0 => Ok(stdout),
-        _ => Err(Error::SubprocessError(format!(
-            "command {:#?} failed with {}",
-            command.to_string(),
-            exit_code
-        ))),
+    let output = command_from_string(command, current_dir)?.check()?;
+    Ok(SString::new(&output.stdout).safe()?)
+}
+
+/// `split` splits `command` into words with POSIX shell quoting
+/// rules as [`split_strict`] does, falling back to a single word
+/// holding the whole of `command` when it is malformed, e.g.: has
+/// unbalanced quotes.
+///
+/// Example
+///
+/// ```
+/// use iocore::sh::split;
+/// assert_eq!(split("grep -r 'some text' ."), vec!["grep", "-r", "some text", "."]);
+/// assert_eq!(split("echo 'unbalanced"), vec!["echo 'unbalanced"]);
+/// ```
+pub fn split(command: impl std::fmt::Display) -> Vec<String> {
+    let command = command.to_string();
+    match split_strict(&command) {
+        Ok(words) => words,
+        Err(_) => vec![command],
     }
 }
 
-fn split_args(args: &str) -> Vec<String> {
-    match shlex::split(args) {
-        Some(args) => args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>(),
-        None => vec![args.to_string()],
+/// `split_strict` splits `command` into words with POSIX shell
+/// quoting rules returning [`crate::Error::ShellCommandError`] when
+/// `command` is malformed, e.g.: has unbalanced quotes.
+///
+/// Example
+///
+/// ```
+/// use iocore::sh::split_strict;
+/// assert_eq!(split_strict("a 'b c' \"d\\\"e\"").unwrap(), vec!["a", "b c", "d\"e"]);
+/// assert!(split_strict("echo \"unbalanced").is_err());
+/// ```
+pub fn split_strict(command: impl std::fmt::Display) -> Result<Vec<String>> {
+    let command = command.to_string();
+    shlex::split(&command)
+        .ok_or_else(|| traceback!(ShellCommandError, "malformed command line {:#?}", command))
+}
+
+/// `quote` returns `arg` unchanged if it only contains characters
+/// which need no quoting, otherwise single-quoted such that
+/// [`split`] returns it as a single word.
+///
+/// Example
+///
+/// ```
+/// use iocore::sh::quote;
+/// assert_eq!(quote("file.txt"), "file.txt");
+/// assert_eq!(quote("some text"), "'some text'");
+/// assert_eq!(quote("it's"), "'it'\\''s'");
+/// assert_eq!(quote(""), "''");
+/// ```
+pub fn quote(arg: impl std::fmt::Display) -> String {
+    let arg = arg.to_string();
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c))
+    {
+        return arg;
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// `join` quotes every argument with [`quote`] and joins them with
+/// spaces such that [`split`] returns the original arguments.
+///
+/// Example
+///
+/// ```
+/// use iocore::sh::{join, split};
+/// let args = vec!["echo", "it's", "$HOME", ""];
+/// assert_eq!(join(&args), "echo 'it'\\''s' '$HOME' ''");
+/// assert_eq!(split(join(&args)), args);
+/// ```
+pub fn join<T: std::fmt::Display>(args: impl IntoIterator<Item = T>) -> String {
+    args.into_iter().map(quote).collect::<Vec<String>>().join(" ")
+}
+
+/// `command_from_string` splits `command` with [`split_strict`]
+/// returning [`crate::Error::ShellCommandError`] when `command` is
+/// malformed or empty
+fn command_from_string(
+    command: impl std::fmt::Display,
+    current_dir: impl Into<Path>,
+) -> Result<Cmd> {
+    let command = command.to_string();
+    let args = split_strict(&command)?;
+    match args.split_first() {
+        Some((program, args)) => Ok(Cmd::new(program).args(args).current_dir(current_dir)),
+        None => Err(traceback!(ShellCommandError, "empty command line {:#?}", command)),
     }
 }
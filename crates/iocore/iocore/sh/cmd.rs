@@ -0,0 +1,623 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::{AsFd, FromRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, ChildStdout, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::sh::timeout::{self, Capture};
+use crate::{traceback, CommandOutput, Error, OpenOptions, Path};
+
+/// `CmdInput` determines the stdin of a [`Cmd`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum CmdInput {
+    #[default]
+    Null,
+    Inherit,
+    /// `Piped` leaves the stdin of the spawned [`Child`] open for
+    /// the caller to write into
+    Piped,
+    Bytes(Vec<u8>),
+    Path(Path),
+}
+
+/// `CmdRedirect` determines the stdout or stderr of a [`Cmd`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum CmdRedirect {
+    #[default]
+    Piped,
+    Inherit,
+    Null,
+    /// `Path` writes into the given file truncating it or appending
+    /// to it, the file is created with mode `0644` when missing.
+    /// Relative paths are relative to [`Cmd::current_dir`] if set.
+    Path {
+        path: Path,
+        append: bool,
+    },
+    /// `Stdout` sends stderr wherever stdout goes, as `2>&1` does,
+    /// and is only valid for stderr
+    Stdout,
+    /// `PipedStdout` sends stderr into the pipe read as the stdout of
+    /// the subprocess while stdout itself is redirected elsewhere, as
+    /// `2>&1 > file` does, and is only valid for stderr
+    PipedStdout,
+}
+
+/// `Cmd` builds and spawns subprocesses.
+///
+/// Unlike [`std::process::Command`], `stdin` defaults to
+/// [`CmdInput::Null`], `stdout` and `stderr` default to
+/// [`CmdRedirect::Piped`] and program names without `/` are resolved
+/// with [`crate::which_in`], within the `PATH` passed to the
+/// subprocess or [`crate::DEFAULT_SEARCH_PATH`] if it is unset or
+/// empty, such that missing programs are reported clearly. Relative
+/// entries of `PATH` are relative to [`Cmd::current_dir`] and the
+/// subprocess still receives the program name as `argv[0]`.
+///
+/// Example
+///
+/// ```
+/// use iocore::Cmd;
+/// let output = Cmd::new("sh")
+///     .args(["-c", "read line; echo \"$GREETING, $line\""])
+///     .env("GREETING", "hello")
+///     .stdin_bytes(b"world\n")
+///     .output()
+///     .unwrap();
+/// assert_eq!(output.stdout, b"hello, world\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cmd {
+    program: String,
+    args: Vec<String>,
+    current_dir: Option<Path>,
+    env: BTreeMap<String, Option<String>>,
+    env_clear: bool,
+    stdin: CmdInput,
+    stdout: CmdRedirect,
+    stderr: CmdRedirect,
+    umask: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    process_group: Option<i32>,
+    timeout: Option<Duration>,
+    timeout_signal: Option<i32>,
+    kill_after: Option<Duration>,
+}
+impl Cmd {
+    pub fn new(program: impl std::fmt::Display) -> Cmd {
+        Cmd {
+            program: program.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// `parse` creates a [`Cmd`] from a command line split with
+    /// POSIX shell quoting rules, e.g.: `"grep -r 'some text' ."`
+    pub fn parse(command: impl std::fmt::Display) -> Result<Cmd, Error> {
+        let command = command.to_string();
+        let mut args = crate::sh::split_strict(&command)?.into_iter();
+        let program = args
+            .next()
+            .ok_or_else(|| traceback!(ShellCommandError, "empty command line {:#?}", command))?;
+        Ok(Cmd::new(program).args(args))
+    }
+
+    pub fn arg(mut self, arg: impl std::fmt::Display) -> Cmd {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn args<T: std::fmt::Display>(mut self, args: impl IntoIterator<Item = T>) -> Cmd {
+        self.args.extend(args.into_iter().map(|arg| arg.to_string()));
+        self
+    }
+
+    pub fn current_dir(mut self, current_dir: impl Into<Path>) -> Cmd {
+        self.current_dir = Some(current_dir.into());
+        self
+    }
+
+    pub fn env(mut self, key: impl std::fmt::Display, value: impl std::fmt::Display) -> Cmd {
+        self.env.insert(key.to_string(), Some(value.to_string()));
+        self
+    }
+
+    pub fn envs<K: std::fmt::Display, V: std::fmt::Display>(
+        mut self,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> Cmd {
+        for (key, value) in vars {
+            self.env.insert(key.to_string(), Some(value.to_string()));
+        }
+        self
+    }
+
+    pub fn env_remove(mut self, key: impl std::fmt::Display) -> Cmd {
+        self.env.insert(key.to_string(), None);
+        self
+    }
+
+    /// `env_clear` prevents the subprocess from inheriting the
+    /// environment of the current process, variables set with
+    /// [`Cmd::env`] are still passed
+    pub fn env_clear(mut self) -> Cmd {
+        self.env_clear = true;
+        self.env.retain(|_, value| value.is_some());
+        self
+    }
+
+    pub fn stdin(mut self, stdin: CmdInput) -> Cmd {
+        self.stdin = stdin;
+        self
+    }
+
+    pub fn stdin_bytes(self, bytes: impl AsRef<[u8]>) -> Cmd {
+        self.stdin(CmdInput::Bytes(bytes.as_ref().to_vec()))
+    }
+
+    pub fn stdin_path(self, path: impl Into<Path>) -> Cmd {
+        self.stdin(CmdInput::Path(path.into()))
+    }
+
+    pub fn stdout(mut self, stdout: CmdRedirect) -> Cmd {
+        self.stdout = stdout;
+        self
+    }
+
+    pub fn stderr(mut self, stderr: CmdRedirect) -> Cmd {
+        self.stderr = stderr;
+        self
+    }
+
+    pub fn stdout_path(self, path: impl Into<Path>, append: bool) -> Cmd {
+        self.stdout(CmdRedirect::Path {
+            path: path.into(),
+            append,
+        })
+    }
+
+    pub fn stderr_path(self, path: impl Into<Path>, append: bool) -> Cmd {
+        self.stderr(CmdRedirect::Path {
+            path: path.into(),
+            append,
+        })
+    }
+
+    /// `umask` sets the file mode creation mask of the subprocess
+    pub fn umask(mut self, umask: u32) -> Cmd {
+        self.umask = Some(umask);
+        self
+    }
+
+    pub fn uid(mut self, uid: u32) -> Cmd {
+        self.uid = Some(uid);
+        self
+    }
+
+    pub fn gid(mut self, gid: u32) -> Cmd {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// `process_group` places the subprocess in the process group
+    /// `pgid`, `0` creates a new process group whose id is the pid
+    /// of the subprocess
+    pub fn process_group(mut self, pgid: i32) -> Cmd {
+        self.process_group = Some(pgid);
+        self
+    }
+
+    /// `timeout` limits the duration of [`Cmd::output`],
+    /// [`Cmd::status`], [`Cmd::stream`] and the [`crate::StreamingChild`]
+    /// of [`Cmd::spawn_streaming`] after which the subprocess receives the
+    /// [`Cmd::timeout_signal`] then, if still running after
+    /// [`Cmd::kill_after`], `SIGKILL`. Unless [`Cmd::process_group`]
+    /// is set, the subprocess is placed in a new process group to
+    /// which both signals are sent. A subprocess joining an existing
+    /// group with a positive [`Cmd::process_group`] is signalled
+    /// alone such that the rest of that group is left running.
+    ///
+    /// Exceeding the timeout results in `Error::SubprocessTimeout`
+    /// carrying the output collected so far. The subprocess is
+    /// terminated likewise if [`crate::shutdown_requested`] while
+    /// waiting, resulting in `Error::ShutdownRequested`.
+    ///
+    /// Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use iocore::{Cmd, Error};
+    /// let error = Cmd::new("sh")
+    ///     .args(["-c", "echo started; sleep 10"])
+    ///     .timeout(Duration::from_millis(200))
+    ///     .output()
+    ///     .unwrap_err();
+    /// match error {
+    ///     Error::SubprocessTimeout(timeout) => assert_eq!(timeout.stdout, b"started\n"),
+    ///     error => panic!("unexpected error {}", error),
+    /// }
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Cmd {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// `timeout_signal` sets the signal sent when the timeout
+    /// elapses, defaults to `SIGTERM`
+    pub fn timeout_signal(mut self, signal: i32) -> Cmd {
+        self.timeout_signal = Some(signal);
+        self
+    }
+
+    /// `kill_after` sets the grace period between the timeout signal
+    /// and `SIGKILL`, defaults to [`crate::DEFAULT_KILL_AFTER`]
+    pub fn kill_after(mut self, kill_after: Duration) -> Cmd {
+        self.kill_after = Some(kill_after);
+        self
+    }
+
+    pub fn get_program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn get_args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn get_current_dir(&self) -> Option<&Path> {
+        self.current_dir.as_ref()
+    }
+
+    pub fn get_stdin(&self) -> &CmdInput {
+        &self.stdin
+    }
+
+    pub fn get_stdout(&self) -> &CmdRedirect {
+        &self.stdout
+    }
+
+    pub fn get_stderr(&self) -> &CmdRedirect {
+        &self.stderr
+    }
+
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// `to_command` returns the [`std::process::Command`] equivalent
+    /// to this [`Cmd`], [`CmdInput::Bytes`] becomes a pipe which
+    /// [`Cmd::spawn`] writes into.
+    ///
+    /// Redirecting stderr to a piped stdout with [`CmdRedirect::Stdout`]
+    /// requires [`Cmd::spawn`].
+    pub fn to_command(&self) -> Result<Command, Error> {
+        match self.build(None)? {
+            (command, None) => Ok(command),
+            (_, Some(_)) => Err(traceback!(
+                SubprocessError,
+                "{}: stderr redirected to piped stdout requires Cmd::spawn",
+                self
+            )),
+        }
+    }
+
+    /// `to_session_command` returns the [`std::process::Command`]
+    /// equivalent to this [`Cmd`] without stdio redirections nor
+    /// process group such that the caller may start a new session,
+    /// e.g.: [`Cmd::spawn_pty`]
+    pub(crate) fn to_session_command(&self) -> Result<Command, Error> {
+        let mut cmd = self.clone();
+        cmd.stdin = CmdInput::Null;
+        cmd.stdout = CmdRedirect::Null;
+        cmd.stderr = CmdRedirect::Null;
+        cmd.process_group = None;
+        cmd.timeout = None;
+        cmd.to_command()
+    }
+
+    /// `build` returns the [`std::process::Command`] along with the
+    /// read end of the pipe shared by stdout and stderr when
+    /// [`CmdRedirect::Stdout`] is used with a piped stdout
+    fn build(&self, stdin: Option<Stdio>) -> Result<(Command, Option<File>), Error> {
+        let mut command = Command::new(self.resolve_program()?);
+        command.arg0(&self.program);
+        command.args(&self.args);
+        if let Some(current_dir) = &self.current_dir {
+            command.current_dir(current_dir);
+        }
+        if self.env_clear {
+            command.env_clear();
+        }
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+        command.stdin(match (stdin, &self.stdin) {
+            (Some(stdin), _) => stdin,
+            (None, CmdInput::Null) => Stdio::null(),
+            (None, CmdInput::Inherit) => Stdio::inherit(),
+            (None, CmdInput::Piped | CmdInput::Bytes(_)) => Stdio::piped(),
+            (None, CmdInput::Path(path)) =>
+                self.resolve_path(path).to_stdio(OpenOptions::new().read(true))?,
+        });
+        let (stdout, stderr, reader) = match (&self.stdout, &self.stderr) {
+            (CmdRedirect::Stdout | CmdRedirect::PipedStdout, _) =>
+                return Err(traceback!(SubprocessError, "{}: stdout redirected to itself", self)),
+            (CmdRedirect::Piped, CmdRedirect::Stdout | CmdRedirect::PipedStdout) => {
+                let (reader, writer) = pipe().map_err(|error| {
+                    traceback!(SubprocessError, "creating pipe for {}: {}", self, error)
+                })?;
+                (Stdio::from(writer.try_clone()?), Stdio::from(writer), Some(reader))
+            },
+            (CmdRedirect::Inherit, CmdRedirect::Stdout) => (
+                Stdio::inherit(),
+                Stdio::from(std::io::stdout().as_fd().try_clone_to_owned()?),
+                None,
+            ),
+            (CmdRedirect::Null, CmdRedirect::Stdout) => (Stdio::null(), Stdio::null(), None),
+            (CmdRedirect::Path { path, append }, CmdRedirect::Stdout) => {
+                let file = self.open_redirect(path, *append)?;
+                (Stdio::from(file.try_clone()?), Stdio::from(file), None)
+            },
+            (stdout, CmdRedirect::PipedStdout) => {
+                let (reader, writer) = pipe().map_err(|error| {
+                    traceback!(SubprocessError, "creating pipe for {}: {}", self, error)
+                })?;
+                (self.redirect_stdio(stdout)?, Stdio::from(writer), Some(reader))
+            },
+            (stdout, stderr) => (self.redirect_stdio(stdout)?, self.redirect_stdio(stderr)?, None),
+        };
+        command.stdout(stdout);
+        command.stderr(stderr);
+        if let Some(umask) = self.umask {
+            unsafe {
+                command.pre_exec(move || {
+                    libc::umask(umask as libc::mode_t);
+                    Ok(())
+                });
+            }
+        }
+        if let Some(gid) = self.gid {
+            command.gid(gid);
+        }
+        if let Some(uid) = self.uid {
+            command.uid(uid);
+        }
+        match (self.process_group, self.timeout) {
+            (Some(pgid), _) => {
+                command.process_group(pgid);
+            },
+            (None, Some(_)) => {
+                command.process_group(0);
+            },
+            (None, None) => {},
+        }
+        Ok((command, reader))
+    }
+
+    fn redirect_stdio(&self, redirect: &CmdRedirect) -> Result<Stdio, Error> {
+        Ok(match redirect {
+            CmdRedirect::Piped => Stdio::piped(),
+            CmdRedirect::Inherit => Stdio::inherit(),
+            CmdRedirect::Null => Stdio::null(),
+            CmdRedirect::Path { path, append } => Stdio::from(self.open_redirect(path, *append)?),
+            CmdRedirect::Stdout | CmdRedirect::PipedStdout =>
+                Stdio::from(std::io::stdout().as_fd().try_clone_to_owned()?),
+        })
+    }
+
+    fn open_redirect(&self, path: &Path, append: bool) -> Result<File, Error> {
+        self.resolve_path(path).open(
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(append)
+                .truncate(!append)
+                .mode(0o644),
+        )
+    }
+
+    fn resolve_path(&self, path: &Path) -> Path {
+        match &self.current_dir {
+            Some(current_dir) if !path.to_string().starts_with('/') => current_dir.join(path),
+            _ => path.clone(),
+        }
+    }
+
+    /// `spawn` starts the subprocess and writes [`CmdInput::Bytes`]
+    /// into its stdin from a separate thread
+    pub fn spawn(&self) -> Result<Child, Error> {
+        self.spawn_with_stdin(None)
+    }
+
+    /// `spawn_with_stdin` is [`Cmd::spawn`] overriding the stdin of
+    /// the [`Cmd`], e.g.: with the stdout of a previous stage in a
+    /// [`crate::Pipeline`]
+    pub(crate) fn spawn_with_stdin(&self, stdin: Option<Stdio>) -> Result<Child, Error> {
+        let (mut command, reader) = self.build(stdin)?;
+        let mut child = command
+            .spawn()
+            .map_err(|error| traceback!(SubprocessError, "spawning {}: {}", self, error))?;
+        // drops the write ends of the pipe shared by stdout and stderr
+        drop(command);
+        if let Some(reader) = reader {
+            child.stdout = Some(ChildStdout::from(OwnedFd::from(reader)));
+        }
+        if let CmdInput::Bytes(bytes) = &self.stdin {
+            if let Some(mut stdin) = child.stdin.take() {
+                let bytes = bytes.clone();
+                std::thread::spawn(move || {
+                    let _ = stdin.write_all(&bytes);
+                });
+            }
+        }
+        Ok(child)
+    }
+
+    /// `output` runs the subprocess to completion collecting its
+    /// piped stdout and stderr
+    pub fn output(&self) -> Result<std::process::Output, Error> {
+        let child = self.spawn()?;
+        match self.timeout {
+            Some(timeout) => self.wait_with_timeout(child, timeout),
+            None => child
+                .wait_with_output()
+                .map_err(|error| traceback!(SubprocessError, "waiting for {}: {}", self, error)),
+        }
+    }
+
+    /// `status` runs the subprocess to completion and returns its
+    /// exit code, `128 + signal` when terminated by a signal
+    pub fn status(&self) -> Result<i32, Error> {
+        Ok(crate::exit_code(&self.output()?.status))
+    }
+
+    /// `run` runs the subprocess to completion returning its
+    /// [`CommandOutput`] regardless of its exit status
+    pub fn run(&self) -> Result<CommandOutput, Error> {
+        let started = Instant::now();
+        let output = self.output()?;
+        Ok(CommandOutput::new(
+            self,
+            output.status,
+            started.elapsed(),
+            output.stdout,
+            output.stderr,
+        ))
+    }
+
+    /// `check` runs the subprocess to completion returning
+    /// [`Error::SubprocessError`] carrying its [`CommandOutput`] unless
+    /// it exits successfully
+    pub fn check(&self) -> Result<CommandOutput, Error> {
+        self.run()?.check()
+    }
+
+    fn wait_with_timeout(
+        &self,
+        mut child: Child,
+        timeout: Duration,
+    ) -> Result<std::process::Output, Error> {
+        let stdout = Capture::new(child.stdout.take());
+        let stderr = Capture::new(child.stderr.take());
+        match timeout::wait_until(&mut child, Instant::now() + timeout, true)? {
+            Some(status) => Ok(std::process::Output {
+                status,
+                stdout: stdout.finish(),
+                stderr: stderr.finish(),
+            }),
+            None => {
+                let killed = self.terminate(&mut child)?;
+                if crate::shutdown_requested() {
+                    return Err(traceback!(
+                        ShutdownRequested,
+                        "{} terminated because shutdown was requested",
+                        self
+                    ));
+                }
+                Err(timeout::timed_out(self.to_string(), timeout, killed, stdout, stderr))
+            },
+        }
+    }
+
+    /// `expire` terminates `child`, spawned from this [`Cmd`] at
+    /// `started`, once its [`Cmd::timeout`] elapsed returning its exit
+    /// status, `None` if it is still within its timeout
+    pub(crate) fn expire(
+        &self,
+        child: &mut Child,
+        started: Instant,
+    ) -> Result<Option<ExitStatus>, Error> {
+        match self.timeout {
+            Some(timeout) if started.elapsed() >= timeout => {
+                self.terminate(child)?;
+                Ok(Some(child.wait().map_err(|error| {
+                    traceback!(SubprocessError, "waiting for {}: {}", self, error)
+                })?))
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// `terminate` sends the [`Cmd::timeout_signal`] then `SIGKILL`
+    /// to `child` returning whether `SIGKILL` was necessary. The
+    /// signals go to the process group of `child` when this [`Cmd`]
+    /// created it and only to `child` when it joined an existing
+    /// group through [`Cmd::process_group`].
+    pub(crate) fn terminate(&self, child: &mut Child) -> Result<bool, Error> {
+        let pid = child.id() as i32;
+        let target = match self.process_group {
+            Some(pgid) if pgid > 0 => pid,
+            _ => -pid,
+        };
+        timeout::terminate(
+            child,
+            target,
+            self.timeout_signal.unwrap_or(libc::SIGTERM),
+            self.kill_after.unwrap_or(timeout::DEFAULT_KILL_AFTER),
+        )
+    }
+
+    /// `resolve_program` searches the program within the `PATH` which
+    /// the subprocess receives, resolving relative entries against
+    /// [`Cmd::current_dir`] such that the returned path remains valid
+    /// once the subprocess changed directory
+    fn resolve_program(&self) -> Result<String, Error> {
+        if self.program.contains('/') {
+            return Ok(self.program.to_string());
+        }
+        let search_path = match self.env.get("PATH") {
+            Some(Some(path)) => Some(path.to_string()),
+            Some(None) => None,
+            None if self.env_clear => None,
+            None => crate::env::try_var("PATH").ok(),
+        }
+        .filter(|path| !path.is_empty())
+        .unwrap_or(crate::DEFAULT_SEARCH_PATH.to_string());
+        let dirs = crate::split_search_path(&search_path)
+            .into_iter()
+            .map(|dir| match &self.current_dir {
+                Some(current_dir) if !dir.is_absolute() =>
+                    Path::cwd().join(current_dir).join(dir),
+                _ => dir,
+            })
+            .collect::<Vec<Path>>();
+        Ok(crate::which_in(&self.program, &dirs)?.to_string())
+    }
+}
+impl std::fmt::Display for Cmd {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            crate::sh::join(std::iter::once(&self.program).chain(self.args.iter()))
+        )
+    }
+}
+
+/// `pipe` returns the read and write ends of a pipe which are closed
+/// on `exec`
+pub(crate) fn pipe() -> std::io::Result<(File, File)> {
+    let mut fds = [-1; 2];
+    #[cfg(target_os = "linux")]
+    let created = unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) };
+    #[cfg(not(target_os = "linux"))]
+    let created = unsafe { libc::pipe(fds.as_mut_ptr()) };
+    if created != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let [read_fd, write_fd] = fds;
+    let ends = unsafe { (File::from_raw_fd(read_fd), File::from_raw_fd(write_fd)) };
+    #[cfg(not(target_os = "linux"))]
+    for fd in fds {
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(ends)
+}
@@ -0,0 +1,122 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use crate::{Cmd, Error, Path};
+
+/// `CommandOutput` is the structured result of running a [`Cmd`] to
+/// completion. Unlike [`ExitStatus::code`], it distinguishes exiting
+/// with a code from being terminated by a signal.
+///
+/// Example
+///
+/// ```
+/// use iocore::Cmd;
+/// let output = Cmd::new("sh").args(["-c", "kill -KILL $$"]).run().unwrap();
+/// assert_eq!(output.code, None);
+/// assert_eq!(output.signal, Some(9));
+/// assert_eq!(output.exit_code(), 137);
+/// assert!(!output.success());
+/// assert!(output.check().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CommandOutput {
+    pub command: String,
+    pub current_dir: Option<Path>,
+    /// `code` is the exit code of the subprocess, `None` when it was
+    /// terminated by a signal
+    pub code: Option<i32>,
+    /// `signal` is the signal which terminated the subprocess
+    pub signal: Option<i32>,
+    pub core_dumped: bool,
+    pub duration: Duration,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+impl CommandOutput {
+    pub(crate) fn new(
+        cmd: &Cmd,
+        status: ExitStatus,
+        duration: Duration,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+    ) -> CommandOutput {
+        CommandOutput {
+            command: cmd.to_string(),
+            current_dir: cmd.get_current_dir().cloned(),
+            code: status.code(),
+            signal: status.signal(),
+            core_dumped: status.core_dumped(),
+            duration,
+            stdout,
+            stderr,
+        }
+    }
+
+    /// `exit_code` returns the exit code as a POSIX shell reports it,
+    /// see [`crate::exit_code`]
+    pub fn exit_code(&self) -> i32 {
+        crate::sh::pipeline::shell_exit_code(self.code, self.signal)
+    }
+
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// `check` returns `self` if the subprocess exited successfully
+    /// or [`Error::SubprocessError`] carrying `self` otherwise
+    pub fn check(self) -> Result<CommandOutput, Error> {
+        if self.success() {
+            Ok(self)
+        } else {
+            Err(Error::SubprocessError(SubprocessFailure::from(self)))
+        }
+    }
+}
+impl std::fmt::Display for CommandOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "command {:#?}", self.command)?;
+        if let Some(current_dir) = &self.current_dir {
+            write!(f, " in {:#?}", current_dir.to_string())?;
+        }
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, " exited with code {}", code)?,
+            (None, Some(signal)) => write!(f, " was terminated by signal {}", signal)?,
+            (None, None) => write!(f, " exited with unknown status")?,
+        }
+        if self.core_dumped {
+            write!(f, " (core dumped)")?;
+        }
+        write!(f, " after {:?}", self.duration)
+    }
+}
+
+/// `SubprocessFailure` is the payload of [`Error::SubprocessError`],
+/// `output` is set when the subprocess ran to completion but exited
+/// with a non-zero code or was terminated by a signal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubprocessFailure {
+    pub message: String,
+    pub output: Option<Box<CommandOutput>>,
+}
+impl From<String> for SubprocessFailure {
+    fn from(message: String) -> SubprocessFailure {
+        SubprocessFailure {
+            message,
+            output: None,
+        }
+    }
+}
+impl From<CommandOutput> for SubprocessFailure {
+    fn from(output: CommandOutput) -> SubprocessFailure {
+        SubprocessFailure {
+            message: output.to_string(),
+            output: Some(Box::new(output)),
+        }
+    }
+}
+impl std::fmt::Display for SubprocessFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
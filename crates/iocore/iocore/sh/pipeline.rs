@@ -0,0 +1,541 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, ExitStatus, Stdio};
+use std::time::Instant;
+
+use crate::sh::timeout::{Capture, POLL_INTERVAL};
+use crate::{traceback, Cmd, CmdInput, CmdRedirect, Error, Path};
+
+/// `NOT_EXECUTED` is the exit code of a stage which could not be
+/// spawned, as a POSIX shell reports commands which are not found
+const NOT_EXECUTED: i32 = 127;
+
+/// `exit_code` returns the exit code of `status` as a POSIX shell
+/// reports it, i.e.: `128 + signal` when terminated by a signal
+pub fn exit_code(status: &ExitStatus) -> i32 {
+    shell_exit_code(status.code(), status.signal())
+}
+
+/// `shell_exit_code` is the counterpart of [`exit_code`] for an exit
+/// `code` or terminating `signal` obtained separately
+pub(crate) fn shell_exit_code(code: Option<i32>, signal: Option<i32>) -> i32 {
+    match (code, signal) {
+        (Some(code), _) => code,
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 1,
+    }
+}
+
+/// `PipelineOutput` holds the exit status of every stage of a
+/// [`Pipeline`] along with the stdout of the last stage and the
+/// stderr of every stage in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineOutput {
+    pub statuses: Vec<ExitStatus>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+impl PipelineOutput {
+    /// `codes` returns the exit code of every stage
+    pub fn codes(&self) -> Vec<i32> {
+        self.statuses.iter().map(exit_code).collect()
+    }
+
+    /// `code` returns the exit code of the pipeline with `pipefail`
+    /// semantics, that is, the exit code of the last stage which
+    /// failed or `0` if every stage succeeded
+    pub fn code(&self) -> i32 {
+        self.codes().into_iter().rev().find(|code| *code != 0).unwrap_or_default()
+    }
+
+    pub fn success(&self) -> bool {
+        self.code() == 0
+    }
+}
+
+/// `Pipeline` connects the stdout of each [`Cmd`] to the stdin of
+/// the next without invoking a shell. A stage reading from
+/// [`CmdInput::Path`] or [`CmdInput::Bytes`] ignores the stdout of
+/// the previous one, as `a | b < file` does in a shell.
+///
+/// Example
+///
+/// ```
+/// use iocore::{Cmd, Pipeline};
+/// let output = Pipeline::new()
+///     .pipe(Cmd::new("printf").arg("b\\na\\nb\\n"))
+///     .pipe(Cmd::new("sort"))
+///     .pipe(Cmd::new("uniq"))
+///     .output()
+///     .unwrap();
+/// assert_eq!(output.stdout, b"a\nb\n");
+/// assert_eq!(output.codes(), vec![0, 0, 0]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pipeline {
+    stages: Vec<Cmd>,
+}
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// `parse` parses a pipeline, e.g.: `"grep -v '^#' < input | sort > output"`,
+    /// see [`Script::parse`] for the syntax supported.
+    pub fn parse(pipeline: impl std::fmt::Display) -> Result<Pipeline, Error> {
+        let pipeline = pipeline.to_string();
+        let mut script = Script::parse(&pipeline)?;
+        match script.items.len() {
+            1 => Ok(script.items.remove(0).1),
+            _ => Err(traceback!(ParseError, "expected a single pipeline in {:#?}", pipeline)),
+        }
+    }
+
+    pub fn pipe(mut self, cmd: Cmd) -> Pipeline {
+        self.stages.push(cmd);
+        self
+    }
+
+    pub fn stages(&self) -> &[Cmd] {
+        &self.stages
+    }
+
+    /// `current_dir` sets the working directory of every stage which
+    /// does not have one
+    pub fn current_dir(mut self, current_dir: impl Into<Path>) -> Pipeline {
+        let current_dir = current_dir.into();
+        self.stages = self
+            .stages
+            .into_iter()
+            .map(|cmd| match cmd.get_current_dir() {
+                Some(_) => cmd,
+                None => cmd.current_dir(&current_dir),
+            })
+            .collect();
+        self
+    }
+
+    /// `output` runs every stage concurrently and waits for all of
+    /// them to exit.
+    ///
+    /// As in a POSIX shell, a stage which cannot be spawned exits
+    /// with `127` and its error is written to its stderr, while the
+    /// other stages run regardless. A stage exceeding its
+    /// [`Cmd::timeout`] is terminated and exits with `128 + signal`.
+    pub fn output(&self) -> Result<PipelineOutput, Error> {
+        if self.stages.is_empty() {
+            return Err(traceback!(SubprocessError, "empty pipeline"));
+        }
+        let started = Instant::now();
+        let mut children = Vec::<Option<Child>>::new();
+        let mut statuses = Vec::<Option<ExitStatus>>::new();
+        let mut stderrs = Vec::<Capture>::new();
+        let mut stdout = Capture::with(Vec::new());
+        let mut previous_stdout: Option<Stdio> = None;
+        let last = self.stages.len() - 1;
+        for (index, stage) in self.stages.iter().enumerate() {
+            let upstream = previous_stdout.take();
+            // as in a shell, a stage's own input redirection wins over
+            // the pipe, which is then closed
+            let stdin = match stage.get_stdin() {
+                CmdInput::Path(_) | CmdInput::Bytes(_) => None,
+                _ if index == 0 => None,
+                _ => Some(upstream.unwrap_or(Stdio::null())),
+            };
+            match stage.spawn_with_stdin(stdin) {
+                Ok(mut child) => {
+                    stderrs.push(Capture::new(child.stderr.take()));
+                    if index < last {
+                        previous_stdout = child.stdout.take().map(Stdio::from);
+                    } else {
+                        stdout = Capture::new(child.stdout.take());
+                    }
+                    children.push(Some(child));
+                    statuses.push(None);
+                },
+                Err(error) => {
+                    stderrs.push(Capture::with(format!("{}\n", error).into_bytes()));
+                    children.push(None);
+                    statuses.push(Some(ExitStatus::from_raw(NOT_EXECUTED << 8)));
+                },
+            }
+        }
+        while statuses.iter().any(Option::is_none) {
+            for ((child, status), stage) in
+                children.iter_mut().zip(statuses.iter_mut()).zip(self.stages.iter())
+            {
+                let (Some(child), None) = (child, &status) else {
+                    continue;
+                };
+                *status = match child.try_wait() {
+                    Ok(Some(exited)) => Some(exited),
+                    Ok(None) => stage.expire(child, started)?,
+                    Err(error) =>
+                        return Err(traceback!(SubprocessError, "waiting for {}: {}", stage, error)),
+                };
+            }
+            if statuses.iter().any(Option::is_none) {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+        Ok(PipelineOutput {
+            statuses: statuses.into_iter().flatten().collect(),
+            stdout: stdout.finish(),
+            stderr: stderrs.into_iter().flat_map(|stderr| stderr.finish()).collect(),
+        })
+    }
+}
+impl std::fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let stages = self.stages.iter().map(|stage| stage.to_string()).collect::<Vec<String>>();
+        write!(f, "{}", stages.join(" | "))
+    }
+}
+
+/// `Connector` determines whether a pipeline of a [`Script`] runs
+/// depending on the exit code of the previous one
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Connector {
+    /// `;` or the first pipeline of a script
+    Always,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+}
+
+/// `ScriptOutput` holds the output of every pipeline of a [`Script`]
+/// which ran
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ScriptOutput {
+    pub pipelines: Vec<PipelineOutput>,
+}
+impl ScriptOutput {
+    /// `code` returns the exit code of the last pipeline which ran
+    pub fn code(&self) -> i32 {
+        self.pipelines.last().map(|output| output.code()).unwrap_or_default()
+    }
+
+    pub fn success(&self) -> bool {
+        self.code() == 0
+    }
+
+    pub fn stdout(&self) -> Vec<u8> {
+        self.pipelines.iter().flat_map(|output| output.stdout.clone()).collect()
+    }
+
+    pub fn stderr(&self) -> Vec<u8> {
+        self.pipelines.iter().flat_map(|output| output.stderr.clone()).collect()
+    }
+}
+
+/// `Script` is a list of [`Pipeline`]s parsed from a safe subset of
+/// the POSIX shell syntax, which is never passed to a shell:
+///
+/// - words with `'single'` or `"double"` quotes and `\` escapes
+/// - `|` pipes, `&&`, `||` and `;` or newline separated lists
+/// - `< file`, `> file`, `>> file` and `2>&1` redirections applied
+///   from left to right
+/// - `#` comments up to the end of the line
+///
+/// Anything else the shell would interpret, such as `$VAR`, `*` or
+/// `~`, is passed literally whereas `&`, `(`, `)`, `` ` ``, `$(` and
+/// other redirections are rejected.
+///
+/// Example
+///
+/// ```
+/// use iocore::Script;
+/// let output = Script::parse("false && echo skipped; echo '$HOME' | tr a-z A-Z || echo skipped")
+///     .unwrap()
+///     .output()
+///     .unwrap();
+/// assert_eq!(output.stdout(), b"$HOME\n");
+/// assert_eq!(output.pipelines.len(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Script {
+    items: Vec<(Connector, Pipeline)>,
+}
+impl Script {
+    pub fn parse(script: impl std::fmt::Display) -> Result<Script, Error> {
+        let script = script.to_string();
+        let tokens = tokenize(&script)?;
+        let mut items = Vec::<(Connector, Pipeline)>::new();
+        let mut connector = Connector::Always;
+        let mut pipeline = Pipeline::new();
+        let mut words = Vec::<String>::new();
+        let mut redirects = Vec::<(Operator, String)>::new();
+        let mut tokens = tokens.into_iter().peekable();
+        let unexpected = |what: &str| traceback!(ParseError, "{} in {:#?}", what, script);
+        loop {
+            let token = tokens.next();
+            match token {
+                Some(Token::Word(word)) => words.push(word),
+                Some(Token::Operator(
+                    operator @ (Operator::Input | Operator::Output | Operator::Append),
+                )) => match tokens.next() {
+                    Some(Token::Word(path)) => redirects.push((operator, path)),
+                    _ => return Err(unexpected(&format!("expected a path after {}", operator))),
+                },
+                Some(Token::Operator(Operator::StderrToStdout)) =>
+                    redirects.push((Operator::StderrToStdout, String::new())),
+                Some(Token::Operator(_)) | None => {
+                    if words.is_empty() {
+                        if redirects.is_empty()
+                            && pipeline.stages.is_empty()
+                            && token.is_none()
+                            && (items.is_empty() || connector == Connector::Always)
+                        {
+                            break;
+                        }
+                        return Err(unexpected(&match &token {
+                            Some(Token::Operator(operator)) => format!("unexpected {}", operator),
+                            _ => "unexpected end of script".to_string(),
+                        }));
+                    }
+                    pipeline = pipeline.pipe(command(&words, &redirects));
+                    words.clear();
+                    redirects.clear();
+                    let next = match token {
+                        Some(Token::Operator(Operator::Pipe)) => continue,
+                        Some(Token::Operator(Operator::And)) => Connector::And,
+                        Some(Token::Operator(Operator::Or)) => Connector::Or,
+                        _ => Connector::Always,
+                    };
+                    items.push((connector, pipeline));
+                    pipeline = Pipeline::new();
+                    connector = next;
+                    if token.is_none() {
+                        break;
+                    }
+                },
+            }
+        }
+        if items.is_empty() {
+            return Err(unexpected("empty script"));
+        }
+        Ok(Script { items })
+    }
+
+    pub fn pipelines(&self) -> Vec<&Pipeline> {
+        self.items.iter().map(|(_, pipeline)| pipeline).collect()
+    }
+
+    pub fn items(&self) -> &[(Connector, Pipeline)] {
+        &self.items
+    }
+
+    /// `current_dir` sets the working directory of every command
+    /// which does not have one
+    pub fn current_dir(mut self, current_dir: impl Into<Path>) -> Script {
+        let current_dir = current_dir.into();
+        self.items = self
+            .items
+            .into_iter()
+            .map(|(connector, pipeline)| (connector, pipeline.current_dir(&current_dir)))
+            .collect();
+        self
+    }
+
+    /// `output` runs the pipelines in order skipping those whose
+    /// [`Connector`] does not match the exit code of the previous one
+    pub fn output(&self) -> Result<ScriptOutput, Error> {
+        let mut output = ScriptOutput::default();
+        let mut success = true;
+        for (connector, pipeline) in &self.items {
+            let run = match connector {
+                Connector::Always => true,
+                Connector::And => success,
+                Connector::Or => !success,
+            };
+            if run {
+                let pipeline_output = pipeline.output()?;
+                success = pipeline_output.success();
+                output.pipelines.push(pipeline_output);
+            }
+        }
+        Ok(output)
+    }
+}
+impl std::fmt::Display for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (index, (connector, pipeline)) in self.items.iter().enumerate() {
+            match (index, connector) {
+                (0, _) => {},
+                (_, Connector::Always) => write!(f, "; ")?,
+                (_, Connector::And) => write!(f, " && ")?,
+                (_, Connector::Or) => write!(f, " || ")?,
+            }
+            write!(f, "{}", pipeline)?;
+        }
+        Ok(())
+    }
+}
+
+/// `command` applies `redirects` from left to right as the shell
+/// does, hence `2>&1` points stderr to wherever stdout goes at that
+/// point, e.g.: `2>&1 > file` keeps stderr in the pipe.
+fn command(words: &[String], redirects: &[(Operator, String)]) -> Cmd {
+    let mut cmd = Cmd::new(&words[0]).args(&words[1..]);
+    let mut stdout = CmdRedirect::Piped;
+    let mut stderr = CmdRedirect::Piped;
+    for (operator, path) in redirects {
+        match operator {
+            Operator::Input => cmd = cmd.stdin_path(path),
+            Operator::Output | Operator::Append =>
+                stdout = CmdRedirect::Path {
+                    path: Path::raw(path),
+                    append: *operator == Operator::Append,
+                },
+            _ =>
+                stderr = match &stdout {
+                    CmdRedirect::Piped => CmdRedirect::PipedStdout,
+                    stdout => stdout.clone(),
+                },
+        };
+    }
+    let stderr = match stderr {
+        CmdRedirect::PipedStdout if stdout == CmdRedirect::Piped => CmdRedirect::Stdout,
+        stderr @ CmdRedirect::Path { .. } if stderr == stdout => CmdRedirect::Stdout,
+        stderr => stderr,
+    };
+    cmd.stdout(stdout).stderr(stderr)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Operator {
+    Pipe,
+    And,
+    Or,
+    Sequence,
+    Input,
+    Output,
+    Append,
+    StderrToStdout,
+}
+impl std::fmt::Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:#?}",
+            match self {
+                Operator::Pipe => "|",
+                Operator::And => "&&",
+                Operator::Or => "||",
+                Operator::Sequence => ";",
+                Operator::Input => "<",
+                Operator::Output => ">",
+                Operator::Append => ">>",
+                Operator::StderrToStdout => "2>&1",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Operator(Operator),
+}
+
+fn tokenize(script: &str) -> Result<Vec<Token>, Error> {
+    let chars = script.chars().collect::<Vec<char>>();
+    let mut tokens = Vec::<Token>::new();
+    let mut word: Option<String> = None;
+    let mut index = 0;
+    let unsupported = |what: &str, index: usize| {
+        traceback!(ParseError, "unsupported {} at {} of {:#?}", what, index, script)
+    };
+    while index < chars.len() {
+        let c = chars[index];
+        let next = chars.get(index + 1).copied();
+        let operator = match (c, next) {
+            ('|', Some('|')) => Some((Operator::Or, 2)),
+            ('|', _) => Some((Operator::Pipe, 1)),
+            ('&', Some('&')) => Some((Operator::And, 2)),
+            (';', _) => Some((Operator::Sequence, 1)),
+            ('<', Some('<' | '&' | '>')) => return Err(unsupported("redirection", index)),
+            ('<', _) => Some((Operator::Input, 1)),
+            ('>', Some('>')) => Some((Operator::Append, 2)),
+            ('>', Some('&' | '|')) => return Err(unsupported("redirection", index)),
+            ('>', _) => Some((Operator::Output, 1)),
+            ('2', Some('>')) if word.is_none() => {
+                if chars[index..].starts_with(&['2', '>', '&', '1']) {
+                    Some((Operator::StderrToStdout, 4))
+                } else {
+                    return Err(unsupported("redirection", index));
+                }
+            },
+            ('&' | '(' | ')' | '`', _) => return Err(unsupported(&format!("{:#?}", c), index)),
+            ('$', Some('(')) => return Err(unsupported("command substitution", index)),
+            _ => None,
+        };
+        if let Some((operator, length)) = operator {
+            if let Some(word) = word.take() {
+                tokens.push(Token::Word(word));
+            }
+            tokens.push(Token::Operator(operator));
+            index += length;
+            continue;
+        }
+        match c {
+            '\n' => {
+                if let Some(word) = word.take() {
+                    tokens.push(Token::Word(word));
+                }
+                if let Some(Token::Word(_)) = tokens.last() {
+                    tokens.push(Token::Operator(Operator::Sequence));
+                }
+            },
+            '#' if word.is_none() =>
+                while chars.get(index + 1).is_some_and(|c| *c != '\n') {
+                    index += 1;
+                },
+            c if c.is_whitespace() =>
+                if let Some(word) = word.take() {
+                    tokens.push(Token::Word(word));
+                },
+            '\'' => {
+                let end = chars[index + 1..]
+                    .iter()
+                    .position(|c| *c == '\'')
+                    .ok_or_else(|| unsupported("unterminated single quote", index))?;
+                word.get_or_insert_with(String::new).extend(&chars[index + 1..index + 1 + end]);
+                index += end + 1;
+            },
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                index += 1;
+                loop {
+                    match (chars.get(index), chars.get(index + 1)) {
+                        (None, _) => return Err(unsupported("unterminated double quote", index)),
+                        (Some('"'), _) => break,
+                        (Some('\\'), Some(escaped @ ('"' | '\\' | '$' | '`'))) => {
+                            word.push(*escaped);
+                            index += 1;
+                        },
+                        (Some('`'), _) => return Err(unsupported("'`'", index)),
+                        (Some('$'), Some('(')) =>
+                            return Err(unsupported("command substitution", index)),
+                        (Some(c), _) => word.push(*c),
+                    }
+                    index += 1;
+                }
+            },
+            '\\' => {
+                match next {
+                    Some('\n') => {},
+                    Some(escaped) => word.get_or_insert_with(String::new).push(escaped),
+                    None => return Err(unsupported("trailing backslash", index)),
+                }
+                index += 1;
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+        index += 1;
+    }
+    if let Some(word) = word.take() {
+        tokens.push(Token::Word(word));
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,317 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+
+use regex::bytes::Regex;
+use sanitation::SString;
+
+use crate::{traceback, Cmd, CommandOutput, Error};
+
+/// `WindowSize` is the size of a pseudo-terminal in characters
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WindowSize {
+    pub rows: u16,
+    pub cols: u16,
+}
+impl WindowSize {
+    pub fn new(rows: u16, cols: u16) -> WindowSize {
+        WindowSize { rows, cols }
+    }
+
+    fn to_winsize(self) -> libc::winsize {
+        libc::winsize {
+            ws_row: self.rows,
+            ws_col: self.cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+impl Default for WindowSize {
+    fn default() -> WindowSize {
+        WindowSize::new(24, 80)
+    }
+}
+
+/// `PtyMatch` is the result of [`PtyChild::expect`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PtyMatch {
+    /// `before` is the output preceding the match
+    pub before: String,
+    /// `matched` is the text matched by the pattern
+    pub matched: String,
+    /// `groups` are the capture groups of the pattern
+    pub groups: Vec<Option<String>>,
+}
+
+/// `PtyChild` is a subprocess whose stdin, stdout and stderr are the
+/// slave side of a pseudo-terminal, the master side of which is read
+/// and written through the `PtyChild`.
+///
+/// Example
+///
+/// ```
+/// use std::time::Duration;
+/// use iocore::{Cmd, WindowSize};
+/// let mut child = Cmd::new("cat").spawn_pty(WindowSize::default()).unwrap();
+/// child.send_line("hello").unwrap();
+/// let found = child.expect(r"hello\r\n", Duration::from_secs(5)).unwrap();
+/// assert_eq!(found.matched, "hello\r\n");
+/// child.send_eof().unwrap();
+/// assert!(child.wait().unwrap().success());
+/// ```
+#[derive(Debug)]
+pub struct PtyChild {
+    cmd: Cmd,
+    child: Child,
+    master: File,
+    buffer: Vec<u8>,
+    eof: bool,
+    started: Instant,
+}
+impl PtyChild {
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// `master` returns the master side of the pseudo-terminal
+    pub fn master(&mut self) -> &mut File {
+        &mut self.master
+    }
+
+    pub fn window_size(&self) -> Result<WindowSize, Error> {
+        let mut winsize = WindowSize::default().to_winsize();
+        if unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCGWINSZ, &mut winsize) } != 0 {
+            return Err(traceback!(
+                SubprocessError,
+                "reading window size of {}: {}",
+                self.cmd,
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(WindowSize::new(winsize.ws_row, winsize.ws_col))
+    }
+
+    /// `set_window_size` resizes the pseudo-terminal which sends
+    /// `SIGWINCH` to its foreground process group
+    pub fn set_window_size(&mut self, size: WindowSize) -> Result<(), Error> {
+        let winsize = size.to_winsize();
+        if unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &winsize) } != 0 {
+            return Err(traceback!(
+                SubprocessError,
+                "setting window size of {}: {}",
+                self.cmd,
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn send(&mut self, bytes: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.master
+            .write_all(bytes.as_ref())
+            .and_then(|_| self.master.flush())
+            .map_err(|error| traceback!(SubprocessError, "writing to {}: {}", self.cmd, error))
+    }
+
+    pub fn send_line(&mut self, line: impl std::fmt::Display) -> Result<(), Error> {
+        self.send(format!("{}\n", line))
+    }
+
+    /// `send_eof` sends the end-of-file character, i.e.: `^D`
+    pub fn send_eof(&mut self) -> Result<(), Error> {
+        self.send([4u8])
+    }
+
+    /// `read` waits up to `timeout` for output returning everything
+    /// read along with any output left unmatched by
+    /// [`PtyChild::expect`], an empty result means either timeout or
+    /// end of output.
+    pub fn read(&mut self, timeout: Duration) -> Result<Vec<u8>, Error> {
+        if self.buffer.is_empty() {
+            self.fill(Instant::now() + timeout)?;
+        }
+        Ok(std::mem::take(&mut self.buffer))
+    }
+
+    /// `expect` waits up to `timeout` for the output to match the
+    /// regular expression `pattern` consuming the output up to the
+    /// end of the match
+    pub fn expect(&mut self, pattern: &str, timeout: Duration) -> Result<PtyMatch, Error> {
+        let regex = Regex::new(pattern)
+            .map_err(|error| traceback!(ParseError, "invalid pattern {:#?}: {}", pattern, error))?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(captures) = regex.captures(&self.buffer) {
+                let found = captures.get(0).expect("group 0 is always present");
+                let pty_match = PtyMatch {
+                    before: lossy(&self.buffer[..found.start()]),
+                    matched: lossy(found.as_bytes()),
+                    groups: captures
+                        .iter()
+                        .skip(1)
+                        .map(|group| group.map(|group| lossy(group.as_bytes())))
+                        .collect(),
+                };
+                self.buffer.drain(..found.end());
+                return Ok(pty_match);
+            }
+            if self.eof || Instant::now() >= deadline {
+                return Err(traceback!(
+                    PatternMismatch,
+                    "{} waiting for {:#?} in output of {}: {:#?}",
+                    if self.eof {
+                        "end of output".to_string()
+                    } else {
+                        format!("timed out after {:?}", timeout)
+                    },
+                    pattern,
+                    self.cmd,
+                    lossy(&self.buffer)
+                ));
+            }
+            self.fill(deadline)?;
+        }
+    }
+
+    /// `wait` reads the output until the slave side of the
+    /// pseudo-terminal is closed then waits for the subprocess to
+    /// exit, `stdout` holds the output not consumed by
+    /// [`PtyChild::read`] or [`PtyChild::expect`].
+    pub fn wait(mut self) -> Result<CommandOutput, Error> {
+        while !self.eof {
+            self.fill(Instant::now() + Duration::from_secs(1))?;
+        }
+        let status = self
+            .child
+            .wait()
+            .map_err(|error| traceback!(SubprocessError, "waiting for {}: {}", self.cmd, error))?;
+        Ok(CommandOutput::new(
+            &self.cmd,
+            status,
+            self.started.elapsed(),
+            std::mem::take(&mut self.buffer),
+            Vec::new(),
+        ))
+    }
+
+    /// `fill` appends available output to the buffer waiting up to
+    /// `deadline` for it
+    fn fill(&mut self, deadline: Instant) -> Result<(), Error> {
+        if self.eof {
+            return Ok(());
+        }
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let mut pollfd = libc::pollfd {
+            fd: self.master.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready =
+            unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis().min(i32::MAX as u128) as i32) };
+        if ready < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                return Ok(());
+            }
+            return Err(traceback!(SubprocessError, "polling output of {}: {}", self.cmd, error));
+        }
+        if ready == 0 {
+            return Ok(());
+        }
+        let mut chunk = [0u8; 8192];
+        match self.master.read(&mut chunk) {
+            Ok(0) => self.eof = true,
+            Ok(count) => self.buffer.extend_from_slice(&chunk[..count]),
+            // linux reports a closed slave side as EIO
+            Err(error) if error.raw_os_error() == Some(libc::EIO) => self.eof = true,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {},
+            Err(error) =>
+                return Err(traceback!(
+                    SubprocessError,
+                    "reading output of {}: {}",
+                    self.cmd,
+                    error
+                )),
+        }
+        Ok(())
+    }
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    SString::new(bytes).unchecked_safe()
+}
+
+fn set_cloexec(fd: &OwnedFd) {
+    unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+}
+
+impl Cmd {
+    /// `spawn_pty` starts the subprocess in a new session whose
+    /// controlling terminal is a pseudo-terminal of the given size,
+    /// the stdio redirections and process group of the [`Cmd`] are
+    /// ignored.
+    pub fn spawn_pty(&self, size: WindowSize) -> Result<PtyChild, Error> {
+        let mut master = -1;
+        let mut slave = -1;
+        let mut winsize = size.to_winsize();
+        if unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::addr_of_mut!(winsize),
+            )
+        } != 0
+        {
+            return Err(traceback!(
+                SubprocessError,
+                "openpty for {}: {}",
+                self,
+                std::io::Error::last_os_error()
+            ));
+        }
+        let master = unsafe { OwnedFd::from_raw_fd(master) };
+        let slave = unsafe { OwnedFd::from_raw_fd(slave) };
+        set_cloexec(&master);
+        set_cloexec(&slave);
+
+        let mut command = self.to_session_command()?;
+        command.stdin(Stdio::from(slave.try_clone()?));
+        command.stdout(Stdio::from(slave.try_clone()?));
+        command.stderr(Stdio::from(slave));
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if libc::ioctl(0, libc::TIOCSCTTY as _, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let started = Instant::now();
+        let child = command
+            .spawn()
+            .map_err(|error| traceback!(SubprocessError, "spawning {}: {}", self, error))?;
+        // drops the slave side held by `command`
+        drop(command);
+        Ok(PtyChild {
+            cmd: self.clone(),
+            child,
+            master: File::from(master),
+            buffer: Vec::new(),
+            eof: false,
+            started,
+        })
+    }
+}
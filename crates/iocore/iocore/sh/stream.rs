@@ -0,0 +1,278 @@
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, ExitStatus};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::time::{Instant, SystemTime};
+
+use sanitation::SString;
+use serde::{Deserialize, Serialize};
+
+use crate::sh::timeout::{self, CAPTURE_GRACE_PERIOD};
+use crate::{traceback, Cmd, Error, PathDateTime, SubprocessTimeout};
+
+/// `OutputStream` identifies the stream which an [`OutputLine`] was
+/// read from
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+impl std::fmt::Display for OutputStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OutputStream::Stdout => write!(f, "stdout"),
+            OutputStream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+/// `OutputLine` is a line of output of a subprocess without its
+/// trailing newline, sanitized with [`SString::safe`]. When the line
+/// is not valid UTF-8, `line` holds its valid bytes only and `safe`
+/// is false.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct OutputLine {
+    pub stream: OutputStream,
+    pub time: PathDateTime,
+    pub line: String,
+    pub safe: bool,
+}
+impl OutputLine {
+    fn new(stream: OutputStream, mut bytes: Vec<u8>) -> OutputLine {
+        if bytes.last() == Some(&b'\n') {
+            bytes.pop();
+        }
+        let sstring = SString::new(&bytes);
+        let (line, safe) = match sstring.safe() {
+            Ok(line) => (line, true),
+            Err(_) => (sstring.unchecked_safe(), false),
+        };
+        OutputLine {
+            stream,
+            time: PathDateTime::from(SystemTime::now()),
+            line,
+            safe,
+        }
+    }
+}
+impl std::fmt::Display for OutputLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.time.to_rfc3339(), self.stream, self.line)
+    }
+}
+
+/// `StreamedOutput` holds the exit status and the merged log of
+/// stdout and stderr lines of a [`StreamingChild`] in order of
+/// arrival
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamedOutput {
+    pub status: ExitStatus,
+    pub log: Vec<OutputLine>,
+}
+impl StreamedOutput {
+    pub fn stdout_lines(&self) -> Vec<String> {
+        self.lines(OutputStream::Stdout)
+    }
+
+    pub fn stderr_lines(&self) -> Vec<String> {
+        self.lines(OutputStream::Stderr)
+    }
+
+    /// `merged_log` returns every line of the log prefixed with its
+    /// timestamp and stream
+    pub fn merged_log(&self) -> String {
+        self.log.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    fn lines(&self, stream: OutputStream) -> Vec<String> {
+        self.log
+            .iter()
+            .filter(|line| line.stream == stream)
+            .map(|line| line.line.to_string())
+            .collect()
+    }
+}
+
+/// `StreamingChild` is a running subprocess whose stdout and stderr
+/// lines are delivered as they arrive, either by iterating over it
+/// or through the callback of [`StreamingChild::wait_with`].
+///
+/// Once the [`Cmd::timeout`] elapses the subprocess is terminated as
+/// by [`Cmd::output`], iteration stops shortly after and
+/// [`StreamingChild::wait`] returns `Error::SubprocessTimeout`.
+///
+/// Example
+///
+/// ```
+/// use iocore::{Cmd, OutputStream};
+/// let mut child = Cmd::new("sh")
+///     .args(["-c", "echo one; sleep 0.1; echo two >&2; echo three"])
+///     .spawn_streaming()
+///     .unwrap();
+/// let first = child.next().unwrap();
+/// assert_eq!(first.stream, OutputStream::Stdout);
+/// assert_eq!(first.line, "one");
+/// let output = child.wait().unwrap();
+/// assert!(output.status.success());
+/// assert_eq!(output.stdout_lines(), vec!["one", "three"]);
+/// assert_eq!(output.stderr_lines(), vec!["two"]);
+/// ```
+#[derive(Debug)]
+pub struct StreamingChild {
+    cmd: Cmd,
+    child: Child,
+    receiver: Receiver<OutputLine>,
+    log: Vec<OutputLine>,
+    deadline: Option<Instant>,
+    killed: Option<bool>,
+}
+impl StreamingChild {
+    pub(crate) fn new(cmd: &Cmd, mut child: Child) -> StreamingChild {
+        let (sender, receiver) = mpsc::channel::<OutputLine>();
+        if let Some(stdout) = child.stdout.take() {
+            read_lines(OutputStream::Stdout, stdout, sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            read_lines(OutputStream::Stderr, stderr, sender);
+        }
+        StreamingChild {
+            cmd: cmd.clone(),
+            child,
+            receiver,
+            log: Vec::new(),
+            deadline: cmd.get_timeout().map(|timeout| Instant::now() + timeout),
+            killed: None,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// `try_next` returns the next line if one is available without
+    /// blocking
+    pub fn try_next(&mut self) -> Option<OutputLine> {
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.expire();
+        }
+        match self.receiver.try_recv() {
+            Ok(line) => {
+                self.log.push(line.clone());
+                Some(line)
+            },
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => None,
+        }
+    }
+
+    /// `wait` consumes the remaining lines then waits for the
+    /// subprocess to exit
+    pub fn wait(self) -> Result<StreamedOutput, Error> {
+        self.wait_with(|_| {})
+    }
+
+    /// `wait_with` calls `callback` with each remaining line as it
+    /// arrives then waits for the subprocess to exit
+    pub fn wait_with(
+        mut self,
+        mut callback: impl FnMut(&OutputLine),
+    ) -> Result<StreamedOutput, Error> {
+        for line in self.by_ref() {
+            callback(&line);
+        }
+        if let (Some(deadline), None) = (self.deadline, self.killed) {
+            if timeout::wait_until(&mut self.child, deadline, false)?.is_none() {
+                self.expire();
+            }
+        }
+        if let (Some(timeout), Some(killed)) = (self.cmd.get_timeout(), self.killed) {
+            return Err(Error::SubprocessTimeout(SubprocessTimeout {
+                command: self.cmd.to_string(),
+                timeout,
+                killed,
+                stdout: self.output_of(OutputStream::Stdout),
+                stderr: self.output_of(OutputStream::Stderr),
+            }));
+        }
+        let status = self
+            .child
+            .wait()
+            .map_err(|error| traceback!(SubprocessError, "waiting for {}: {}", self.cmd, error))?;
+        Ok(StreamedOutput {
+            status,
+            log: self.log,
+        })
+    }
+
+    /// `expire` terminates the subprocess once its timeout elapsed
+    fn expire(&mut self) {
+        if self.killed.is_none() {
+            self.killed = Some(self.cmd.terminate(&mut self.child).unwrap_or(true));
+        }
+    }
+
+    fn output_of(&self, stream: OutputStream) -> Vec<u8> {
+        self.log
+            .iter()
+            .filter(|line| line.stream == stream)
+            .flat_map(|line| format!("{}\n", line.line).into_bytes())
+            .collect()
+    }
+}
+impl Iterator for StreamingChild {
+    type Item = OutputLine;
+
+    /// `next` blocks until a line arrives returning `None` once both
+    /// stdout and stderr are closed or shortly after the timeout
+    /// elapsed
+    fn next(&mut self) -> Option<OutputLine> {
+        loop {
+            let received = match (self.deadline, self.killed) {
+                (_, Some(_)) => self.receiver.recv_timeout(CAPTURE_GRACE_PERIOD),
+                (Some(deadline), None) =>
+                    self.receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                (None, None) => self.receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(line) => {
+                    self.log.push(line.clone());
+                    return Some(line);
+                },
+                Err(RecvTimeoutError::Timeout) if self.killed.is_none() => self.expire(),
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+fn read_lines(stream: OutputStream, pipe: impl Read + Send + 'static, sender: Sender<OutputLine>) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        loop {
+            let mut bytes = Vec::<u8>::new();
+            match reader.read_until(b'\n', &mut bytes) {
+                Ok(0) | Err(_) => break,
+                Ok(_) =>
+                    if sender.send(OutputLine::new(stream, bytes)).is_err() {
+                        break;
+                    },
+            }
+        }
+    });
+}
+
+impl Cmd {
+    /// `spawn_streaming` starts the subprocess delivering the lines
+    /// of its piped stdout and stderr through a [`StreamingChild`]
+    pub fn spawn_streaming(&self) -> Result<StreamingChild, Error> {
+        Ok(StreamingChild::new(self, self.spawn()?))
+    }
+
+    /// `stream` runs the subprocess to completion calling `callback`
+    /// with each line of its piped stdout and stderr as it arrives
+    pub fn stream(&self, callback: impl FnMut(&OutputLine)) -> Result<StreamedOutput, Error> {
+        self.spawn_streaming()?.wait_with(callback)
+    }
+}
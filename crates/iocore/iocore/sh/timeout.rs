@@ -0,0 +1,168 @@
+use std::io::Read;
+use std::process::{Child, ExitStatus};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::{traceback, Error};
+
+/// `DEFAULT_KILL_AFTER` is the grace period between the timeout
+/// signal and `SIGKILL`
+pub const DEFAULT_KILL_AFTER: Duration = Duration::from_secs(5);
+
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(10);
+pub(crate) const CAPTURE_GRACE_PERIOD: Duration = Duration::from_millis(100);
+
+/// `SubprocessTimeout` is carried by `Error::SubprocessTimeout` when
+/// a subprocess exceeds its timeout, `stdout` and `stderr` hold the
+/// output collected until the subprocess was terminated.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubprocessTimeout {
+    pub command: String,
+    pub timeout: Duration,
+    /// `killed` is true when the subprocess outlived the grace
+    /// period and received `SIGKILL`
+    pub killed: bool,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+impl std::fmt::Display for SubprocessTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "command {:#?} timed out after {:?}{}",
+            self.command,
+            self.timeout,
+            if self.killed { " and was killed" } else { "" }
+        )
+    }
+}
+
+/// `Capture` reads a pipe into a shared buffer from a separate thread
+/// such that partial output remains available
+pub(crate) struct Capture {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    handle: Option<JoinHandle<()>>,
+}
+impl Capture {
+    pub(crate) fn new(pipe: Option<impl Read + Send + 'static>) -> Capture {
+        let buffer = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let handle = pipe.map(|mut pipe| {
+            let buffer = buffer.clone();
+            std::thread::spawn(move || {
+                let mut chunk = [0u8; 8192];
+                while let Ok(count) = pipe.read(&mut chunk) {
+                    if count == 0 {
+                        break;
+                    }
+                    if let Ok(mut buffer) = buffer.lock() {
+                        buffer.extend_from_slice(&chunk[..count]);
+                    }
+                }
+            })
+        });
+        Capture { buffer, handle }
+    }
+
+    /// `with` returns a [`Capture`] of `bytes` which does not read
+    /// from any pipe
+    pub(crate) fn with(bytes: Vec<u8>) -> Capture {
+        Capture {
+            buffer: Arc::new(Mutex::new(bytes)),
+            handle: None,
+        }
+    }
+
+    /// `finish` waits for the pipe to close then returns everything
+    /// read from it
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        self.snapshot()
+    }
+
+    /// `finish_within` waits up to `grace_period` for the pipe to
+    /// close, e.g.: when descendants of a killed process still hold
+    /// it, then returns what was read so far
+    pub(crate) fn finish_within(mut self, grace_period: Duration) -> Vec<u8> {
+        let deadline = Instant::now() + grace_period;
+        if let Some(handle) = self.handle.take() {
+            while !handle.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            if handle.is_finished() {
+                let _ = handle.join();
+            }
+        }
+        self.snapshot()
+    }
+
+    fn snapshot(&self) -> Vec<u8> {
+        self.buffer.lock().map(|buffer| buffer.clone()).unwrap_or_default()
+    }
+}
+
+/// `wait_until` waits for `child` to exit until `deadline` returning
+/// `None` if it is still running. When `interruptible` it also
+/// returns `None` once [`crate::shutdown_requested`].
+pub(crate) fn wait_until(
+    child: &mut Child,
+    deadline: Instant,
+    interruptible: bool,
+) -> Result<Option<ExitStatus>, Error> {
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(Some(status)),
+            Ok(None) if Instant::now() >= deadline => return Ok(None),
+            Ok(None) if interruptible && crate::shutdown_requested() => return Ok(None),
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(error) =>
+                return Err(traceback!(
+                    SubprocessError,
+                    "waiting for pid {}: {}",
+                    child.id(),
+                    error
+                )),
+        }
+    }
+}
+
+/// `terminate` sends `signal` to `target`, a pid or a negated
+/// process group id as accepted by `kill(2)`, waits up to
+/// `kill_after` for `child` then sends `SIGKILL` to `target`
+/// returning whether `SIGKILL` was necessary
+pub(crate) fn terminate(
+    child: &mut Child,
+    target: i32,
+    signal: i32,
+    kill_after: Duration,
+) -> Result<bool, Error> {
+    unsafe { libc::kill(target, signal) };
+    if wait_until(child, Instant::now() + kill_after, false)?.is_some() {
+        return Ok(false);
+    }
+    unsafe { libc::kill(target, libc::SIGKILL) };
+    child.wait().map_err(|error| {
+        traceback!(SubprocessError, "waiting for pid {}: {}", child.id(), error)
+    })?;
+    Ok(true)
+}
+
+/// `timed_out` waits briefly for the captured pipes and returns the
+/// `Error::SubprocessTimeout` carrying their partial output
+pub(crate) fn timed_out(
+    command: String,
+    timeout: Duration,
+    killed: bool,
+    stdout: Capture,
+    stderr: Capture,
+) -> Error {
+    Error::SubprocessTimeout(SubprocessTimeout {
+        command,
+        timeout,
+        killed,
+        stdout: stdout.finish_within(CAPTURE_GRACE_PERIOD),
+        stderr: stderr.finish_within(CAPTURE_GRACE_PERIOD),
+    })
+}
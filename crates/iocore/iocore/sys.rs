@@ -1,6 +1,16 @@
+pub(crate) mod app_dirs;
+pub(crate) mod daemon;
+pub(crate) mod home;
+pub(crate) mod info;
+pub(crate) mod passwd;
+pub(crate) mod process;
+pub(crate) mod signals;
+pub(crate) mod userdb;
+pub(crate) mod which;
 use std::process::{Command, Stdio};
 use std::str::FromStr;
 
+use home::HomePolicy;
 use regex::Regex;
 use sanitation::{from_hex, SBoolean, SString};
 
@@ -13,6 +23,38 @@ pub struct Group {
     pub gid: u32,
     pub name: String,
 }
+impl Group {
+    /// `by_gid` looks up the group whose gid is `gid` in the group
+    /// database through `getgrgid_r(3)` falling back to `/etc/group`
+    pub fn by_gid(gid: u32) -> Result<Group, Error> {
+        let entry = userdb::group_by_gid(gid)
+            .ok_or_else(|| traceback!(SystemError, "group not found for gid {}", gid))?;
+        Ok(Group {
+            gid: entry.gid,
+            name: entry.name,
+        })
+    }
+
+    /// `by_name` looks up the group named `name` in the group
+    /// database through `getgrnam_r(3)` falling back to `/etc/group`
+    pub fn by_name(name: &str) -> Result<Group, Error> {
+        let entry = userdb::group_by_name(name)
+            .ok_or_else(|| traceback!(SystemError, "group not found: {:#?}", name))?;
+        Ok(Group {
+            gid: entry.gid,
+            name: entry.name,
+        })
+    }
+
+    /// `members` returns the names of the users listed as
+    /// supplementary members of the group, users whose primary group
+    /// is this group are not necessarily listed.
+    pub fn members(&self) -> Result<Vec<String>, Error> {
+        Ok(userdb::group_by_gid(self.gid)
+            .ok_or_else(|| traceback!(SystemError, "group not found for gid {}", self.gid))?
+            .members)
+    }
+}
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct User {
     pub uid: u32,
@@ -20,12 +62,58 @@ pub struct User {
     pub name: String,
     pub group: Option<String>,
     pub groups: Vec<Group>,
+    pub gecos: Option<String>,
+    pub home_dir: Option<String>,
+    pub shell: Option<String>,
 }
 
 impl User {
+    /// `id` returns the [`User`] of the current process, looked up
+    /// natively by uid, falling back to parsing the output of
+    /// `/usr/bin/id`
     pub fn id() -> Result<User, Error> {
-        let stdout = get_stdout_string("/usr/bin/id")?;
-        Ok(User::from_id_cmd_string(stdout)?)
+        User::by_uid(unsafe { libc::getuid() }).or_else(|_| {
+            let stdout = get_stdout_string("/usr/bin/id")?;
+            User::from_id_cmd_string(stdout)
+        })
+    }
+
+    /// `by_uid` looks up the user whose uid is `uid` in the user
+    /// database through `getpwuid_r(3)` falling back to `/etc/passwd`
+    pub fn by_uid(uid: u32) -> Result<User, Error> {
+        Ok(User::from_passwd_entry(
+            userdb::passwd_by_uid(uid)
+                .ok_or_else(|| traceback!(SystemError, "user not found for uid {}", uid))?,
+        ))
+    }
+
+    /// `by_name` looks up the user named `name` in the user database
+    /// through `getpwnam_r(3)` falling back to `/etc/passwd`
+    pub fn by_name(name: &str) -> Result<User, Error> {
+        Ok(User::from_passwd_entry(
+            userdb::passwd_by_name(name)
+                .ok_or_else(|| traceback!(SystemError, "user not found: {:#?}", name))?,
+        ))
+    }
+
+    fn from_passwd_entry(entry: passwd::PasswdEntry) -> User {
+        let groups = userdb::group_list(&entry.name, entry.gid)
+            .into_iter()
+            .map(|gid| Group {
+                gid,
+                name: unix_group_name(gid).unwrap_or_else(|| gid.to_string()),
+            })
+            .collect::<Vec<Group>>();
+        User {
+            uid: entry.uid,
+            gid: Some(entry.gid),
+            group: unix_group_name(entry.gid),
+            name: entry.name,
+            groups,
+            gecos: Some(entry.gecos),
+            home_dir: Some(entry.home),
+            shell: Some(entry.shell),
+        }
     }
 
     pub fn from_env() -> User {
@@ -37,6 +125,9 @@ impl User {
             group: None,
             gid: None,
             groups: Vec::new(),
+            gecos: None,
+            home_dir: None,
+            shell: None,
         }
     }
 
@@ -68,6 +159,9 @@ impl User {
                 name,
                 group: Some(group),
                 groups,
+                gecos: None,
+                home_dir: None,
+                shell: None,
             })
         } else {
             Err(traceback!(SystemError, "could not secure user information from /usr/bin/id"))
@@ -86,14 +180,72 @@ impl User {
         self.name.to_string()
     }
 
+    /// `home` returns the home directory of the user resolved
+    /// according to [`crate::home_policy`]
     pub fn home(&self) -> Result<String, Error> {
+        Ok(self.resolve_home(home::home_policy())?.to_string())
+    }
+
+    /// `resolve_home` returns the home directory of the user
+    /// according to the given [`HomePolicy`] or a
+    /// [`Error::HomePathError`] describing every candidate rejected.
+    pub fn resolve_home(&self, policy: HomePolicy) -> Result<crate::Path, Error> {
         let user = self.name();
         let uid = self.uid();
-        Ok(unix_user_info_home("/etc/passwd", &user, uid)
-            .map_err(|e| traceback!(SystemError, e))
-            .or_else(|_| env_var_home(&user, uid, None).map_err(|e| traceback!(SystemError, e)))
-            .or_else(|_| best_guess_home(&user).map_err(|e| traceback!(SystemError, e)))?
-            .to_string())
+        let passwd_home = self
+            .home_dir
+            .clone()
+            .or_else(|| userdb::passwd_by_uid(uid).map(|entry| entry.home))
+            .filter(|home| !home.is_empty());
+        let env_home = crate::env::var("HOME").ok().filter(|home| !home.trim().is_empty());
+        let mut rejected = Vec::<String>::new();
+        let candidates = match policy {
+            HomePolicy::Strict => vec![
+                ("user database", passwd_home),
+                ("HOME", env_home),
+                ("guess", guess_unix_home(&user).ok()),
+            ],
+            HomePolicy::TrustEnv => vec![("HOME", env_home), ("user database", passwd_home)],
+            HomePolicy::PasswdOnly => match passwd_home {
+                Some(home) => return Ok(crate::Path::raw(home)),
+                None => vec![],
+            },
+        };
+        for (source, home) in candidates {
+            let Some(home) = home else {
+                rejected.push(format!("{}: not set", source));
+                continue;
+            };
+            let path = crate::Path::raw(home.trim());
+            if !path.is_dir() {
+                rejected.push(format!("{}: {:#?} is not a directory", source, path.to_string()));
+            } else if policy == HomePolicy::Strict {
+                match path.uid() {
+                    Ok(owner) if owner == uid => return Ok(path),
+                    Ok(owner) => rejected.push(format!(
+                        "{}: {:#?} is owned by uid {}",
+                        source,
+                        path.to_string(),
+                        owner
+                    )),
+                    Err(error) => rejected.push(format!("{}: {}", source, error)),
+                }
+            } else {
+                return Ok(path);
+            }
+        }
+        Err(traceback!(
+            HomePathError,
+            "resolving home of {:#?} (uid {}) with {:?} policy: {}",
+            user,
+            uid,
+            policy,
+            if rejected.is_empty() {
+                "user not found in the user database".to_string()
+            } else {
+                rejected.join("; ")
+            }
+        ))
     }
 }
 
@@ -133,65 +285,47 @@ pub fn safe_string(bytes: &[u8], short_description: &str) -> Result<String, Erro
 }
 
 pub fn unix_user_info_home(path: &str, name: &str, uid: u32) -> Result<String, Error> {
-    for (n, line) in crate::Path::from(path).read_lines()?.iter().enumerate() {
-        let location = format!("{}:{}", path, n + 1);
-        if !line.starts_with(&format!("{}:", &name)) {
-            continue;
-        }
-
-        let fields = line.split(':').into_iter().collect::<Vec<_>>();
-        if fields[2] != uid.to_string() {
-            return Err(traceback!(
-                SystemError,
-                "unexpected uid in {:#?}: {} != {}",
-                location,
-                fields[2],
-                uid
-            ));
-        }
-
-        return Ok(path_owned_expectedly(
-            crate::Path::raw(match fields.len() {
-                7 => fields[5],
-                10 => fields[7],
-                e =>
-                    return Err(traceback!(
-                        SystemError,
-                        "unexpected number of fields in {:#?} {}",
-                        location,
-                        e
-                    )),
-            }),
+    let passwd = passwd::PasswdFile::read(path)?;
+    let entry = passwd.get(name).ok_or_else(|| {
+        traceback!(SystemError, "home not found in {} for uid {} ({})", path, uid, name)
+    })?;
+    if entry.uid != uid {
+        return Err(traceback!(
+            SystemError,
+            "unexpected uid of {:#?} in {:#?}: {} != {}",
             name,
-            uid,
-        )?
-        .to_string());
+            path,
+            entry.uid,
+            uid
+        ));
     }
-    Err(traceback!(
-        SystemError,
-        "home not found in {} for uid {} ({})",
-        path,
-        uid,
-        name
-    ))
+    Ok(path_owned_expectedly(crate::Path::raw(&entry.home), name, uid)?.to_string())
 }
 
-fn env_var_home(user: &str, uid: u32, key: Option<String>) -> Result<String, Error> {
-    let key = key.unwrap_or("HOME".to_string());
-    Ok(path_owned_expectedly(
-        crate::Path::directory(crate::env::var(&key)?).map_err(|e| {
-            traceback!(
-                SystemError,
-                "fetching home directory from environment variable {:#?}: {}",
-                key,
-                e
-            )
-        })?,
-        user,
-        uid,
-    )?
-    .to_string())
+/// `unix_user_name` returns the name of the user whose uid is
+/// `uid` according to the user database
+pub(crate) fn unix_user_name(uid: u32) -> Option<String> {
+    userdb::passwd_by_uid(uid).map(|entry| entry.name)
+}
+
+/// `unix_group_name` returns the name of the group whose gid is
+/// `gid` according to the group database
+pub(crate) fn unix_group_name(gid: u32) -> Option<String> {
+    userdb::group_by_gid(gid).map(|entry| entry.name)
 }
+
+/// `unix_user_id` returns the uid of the user named `name`
+/// according to the user database
+pub(crate) fn unix_user_id(name: &str) -> Option<u32> {
+    userdb::passwd_by_name(name).map(|entry| entry.uid)
+}
+
+/// `unix_group_id` returns the gid of the group named `name`
+/// according to the group database
+pub(crate) fn unix_group_id(name: &str) -> Option<u32> {
+    userdb::group_by_name(name).map(|entry| entry.gid)
+}
+
 fn env_var_uid() -> Result<u32, Error> {
     Ok(parse_u32(env_var!("UID"), "UID environment variable")?)
 }
@@ -199,7 +333,7 @@ fn env_var_user() -> String {
     env_var!("USER")
 }
 fn path_owned_expectedly(path: crate::Path, user: &str, uid: u32) -> Result<crate::Path, Error> {
-    if path.uid() == uid {
+    if path.uid()? == uid {
         Ok(path)
     } else {
         Err(traceback!(SystemError, "{:#?} ain't owned by uid {} ({:#?})", path, uid, user))
@@ -0,0 +1,223 @@
+use std::os::unix::fs::DirBuilderExt;
+
+use crate::{traceback, Error, Path};
+
+/// `XdgDirs` holds the base directories defined by the [XDG Base
+/// Directory
+/// Specification](https://specifications.freedesktop.org/basedir-spec/latest/)
+/// resolved from environment variables with the specified defaults
+/// relative to the user's home.
+///
+/// Relative paths in environment variables are ignored as the
+/// specification requires.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XdgDirs {
+    pub config_home: Path,
+    pub data_home: Path,
+    pub cache_home: Path,
+    pub state_home: Path,
+    pub runtime_dir: Option<Path>,
+    pub config_dirs: Vec<Path>,
+    pub data_dirs: Vec<Path>,
+}
+impl XdgDirs {
+    /// `from_env` resolves the XDG base directories from the
+    /// environment of the current process, an unset or empty `$HOME`
+    /// falls back to the home directory of the current user
+    pub fn from_env() -> Result<XdgDirs, Error> {
+        let home = crate::env::var("HOME")
+            .ok()
+            .filter(|home| !home.is_empty())
+            .map(|home| Ok(Path::raw(home)))
+            .unwrap_or_else(crate::home_dir)
+            .map_err(|error| traceback!(HomePathError, "resolving XDG directories: {}", error))?;
+        Ok(XdgDirs::from_vars(home, |key| crate::env::var(key).ok()))
+    }
+
+    /// `from_vars` resolves the XDG base directories relative to
+    /// `home` looking up environment variables through `var`, empty
+    /// or relative values are ignored as per the XDG specification
+    pub fn from_vars(home: impl Into<Path>, var: impl Fn(&str) -> Option<String>) -> XdgDirs {
+        let home = Into::<Path>::into(home);
+        let absolute = |key: &str| var(key).filter(|value| value.starts_with('/')).map(Path::raw);
+        let absolute_list = |key: &str, default: &[&str]| {
+            let paths = var(key)
+                .unwrap_or_default()
+                .split(':')
+                .filter(|value| value.starts_with('/'))
+                .map(Path::raw)
+                .collect::<Vec<Path>>();
+            if paths.is_empty() {
+                default.iter().map(Path::raw).collect()
+            } else {
+                paths
+            }
+        };
+        XdgDirs {
+            config_home: absolute("XDG_CONFIG_HOME").unwrap_or_else(|| home.join(".config")),
+            data_home: absolute("XDG_DATA_HOME").unwrap_or_else(|| home.join(".local/share")),
+            cache_home: absolute("XDG_CACHE_HOME").unwrap_or_else(|| home.join(".cache")),
+            state_home: absolute("XDG_STATE_HOME").unwrap_or_else(|| home.join(".local/state")),
+            runtime_dir: absolute("XDG_RUNTIME_DIR"),
+            config_dirs: absolute_list("XDG_CONFIG_DIRS", &["/etc/xdg"]),
+            data_dirs: absolute_list("XDG_DATA_DIRS", &["/usr/local/share", "/usr/share"]),
+        }
+    }
+}
+
+/// `AppDirs` resolves the per-application directories within the
+/// XDG base directories, e.g.: `~/.config/<name>`.
+///
+/// Directories are not created until one of the `ensure_*` methods
+/// is called, in which case missing directories are created with
+/// mode `0700`.
+///
+/// Example
+///
+/// ```
+/// use iocore::{AppDirs, XdgDirs};
+/// let xdg = XdgDirs::from_vars("/home/user", |key| match key {
+///     "XDG_CONFIG_HOME" => Some("/home/user/etc".to_string()),
+///     _ => None,
+/// });
+/// let dirs = AppDirs::with_xdg("tool", xdg);
+/// assert_eq!(dirs.config_dir().to_string(), "/home/user/etc/tool");
+/// assert_eq!(dirs.cache_dir().to_string(), "/home/user/.cache/tool");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AppDirs {
+    name: String,
+    xdg: XdgDirs,
+}
+impl AppDirs {
+    pub fn new(name: impl std::fmt::Display) -> Result<AppDirs, Error> {
+        Ok(AppDirs::with_xdg(name, XdgDirs::from_env()?))
+    }
+
+    pub fn with_xdg(name: impl std::fmt::Display, xdg: XdgDirs) -> AppDirs {
+        AppDirs {
+            name: name.to_string(),
+            xdg,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    pub fn xdg(&self) -> &XdgDirs {
+        &self.xdg
+    }
+
+    pub fn config_dir(&self) -> Path {
+        self.xdg.config_home.join(&self.name)
+    }
+
+    pub fn data_dir(&self) -> Path {
+        self.xdg.data_home.join(&self.name)
+    }
+
+    pub fn cache_dir(&self) -> Path {
+        self.xdg.cache_home.join(&self.name)
+    }
+
+    pub fn state_dir(&self) -> Path {
+        self.xdg.state_home.join(&self.name)
+    }
+
+    pub fn runtime_dir(&self) -> Option<Path> {
+        self.xdg.runtime_dir.as_ref().map(|path| path.join(&self.name))
+    }
+
+    pub fn ensure_config_dir(&self) -> Result<Path, Error> {
+        ensure_private_dir(&self.config_dir())
+    }
+
+    pub fn ensure_data_dir(&self) -> Result<Path, Error> {
+        ensure_private_dir(&self.data_dir())
+    }
+
+    pub fn ensure_cache_dir(&self) -> Result<Path, Error> {
+        ensure_private_dir(&self.cache_dir())
+    }
+
+    pub fn ensure_state_dir(&self) -> Result<Path, Error> {
+        ensure_private_dir(&self.state_dir())
+    }
+
+    pub fn ensure_runtime_dir(&self) -> Result<Path, Error> {
+        let runtime_dir = self.runtime_dir().ok_or_else(|| {
+            traceback!(EnvironmentVarError, "XDG_RUNTIME_DIR is not set to an absolute path")
+        })?;
+        ensure_private_dir(&runtime_dir)
+    }
+
+    /// `config_search_dirs` returns the application's configuration
+    /// directories in order of preference
+    pub fn config_search_dirs(&self) -> Vec<Path> {
+        let mut dirs = vec![self.config_dir()];
+        dirs.extend(self.xdg.config_dirs.iter().map(|path| path.join(&self.name)));
+        dirs
+    }
+
+    /// `data_search_dirs` returns the application's data directories
+    /// in order of preference
+    pub fn data_search_dirs(&self) -> Vec<Path> {
+        let mut dirs = vec![self.data_dir()];
+        dirs.extend(self.xdg.data_dirs.iter().map(|path| path.join(&self.name)));
+        dirs
+    }
+
+    /// `find_config_file` returns the most important existing
+    /// configuration file with the given relative path
+    pub fn find_config_file(&self, name: impl std::fmt::Display) -> Option<Path> {
+        self.find_config_files(name).into_iter().next()
+    }
+
+    /// `find_config_files` returns every existing configuration file
+    /// with the given relative path in order of preference
+    pub fn find_config_files(&self, name: impl std::fmt::Display) -> Vec<Path> {
+        find_files(&self.config_search_dirs(), &name.to_string())
+    }
+
+    /// `find_data_file` returns the most important existing data
+    /// file with the given relative path
+    pub fn find_data_file(&self, name: impl std::fmt::Display) -> Option<Path> {
+        find_files(&self.data_search_dirs(), &name.to_string()).into_iter().next()
+    }
+}
+
+fn find_files(dirs: &[Path], name: &str) -> Vec<Path> {
+    dirs.iter().map(|dir| dir.join(name)).filter(|path| path.is_file()).collect()
+}
+
+fn ensure_private_dir(path: &Path) -> Result<Path, Error> {
+    let mut missing = Vec::<Path>::new();
+    let mut current = Some(path.clone());
+    while let Some(path) = current {
+        if path.exists() {
+            break;
+        }
+        current = path.parent();
+        missing.push(path);
+    }
+    for path in missing.into_iter().rev() {
+        // created with the mode rather than `chmod`ed afterwards such
+        // that the directory never exists with looser permissions
+        match std::fs::DirBuilder::new().mode(0o700).create(&path) {
+            Ok(()) => {},
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {},
+            Err(error) =>
+                return Err(traceback!(
+                    FileSystemError,
+                    "creating directory {:#?}: {}",
+                    path.to_string(),
+                    error
+                )),
+        }
+    }
+    if !path.is_dir() {
+        return Err(traceback!(FileSystemError, "{:#?} is not a directory", path.to_string()));
+    }
+    Ok(path.clone())
+}
@@ -0,0 +1,382 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{traceback, Error, OpenOptions, Path, Process, ProcessState};
+
+/// `TMP_COUNTER` distinguishes the temporary files of concurrent
+/// [`PidFile::create`] calls within the same process
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// `DaemonRedirect` determines the stdin, stdout or stderr of a
+/// [`Daemon`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum DaemonRedirect {
+    /// `Null` is `/dev/null`
+    #[default]
+    Null,
+    /// `Path` opens the given file, stdout and stderr are created
+    /// with mode `0644` and truncated unless `append` is true
+    Path { path: Path, append: bool },
+}
+
+/// `Forked` tells which side of [`Daemon::fork`] returned
+#[derive(Debug)]
+pub enum Forked {
+    /// `Parent` is returned in the calling process along with the
+    /// pid of the daemon
+    Parent(u32),
+    /// `Daemon` is returned in the daemon along with its
+    /// [`PidFile`] if configured
+    Daemon(Option<PidFile>),
+}
+
+/// `Daemon` detaches the current process from its terminal by
+/// forking twice with a new session in between.
+///
+/// The daemon changes directory to `/` and sets its umask to `0o027`
+/// unless configured otherwise and its stdio defaults to `/dev/null`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Daemon {
+    current_dir: Path,
+    umask: u32,
+    stdin: DaemonRedirect,
+    stdout: DaemonRedirect,
+    stderr: DaemonRedirect,
+    pid_file: Option<Path>,
+}
+impl Default for Daemon {
+    fn default() -> Daemon {
+        Daemon {
+            current_dir: Path::raw("/"),
+            umask: 0o027,
+            stdin: DaemonRedirect::Null,
+            stdout: DaemonRedirect::Null,
+            stderr: DaemonRedirect::Null,
+            pid_file: None,
+        }
+    }
+}
+impl Daemon {
+    pub fn new() -> Daemon {
+        Daemon::default()
+    }
+
+    pub fn current_dir(mut self, current_dir: impl Into<Path>) -> Daemon {
+        self.current_dir = current_dir.into();
+        self
+    }
+
+    pub fn umask(mut self, umask: u32) -> Daemon {
+        self.umask = umask;
+        self
+    }
+
+    pub fn stdin(mut self, stdin: DaemonRedirect) -> Daemon {
+        self.stdin = stdin;
+        self
+    }
+
+    pub fn stdout(mut self, stdout: DaemonRedirect) -> Daemon {
+        self.stdout = stdout;
+        self
+    }
+
+    pub fn stderr(mut self, stderr: DaemonRedirect) -> Daemon {
+        self.stderr = stderr;
+        self
+    }
+
+    /// `pid_file` makes the daemon hold a [`PidFile`] at `path` such
+    /// that [`Daemon::fork`] fails while another instance runs
+    pub fn pid_file(mut self, path: impl Into<Path>) -> Daemon {
+        self.pid_file = Some(path.into());
+        self
+    }
+
+    /// `start` daemonizes the current process, the calling process
+    /// exits with status `0` once the daemon is running and only the
+    /// daemon returns.
+    pub fn start(self) -> Result<Option<PidFile>, Error> {
+        match self.fork()? {
+            Forked::Parent(_) => std::process::exit(0),
+            Forked::Daemon(pid_file) => Ok(pid_file),
+        }
+    }
+
+    /// `fork` daemonizes a copy of the current process returning
+    /// [`Forked::Parent`] in the calling process once the daemon is
+    /// running or failed to start, in which case the error is
+    /// returned in the calling process.
+    ///
+    /// As with any `fork`, only the calling thread exists in the
+    /// daemon.
+    pub fn fork(self) -> Result<Forked, Error> {
+        let stdin = self.open_redirect(&self.stdin, OpenOptions::new().read(true))?;
+        let stdout = self.open_output(&self.stdout)?;
+        let stderr = self.open_output(&self.stderr)?;
+        let (mut reader, mut writer) = crate::sh::cmd::pipe()
+            .map_err(|error| traceback!(SystemError, "creating pipe to daemon: {}", error))?;
+        match unsafe { libc::fork() } {
+            -1 => return Err(traceback!(SystemError, "fork: {}", std::io::Error::last_os_error())),
+            0 => {},
+            child => {
+                drop(writer);
+                let mut report = String::new();
+                let read = reader.read_to_string(&mut report);
+                unsafe { libc::waitpid(child, std::ptr::null_mut(), 0) };
+                read.map_err(|error| traceback!(SystemError, "reading from daemon: {}", error))?;
+                return match report.split_once(' ') {
+                    Some(("ok", pid)) =>
+                        Ok(Forked::Parent(pid.parse::<u32>().map_err(|error| {
+                            traceback!(ParseError, "daemon pid {:#?}: {}", pid, error)
+                        })?)),
+                    Some(("error", error)) =>
+                        Err(traceback!(SystemError, "starting daemon: {}", error)),
+                    _ => Err(traceback!(SystemError, "daemon exited before starting")),
+                };
+            },
+        }
+        drop(reader);
+        unsafe {
+            if libc::setsid() < 0 {
+                libc::_exit(1);
+            }
+            match libc::fork() {
+                -1 => libc::_exit(1),
+                0 => {},
+                _ => libc::_exit(0),
+            }
+        }
+        match self.detach(stdin, stdout, stderr) {
+            Ok(pid_file) => {
+                let _ = write!(writer, "ok {}", std::process::id());
+                Ok(Forked::Daemon(pid_file))
+            },
+            Err(error) => {
+                let _ = write!(writer, "error {}", error);
+                drop(writer);
+                unsafe { libc::_exit(1) }
+            },
+        }
+    }
+
+    fn detach(&self, stdin: File, stdout: File, stderr: File) -> Result<Option<PidFile>, Error> {
+        unsafe { libc::umask(self.umask as libc::mode_t) };
+        std::env::set_current_dir(&self.current_dir).map_err(|error| {
+            traceback!(
+                SystemError,
+                "changing directory to {:#?}: {}",
+                self.current_dir.to_string(),
+                error
+            )
+        })?;
+        let pid_file = match &self.pid_file {
+            Some(path) => Some(PidFile::create(path)?),
+            None => None,
+        };
+        for (file, fd) in [(stdin, 0), (stdout, 1), (stderr, 2)] {
+            if unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+                return Err(traceback!(
+                    SystemError,
+                    "redirecting fd {}: {}",
+                    fd,
+                    std::io::Error::last_os_error()
+                ));
+            }
+        }
+        Ok(pid_file)
+    }
+
+    fn open_output(&self, redirect: &DaemonRedirect) -> Result<File, Error> {
+        let append = matches!(redirect, DaemonRedirect::Path { append: true, .. });
+        self.open_redirect(
+            redirect,
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(append)
+                .truncate(!append)
+                .mode(0o644),
+        )
+    }
+
+    fn open_redirect(
+        &self,
+        redirect: &DaemonRedirect,
+        open_options: &mut OpenOptions,
+    ) -> Result<File, Error> {
+        match redirect {
+            DaemonRedirect::Null => open_options.create(false).truncate(false).open("/dev/null"),
+            DaemonRedirect::Path { path, .. } => open_options.open(path.absolute()?),
+        }
+    }
+}
+
+/// `PidFile` records the pid of the current process in a file which
+/// it holds an exclusive lock on and removes on drop.
+///
+/// The file is created atomically with its contents, a file left by
+/// a process which no longer runs, according to `/proc`, is replaced.
+///
+/// Example
+///
+/// ```
+/// use iocore::{Path, PidFile};
+/// let path = Path::tmp_file().with_extension(".pid");
+/// let pid_file = PidFile::create(&path).unwrap();
+/// assert_eq!(PidFile::read(&path).unwrap(), Some(std::process::id()));
+/// assert!(PidFile::create(&path).is_err());
+/// drop(pid_file);
+/// assert!(!path.exists());
+/// ```
+#[derive(Debug)]
+pub struct PidFile {
+    path: Path,
+    pid: u32,
+    // holds the lock for as long as the `PidFile` lives
+    _file: File,
+}
+impl PidFile {
+    pub fn create(path: impl Into<Path>) -> Result<PidFile, Error> {
+        let path = path.into().absolute()?;
+        let pid = std::process::id();
+        let tmp = path.with_filename(format!(
+            ".{}.{}.{}.tmp",
+            path.name(),
+            pid,
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o644)
+            .open(&tmp)
+            .map_err(|error| traceback!(FileSystemError, "{:#?}: {}", tmp.to_string(), error))?;
+        let created = lock(&file, &tmp)
+            .and_then(|_| {
+                file.write_all(format!("{}\n", pid).as_bytes())
+                    .and_then(|_| file.sync_all())
+                    .map_err(|error| {
+                        traceback!(FileSystemError, "{:#?}: {}", tmp.to_string(), error)
+                    })
+            })
+            .and_then(|_| PidFile::link(&tmp, &path));
+        let _ = std::fs::remove_file(&tmp);
+        created?;
+        Ok(PidFile {
+            path,
+            pid,
+            _file: file,
+        })
+    }
+
+    /// `read` returns the pid recorded in the file at `path`, `None`
+    /// if the file does not exist
+    pub fn read(path: impl Into<Path>) -> Result<Option<u32>, Error> {
+        let path = path.into();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = path.read()?;
+        Ok(Some(contents.trim().parse::<u32>().map_err(|error| {
+            traceback!(ParseError, "pid file {:#?}: {:#?}: {}", path.to_string(), contents, error)
+        })?))
+    }
+
+    pub fn path(&self) -> Path {
+        self.path.clone()
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// `link` hard links `tmp` at `path` replacing a stale pid file
+    fn link(tmp: &Path, path: &Path) -> Result<(), Error> {
+        loop {
+            match std::fs::hard_link(tmp, path) {
+                Ok(()) => return Ok(()),
+                Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {},
+                Err(error) =>
+                    return Err(traceback!(FileSystemError, "{:#?}: {}", path.to_string(), error)),
+            }
+            let existing = match path.open(OpenOptions::new().read(true)) {
+                Ok(existing) => existing,
+                // removed in between, e.g.: by its owner exiting
+                Err(_) if !path.exists() => continue,
+                Err(error) => return Err(error),
+            };
+            let locked = lock(&existing, path).is_ok();
+            // another process might have replaced the stale file
+            // between opening and locking it, removing `path` then
+            // would remove the pid file of that process
+            if locked && !is_same_file(&existing, path)? {
+                continue;
+            }
+            let running =
+                !locked || PidFile::read(path).ok().flatten().is_some_and(is_running);
+            if running {
+                return Err(traceback!(
+                    SystemError,
+                    "already running with pid {} according to {:#?}",
+                    PidFile::read(path)
+                        .ok()
+                        .flatten()
+                        .map(|pid| pid.to_string())
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    path.to_string()
+                ));
+            }
+            std::fs::remove_file(path).map_err(|error| {
+                traceback!(FileSystemError, "{:#?}: {}", path.to_string(), error)
+            })?;
+        }
+    }
+}
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // processes forked while holding the `PidFile` leave it alone
+        if std::process::id() == self.pid
+            && PidFile::read(&self.path).ok().flatten() == Some(self.pid)
+        {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn lock(file: &File, path: &Path) -> Result<(), Error> {
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        return Err(traceback!(
+            FileSystemError,
+            "locking {:#?}: {}",
+            path.to_string(),
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+/// `is_same_file` returns true if `file` is still the file at `path`
+/// according to their inode and device, false if `path` was removed
+fn is_same_file(file: &File, path: &Path) -> Result<bool, Error> {
+    let opened = file
+        .metadata()
+        .map_err(|error| traceback!(FileSystemError, "{:#?}: {}", path.to_string(), error))?;
+    match std::fs::metadata(path) {
+        Ok(current) => Ok(opened.ino() == current.ino() && opened.dev() == current.dev()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(error) => Err(traceback!(FileSystemError, "{:#?}: {}", path.to_string(), error)),
+    }
+}
+
+/// `is_running` returns true if the process `pid` exists and is
+/// neither a zombie nor the current process
+fn is_running(pid: u32) -> bool {
+    pid != std::process::id()
+        && Process::new(pid)
+            .and_then(|process| process.status())
+            .is_ok_and(|status| !matches!(status.state, ProcessState::Zombie | ProcessState::Dead))
+}
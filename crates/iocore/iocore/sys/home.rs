@@ -0,0 +1,101 @@
+use std::sync::RwLock;
+
+use crate::{Error, Path};
+
+/// `HomePolicy` determines how [`crate::User::resolve_home`] resolves
+/// the home directory of a user
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum HomePolicy {
+    /// `Strict` accepts the first existing directory *owned by the
+    /// user* among the home in the user database, `$HOME` and the
+    /// platform's conventional location, e.g.: `/home/<name>`
+    #[default]
+    Strict,
+    /// `TrustEnv` accepts `$HOME` when it is an existing directory
+    /// regardless of ownership falling back to the home in the user
+    /// database, which suits containers running under arbitrary uids
+    TrustEnv,
+    /// `PasswdOnly` accepts the home in the user database as is
+    PasswdOnly,
+}
+
+#[derive(Debug, Default)]
+struct HomeSettings {
+    policy: HomePolicy,
+    home_override: Option<Path>,
+    resolved: Option<Path>,
+}
+
+lazy_static! {
+    static ref HOME_SETTINGS: RwLock<HomeSettings> = RwLock::new(HomeSettings::default());
+}
+
+/// `home_policy` returns the [`HomePolicy`] used by [`home_dir`]
+pub fn home_policy() -> HomePolicy {
+    HOME_SETTINGS.read().map(|settings| settings.policy).unwrap_or_default()
+}
+
+/// `set_home_policy` sets the [`HomePolicy`] used by [`home_dir`]
+/// and, consequently, by `~` expansion
+pub fn set_home_policy(policy: HomePolicy) {
+    if let Ok(mut settings) = HOME_SETTINGS.write() {
+        settings.policy = policy;
+        settings.resolved = None;
+    }
+}
+
+/// `home_override` returns the home directory set with
+/// [`set_home_override`], if any
+pub fn home_override() -> Option<Path> {
+    HOME_SETTINGS.read().ok().and_then(|settings| settings.home_override.clone())
+}
+
+/// `set_home_override` makes [`home_dir`] return `home` without any
+/// verification, e.g.: to confine `~` within a sandbox. Passing
+/// `None` restores the resolution according to [`home_policy`].
+pub fn set_home_override(home: Option<Path>) {
+    if let Ok(mut settings) = HOME_SETTINGS.write() {
+        settings.home_override = home;
+        settings.resolved = None;
+    }
+}
+
+/// `home_dir` returns the home directory of the current user, that
+/// is, the override set with [`set_home_override`] or the home of
+/// [`crate::USER`] resolved according to [`home_policy`].
+///
+/// Example
+///
+/// ```
+/// use iocore::{home_dir, set_home_override, Path};
+/// set_home_override(Some(Path::raw("/tmp/sandbox")));
+/// assert_eq!(home_dir().unwrap(), Path::raw("/tmp/sandbox"));
+/// assert_eq!(Path::raw("~/file").expand(), Path::raw("/tmp/sandbox/file"));
+/// set_home_override(None);
+/// ```
+pub fn home_dir() -> Result<Path, Error> {
+    if let Ok(settings) = HOME_SETTINGS.read() {
+        if let Some(home) = settings.home_override.as_ref().or(settings.resolved.as_ref()) {
+            return Ok(home.clone());
+        }
+    }
+    let policy = home_policy();
+    let home = crate::USER.resolve_home(policy)?;
+    if let Ok(mut settings) = HOME_SETTINGS.write() {
+        if settings.policy == policy && settings.home_override.is_none() {
+            settings.resolved = Some(home.clone());
+        }
+    }
+    Ok(home)
+}
+
+/// `tilde` returns [`home_dir`] with a trailing separator, i.e.: the
+/// expansion of `"~/"`
+pub fn tilde() -> Result<String, Error> {
+    let home = home_dir()?.to_string();
+    if home.ends_with('/') {
+        Ok(home)
+    } else {
+        Ok(format!("{}/", home))
+    }
+}
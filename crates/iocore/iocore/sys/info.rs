@@ -0,0 +1,360 @@
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{traceback, Error, Path, PathDateTime, Size};
+
+const OS_RELEASE_PATHS: [&str; 2] = ["/etc/os-release", "/usr/lib/os-release"];
+const CPU_SYSFS_PATH: &str = "/sys/devices/system/cpu";
+
+/// `Uname` holds the fields of `uname(2)`
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uname {
+    pub sysname: String,
+    pub nodename: String,
+    pub release: String,
+    pub version: String,
+    pub machine: String,
+}
+impl Uname {
+    pub fn read() -> Result<Uname, Error> {
+        let mut uname = unsafe { std::mem::zeroed::<libc::utsname>() };
+        if unsafe { libc::uname(&mut uname) } != 0 {
+            return Err(traceback!(SystemError, "uname: {}", std::io::Error::last_os_error()));
+        }
+        let field = |field: &[libc::c_char]| {
+            unsafe { CStr::from_ptr(field.as_ptr()) }.to_string_lossy().to_string()
+        };
+        Ok(Uname {
+            sysname: field(&uname.sysname),
+            nodename: field(&uname.nodename),
+            release: field(&uname.release),
+            version: field(&uname.version),
+            machine: field(&uname.machine),
+        })
+    }
+}
+
+/// `OsRelease` holds the operating system identification of
+/// `os-release(5)` along with every field as read in `fields`
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash, Default)]
+pub struct OsRelease {
+    pub id: String,
+    pub name: String,
+    pub pretty_name: Option<String>,
+    pub version: Option<String>,
+    pub version_id: Option<String>,
+    pub fields: BTreeMap<String, String>,
+}
+impl OsRelease {
+    /// `read` reads `/etc/os-release` falling back to
+    /// `/usr/lib/os-release`
+    pub fn read() -> Result<OsRelease, Error> {
+        for path in OS_RELEASE_PATHS.iter().map(Path::raw) {
+            if path.is_file() {
+                return Ok(OsRelease::parse(&path.read()?));
+            }
+        }
+        Err(traceback!(
+            FileSystemError,
+            "os-release not found in {}",
+            OS_RELEASE_PATHS.join(" nor ")
+        ))
+    }
+
+    /// `parse` parses the contents of `os-release(5)`, the `ID` and
+    /// `NAME` fields default to `"linux"` and `"Linux"` as specified.
+    pub fn parse(contents: &str) -> OsRelease {
+        let fields = contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), unquote(value.trim())))
+            .collect::<BTreeMap<String, String>>();
+        let field = |key: &str| fields.get(key).cloned();
+        OsRelease {
+            id: field("ID").unwrap_or_else(|| "linux".to_string()),
+            name: field("NAME").unwrap_or_else(|| "Linux".to_string()),
+            pretty_name: field("PRETTY_NAME"),
+            version: field("VERSION"),
+            version_id: field("VERSION_ID"),
+            fields,
+        }
+    }
+}
+
+fn unquote(value: &str) -> String {
+    let quote = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) if value.len() > 1 && value.ends_with(quote) => quote,
+        _ => return value.to_string(),
+    };
+    let inner = &value[1..value.len() - 1];
+    if quote == '\'' {
+        return inner.to_string();
+    }
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// `LoadAverage` holds the system load averages over 1, 5 and 15
+/// minutes as reported by `/proc/loadavg`
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, PartialOrd, Default)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+impl LoadAverage {
+    pub fn read() -> Result<LoadAverage, Error> {
+        LoadAverage::parse(&read_proc("loadavg")?)
+    }
+
+    pub fn parse(contents: &str) -> Result<LoadAverage, Error> {
+        let fields = contents
+            .split_whitespace()
+            .take(3)
+            .map(|field| {
+                f64::from_str(field).map_err(|error| {
+                    traceback!(ParseError, "{} parsing load average {:#?}", error, field)
+                })
+            })
+            .collect::<Result<Vec<f64>, Error>>()?;
+        match fields[..] {
+            [one, five, fifteen] => Ok(LoadAverage { one, five, fifteen }),
+            _ => Err(traceback!(ParseError, "unexpected load average {:#?}", contents)),
+        }
+    }
+}
+
+/// `MemoryInfo` holds the memory totals reported by `/proc/meminfo`
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, Hash, Default)]
+pub struct MemoryInfo {
+    pub total: Size,
+    pub free: Size,
+    pub available: Size,
+    pub buffers: Size,
+    pub cached: Size,
+    pub swap_total: Size,
+    pub swap_free: Size,
+}
+impl MemoryInfo {
+    pub fn read() -> Result<MemoryInfo, Error> {
+        MemoryInfo::parse(&read_proc("meminfo")?)
+    }
+
+    pub fn parse(contents: &str) -> Result<MemoryInfo, Error> {
+        let mut fields = BTreeMap::<&str, Size>::new();
+        for (key, value) in contents.lines().filter_map(|line| line.split_once(':')) {
+            let mut parts = value.split_whitespace();
+            let Some(amount) = parts.next().and_then(|amount| u64::from_str(amount).ok()) else {
+                continue;
+            };
+            let bytes = match parts.next() {
+                Some("kB") => amount * 1024,
+                _ => amount,
+            };
+            fields.insert(key.trim(), Size::from(bytes));
+        }
+        let total = fields
+            .get("MemTotal")
+            .copied()
+            .ok_or_else(|| traceback!(ParseError, "MemTotal not found in meminfo"))?;
+        let field = |key: &str| fields.get(key).copied().unwrap_or_default();
+        Ok(MemoryInfo {
+            total,
+            free: field("MemFree"),
+            available: fields.get("MemAvailable").copied().unwrap_or_else(|| field("MemFree")),
+            buffers: field("Buffers"),
+            cached: field("Cached"),
+            swap_total: field("SwapTotal"),
+            swap_free: field("SwapFree"),
+        })
+    }
+
+    pub fn used(&self) -> Size {
+        Size::from(self.total.as_u64().saturating_sub(self.available.as_u64()))
+    }
+}
+
+/// `Cpu` represents a logical CPU and its position within the CPU
+/// topology exposed in `/sys/devices/system/cpu`
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cpu {
+    pub id: u32,
+    pub core_id: Option<u32>,
+    pub package_id: Option<u32>,
+    pub online: bool,
+}
+
+/// `CpuInfo` holds the CPU count and topology of the system
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash, Default)]
+pub struct CpuInfo {
+    pub model_name: Option<String>,
+    pub cpus: Vec<Cpu>,
+}
+impl CpuInfo {
+    pub fn read() -> Result<CpuInfo, Error> {
+        let mut cpus = Vec::<Cpu>::new();
+        let sysfs = Path::raw(CPU_SYSFS_PATH);
+        if sysfs.is_dir() {
+            for path in sysfs.list()? {
+                let Some(id) =
+                    path.name().strip_prefix("cpu").and_then(|id| u32::from_str(id).ok())
+                else {
+                    continue;
+                };
+                let read_id = |name: &str| {
+                    std::fs::read_to_string(path.join("topology").join(name))
+                        .ok()
+                        .and_then(|id| u32::from_str(id.trim()).ok())
+                };
+                let online = std::fs::read_to_string(path.join("online"))
+                    .map(|online| online.trim() == "1")
+                    .unwrap_or(true);
+                cpus.push(Cpu {
+                    id,
+                    core_id: read_id("core_id"),
+                    package_id: read_id("physical_package_id"),
+                    online,
+                });
+            }
+        }
+        let cpuinfo = read_proc("cpuinfo").unwrap_or_default();
+        if cpus.is_empty() {
+            cpus = cpuinfo
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .filter(|(key, _)| key.trim() == "processor")
+                .filter_map(|(_, id)| u32::from_str(id.trim()).ok())
+                .map(|id| Cpu {
+                    id,
+                    core_id: None,
+                    package_id: None,
+                    online: true,
+                })
+                .collect();
+        }
+        cpus.sort();
+        let model_name = cpuinfo
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim() == "model name")
+            .map(|(_, name)| name.trim().to_string());
+        Ok(CpuInfo { model_name, cpus })
+    }
+
+    /// `logical` returns the number of online logical CPUs
+    pub fn logical(&self) -> usize {
+        self.cpus.iter().filter(|cpu| cpu.online).count()
+    }
+
+    /// `physical_cores` returns the number of distinct cores of the
+    /// online CPUs, falling back to [`CpuInfo::logical`] when the
+    /// topology is unknown
+    pub fn physical_cores(&self) -> usize {
+        let mut cores = self
+            .cpus
+            .iter()
+            .filter(|cpu| cpu.online)
+            .filter_map(|cpu| Some((cpu.package_id?, cpu.core_id?)))
+            .collect::<Vec<_>>();
+        cores.sort();
+        cores.dedup();
+        if cores.is_empty() {
+            self.logical()
+        } else {
+            cores.len()
+        }
+    }
+
+    /// `packages` returns the number of distinct physical packages,
+    /// i.e.: sockets
+    pub fn packages(&self) -> usize {
+        let mut packages = self.cpus.iter().filter_map(|cpu| cpu.package_id).collect::<Vec<_>>();
+        packages.sort();
+        packages.dedup();
+        packages.len().max(1)
+    }
+}
+
+/// `SystemInfo` gathers information about the running system from
+/// system calls, `/proc` and `/sys` without spawning processes.
+///
+/// Example
+///
+/// ```
+/// use iocore::SystemInfo;
+/// # if !iocore::Path::raw("/proc/meminfo").exists() { return; }
+/// let info = SystemInfo::gather().unwrap();
+/// assert_eq!(info.hostname, info.uname.nodename);
+/// assert!(info.memory.total.as_u64() > 0);
+/// assert!(info.cpu.logical() > 0);
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SystemInfo {
+    pub hostname: String,
+    pub uname: Uname,
+    pub os_release: Option<OsRelease>,
+    pub uptime: f64,
+    pub boot_time: PathDateTime,
+    pub load_average: LoadAverage,
+    pub memory: MemoryInfo,
+    pub cpu: CpuInfo,
+}
+impl SystemInfo {
+    pub fn gather() -> Result<SystemInfo, Error> {
+        let uptime = uptime()?;
+        let now = PathDateTime::from(std::time::SystemTime::now());
+        let (seconds, nanoseconds) = now.timestamp();
+        let boot = seconds as f64 + nanoseconds as f64 / 1e9 - uptime;
+        Ok(SystemInfo {
+            hostname: hostname()?,
+            uname: Uname::read()?,
+            os_release: OsRelease::read().ok(),
+            uptime,
+            boot_time: PathDateTime::from_timestamp(
+                boot.floor() as i64,
+                (boot.fract() * 1e9) as u32,
+            ),
+            load_average: LoadAverage::read()?,
+            memory: MemoryInfo::read()?,
+            cpu: CpuInfo::read()?,
+        })
+    }
+}
+
+/// `hostname` returns the host name of the system as per
+/// `gethostname(2)`
+pub fn hostname() -> Result<String, Error> {
+    let mut buffer = vec![0 as libc::c_char; 256];
+    if unsafe { libc::gethostname(buffer.as_mut_ptr(), buffer.len()) } != 0 {
+        return Err(traceback!(SystemError, "gethostname: {}", std::io::Error::last_os_error()));
+    }
+    buffer.push(0);
+    Ok(unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_string_lossy().to_string())
+}
+
+/// `uptime` returns the number of seconds since the system booted as
+/// per `/proc/uptime`
+pub fn uptime() -> Result<f64, Error> {
+    let contents = read_proc("uptime")?;
+    let uptime = contents.split_whitespace().next().unwrap_or_default();
+    f64::from_str(uptime)
+        .map_err(|error| traceback!(ParseError, "{} parsing uptime {:#?}", error, uptime))
+}
+
+fn read_proc(name: &str) -> Result<String, Error> {
+    let path = Path::raw(crate::PROC_PATH).join(name);
+    std::fs::read_to_string(&path)
+        .map_err(|error| traceback!(SystemError, "reading {:#?}: {}", path.to_string(), error))
+}
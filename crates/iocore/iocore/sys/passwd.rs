@@ -0,0 +1,418 @@
+use std::fmt::Display;
+use std::fs::{File, Metadata};
+use std::io::Write;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{traceback, Error, Path};
+
+/// `TMP_COUNTER` distinguishes the temporary files of concurrent
+/// [`DatabaseFile::write`] calls within the same process
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// `DatabaseEntry` is implemented by the records of colon-separated
+/// user databases such as `passwd(5)`, `group(5)` and `shadow(5)`
+pub trait DatabaseEntry: Sized + Clone {
+    /// `PATH` is the location of the database relative to the root
+    /// of the file-system, e.g.: `"etc/passwd"`
+    const PATH: &'static str;
+
+    /// `MODE` holds the permissions with which [`DatabaseFile::write`]
+    /// creates the database when it does not exist yet
+    const MODE: u32 = 0o644;
+
+    fn parse(line: &str) -> Result<Self, Error>;
+
+    fn to_line(&self) -> String;
+
+    fn name(&self) -> &str;
+}
+
+/// `PasswdEntry` represents a `passwd(5)` record, the `class`,
+/// `change` and `expire` fields are only present in the 10-field
+/// BSD `master.passwd` format.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PasswdEntry {
+    pub name: String,
+    pub password: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub class: Option<String>,
+    pub change: Option<String>,
+    pub expire: Option<String>,
+    pub gecos: String,
+    pub home: String,
+    pub shell: String,
+}
+impl DatabaseEntry for PasswdEntry {
+    const PATH: &'static str = "etc/passwd";
+
+    fn parse(line: &str) -> Result<PasswdEntry, Error> {
+        let fields = line.split(':').collect::<Vec<_>>();
+        let (class, change, expire, gecos, home, shell) = match fields.len() {
+            7 => (None, None, None, fields[4], fields[5], fields[6]),
+            10 => (
+                Some(fields[4].to_string()),
+                Some(fields[5].to_string()),
+                Some(fields[6].to_string()),
+                fields[7],
+                fields[8],
+                fields[9],
+            ),
+            count =>
+                return Err(traceback!(
+                    ParseError,
+                    "unexpected number of fields in passwd entry {:#?}: {}",
+                    line,
+                    count
+                )),
+        };
+        Ok(PasswdEntry {
+            name: fields[0].to_string(),
+            password: fields[1].to_string(),
+            uid: parse_id(fields[2], "uid", line)?,
+            gid: parse_id(fields[3], "gid", line)?,
+            class,
+            change,
+            expire,
+            gecos: gecos.to_string(),
+            home: home.to_string(),
+            shell: shell.to_string(),
+        })
+    }
+
+    fn to_line(&self) -> String {
+        let mut fields = vec![
+            self.name.to_string(),
+            self.password.to_string(),
+            self.uid.to_string(),
+            self.gid.to_string(),
+        ];
+        if self.class.is_some() || self.change.is_some() || self.expire.is_some() {
+            for field in [&self.class, &self.change, &self.expire] {
+                fields.push(field.clone().unwrap_or_default());
+            }
+        }
+        fields.extend([self.gecos.to_string(), self.home.to_string(), self.shell.to_string()]);
+        fields.join(":")
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// `GroupEntry` represents a `group(5)` record
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct GroupEntry {
+    pub name: String,
+    pub password: String,
+    pub gid: u32,
+    pub members: Vec<String>,
+}
+impl DatabaseEntry for GroupEntry {
+    const PATH: &'static str = "etc/group";
+
+    fn parse(line: &str) -> Result<GroupEntry, Error> {
+        let fields = line.split(':').collect::<Vec<_>>();
+        if fields.len() != 4 {
+            return Err(traceback!(
+                ParseError,
+                "unexpected number of fields in group entry {:#?}: {}",
+                line,
+                fields.len()
+            ));
+        }
+        Ok(GroupEntry {
+            name: fields[0].to_string(),
+            password: fields[1].to_string(),
+            gid: parse_id(fields[2], "gid", line)?,
+            members: fields[3]
+                .split(',')
+                .filter(|member| !member.is_empty())
+                .map(|member| member.to_string())
+                .collect(),
+        })
+    }
+
+    fn to_line(&self) -> String {
+        format!("{}:{}:{}:{}", self.name, self.password, self.gid, self.members.join(","))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// `ShadowEntry` represents a `shadow(5)` record where empty numeric
+/// fields are `None`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ShadowEntry {
+    pub name: String,
+    pub password: String,
+    pub last_change: Option<u64>,
+    pub min_age: Option<u64>,
+    pub max_age: Option<u64>,
+    pub warn_period: Option<u64>,
+    pub inactivity_period: Option<u64>,
+    pub expiration: Option<u64>,
+    pub reserved: String,
+}
+impl DatabaseEntry for ShadowEntry {
+    const MODE: u32 = 0o640;
+    const PATH: &'static str = "etc/shadow";
+
+    fn parse(line: &str) -> Result<ShadowEntry, Error> {
+        let fields = line.split(':').collect::<Vec<_>>();
+        if fields.len() != 9 {
+            return Err(traceback!(
+                ParseError,
+                "unexpected number of fields in shadow entry {:#?}: {}",
+                line,
+                fields.len()
+            ));
+        }
+        let optional = |index: usize| -> Result<Option<u64>, Error> {
+            match fields[index] {
+                "" => Ok(None),
+                field => Ok(Some(u64::from_str(field).map_err(|error| {
+                    traceback!(ParseError, "{} in shadow entry {:#?}: {:#?}", error, line, field)
+                })?)),
+            }
+        };
+        Ok(ShadowEntry {
+            name: fields[0].to_string(),
+            password: fields[1].to_string(),
+            last_change: optional(2)?,
+            min_age: optional(3)?,
+            max_age: optional(4)?,
+            warn_period: optional(5)?,
+            inactivity_period: optional(6)?,
+            expiration: optional(7)?,
+            reserved: fields[8].to_string(),
+        })
+    }
+
+    fn to_line(&self) -> String {
+        let optional =
+            |field: Option<u64>| field.map(|field| field.to_string()).unwrap_or_default();
+        [
+            self.name.to_string(),
+            self.password.to_string(),
+            optional(self.last_change),
+            optional(self.min_age),
+            optional(self.max_age),
+            optional(self.warn_period),
+            optional(self.inactivity_period),
+            optional(self.expiration),
+            self.reserved.to_string(),
+        ]
+        .join(":")
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+fn parse_id(field: &str, short_description: &str, line: &str) -> Result<u32, Error> {
+    u32::from_str(field).map_err(|error| {
+        traceback!(
+            ParseError,
+            "{} parsing {} {:#?} of entry {:#?}",
+            error,
+            short_description,
+            field,
+            line
+        )
+    })
+}
+
+/// `DatabaseLine` is either a parsed entry or a line preserved
+/// verbatim such as comments, blank lines or NIS `+`/`-` lines
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DatabaseLine<T: DatabaseEntry> {
+    Entry(T),
+    Other(String),
+}
+
+/// `DatabaseFile` holds the lines of a `passwd(5)`-like file in
+/// order such that it can be edited and written back without losing
+/// comments or lines which could not be parsed.
+///
+/// Example
+///
+/// ```
+/// use iocore::PasswdFile;
+/// let mut passwd = PasswdFile::parse("# users\nroot:x:0:0:root:/root:/bin/sh\n");
+/// let mut user = passwd.get("root").unwrap().clone();
+/// user.name = "admin".to_string();
+/// user.uid = 1000;
+/// passwd.upsert(user);
+/// assert_eq!(
+///     passwd.to_string(),
+///     "# users\nroot:x:0:0:root:/root:/bin/sh\nadmin:x:1000:0:root:/root:/bin/sh\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DatabaseFile<T: DatabaseEntry> {
+    lines: Vec<DatabaseLine<T>>,
+}
+pub type PasswdFile = DatabaseFile<PasswdEntry>;
+pub type GroupFile = DatabaseFile<GroupEntry>;
+pub type ShadowFile = DatabaseFile<ShadowEntry>;
+
+impl<T: DatabaseEntry> DatabaseFile<T> {
+    pub fn new() -> DatabaseFile<T> {
+        DatabaseFile { lines: Vec::new() }
+    }
+
+    pub fn parse(contents: &str) -> DatabaseFile<T> {
+        DatabaseFile {
+            lines: contents
+                .lines()
+                .map(|line| {
+                    if line.trim().is_empty() || line.starts_with('#') {
+                        DatabaseLine::Other(line.to_string())
+                    } else {
+                        match T::parse(line) {
+                            Ok(entry) => DatabaseLine::Entry(entry),
+                            Err(_) => DatabaseLine::Other(line.to_string()),
+                        }
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn read(path: impl Into<Path>) -> Result<DatabaseFile<T>, Error> {
+        Ok(DatabaseFile::parse(&Into::<Path>::into(path).read()?))
+    }
+
+    /// `read_in` reads the database located at [`DatabaseEntry::PATH`]
+    /// under `root`, e.g.: a chroot or an unpacked container image
+    pub fn read_in(root: impl Into<Path>) -> Result<DatabaseFile<T>, Error> {
+        DatabaseFile::read(Into::<Path>::into(root).join(T::PATH))
+    }
+
+    /// `write` replaces the database at `path` by renaming a synced
+    /// temporary file from the same directory over it, such that
+    /// readers never observe a partially written database.
+    ///
+    /// The mode and ownership of an existing database are preserved,
+    /// a new database is created with [`DatabaseEntry::MODE`].
+    pub fn write(&self, path: impl Into<Path>) -> Result<Path, Error> {
+        let path = Into::<Path>::into(path);
+        path.mkdir_parents()?;
+        let existing = std::fs::metadata(&path).ok();
+        let tmp = path.with_filename(format!(
+            ".{}.{}.{}.tmp",
+            path.name(),
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        replace(&path, &tmp, self.to_string().as_bytes(), existing.as_ref(), T::MODE).map_err(
+            |error| {
+                let _ = std::fs::remove_file(&tmp);
+                traceback!(FileSystemError, "writing {:#?}: {}", path.to_string(), error)
+            },
+        )?;
+        Ok(path)
+    }
+
+    /// `write_in` writes the database at [`DatabaseEntry::PATH`] under
+    /// `root`
+    pub fn write_in(&self, root: impl Into<Path>) -> Result<Path, Error> {
+        self.write(Into::<Path>::into(root).join(T::PATH))
+    }
+
+    pub fn lines(&self) -> &[DatabaseLine<T>] {
+        &self.lines
+    }
+
+    pub fn entries(&self) -> Vec<&T> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                DatabaseLine::Entry(entry) => Some(entry),
+                DatabaseLine::Other(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.entries().into_iter().find(|entry| entry.name() == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut T> {
+        self.lines.iter_mut().find_map(|line| match line {
+            DatabaseLine::Entry(entry) if entry.name() == name => Some(entry),
+            _ => None,
+        })
+    }
+
+    /// `upsert` replaces the entry with the same name in place or
+    /// appends it to the end of the file
+    pub fn upsert(&mut self, entry: T) {
+        match self.get_mut(entry.name()) {
+            Some(existing) => *existing = entry,
+            None => self.lines.push(DatabaseLine::Entry(entry)),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<T> {
+        let index = self.lines.iter().position(|line| match line {
+            DatabaseLine::Entry(entry) => entry.name() == name,
+            DatabaseLine::Other(_) => false,
+        })?;
+        match self.lines.remove(index) {
+            DatabaseLine::Entry(entry) => Some(entry),
+            DatabaseLine::Other(_) => None,
+        }
+    }
+}
+/// `replace` writes `contents` into `tmp` with the mode and ownership
+/// of `existing`, if any, and renames it over `path`
+fn replace(
+    path: &Path,
+    tmp: &Path,
+    contents: &[u8],
+    existing: Option<&Metadata>,
+    mode: u32,
+) -> std::io::Result<()> {
+    let mode = existing.map(|meta| meta.mode() & 0o7777).unwrap_or(mode);
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).mode(mode).open(tmp)?;
+    // the mode given to `open` is subject to the umask
+    file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+    if let Some(meta) = existing {
+        let created = file.metadata()?;
+        if (meta.uid(), meta.gid()) != (created.uid(), created.gid()) {
+            std::os::unix::fs::fchown(&file, Some(meta.uid()), Some(meta.gid()))?;
+        }
+    }
+    file.write_all(contents)?;
+    file.sync_all()?;
+    std::fs::rename(tmp, path)?;
+    if let Some(parent) = path.parent() {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+impl<T: DatabaseEntry> Default for DatabaseFile<T> {
+    fn default() -> DatabaseFile<T> {
+        DatabaseFile::new()
+    }
+}
+impl<T: DatabaseEntry> Display for DatabaseFile<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for line in &self.lines {
+            match line {
+                DatabaseLine::Entry(entry) => writeln!(f, "{}", entry.to_line())?,
+                DatabaseLine::Other(line) => writeln!(f, "{}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::{traceback, Error, Path, PathDateTime};
+
+/// `PROC_PATH` is the mount point of the `proc(5)` file-system
+pub const PROC_PATH: &str = "/proc";
+
+/// `ProcessState` represents the single-letter state of a process
+/// as reported by `/proc/<pid>/stat`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProcessState {
+    Running,
+    Sleeping,
+    DiskSleep,
+    Stopped,
+    TracingStop,
+    Zombie,
+    Dead,
+    Idle,
+    Other(char),
+}
+impl From<char> for ProcessState {
+    fn from(state: char) -> ProcessState {
+        match state {
+            'R' => ProcessState::Running,
+            'S' => ProcessState::Sleeping,
+            'D' => ProcessState::DiskSleep,
+            'T' => ProcessState::Stopped,
+            't' => ProcessState::TracingStop,
+            'Z' => ProcessState::Zombie,
+            'X' | 'x' => ProcessState::Dead,
+            'I' => ProcessState::Idle,
+            state => ProcessState::Other(state),
+        }
+    }
+}
+impl From<ProcessState> for char {
+    fn from(state: ProcessState) -> char {
+        match state {
+            ProcessState::Running => 'R',
+            ProcessState::Sleeping => 'S',
+            ProcessState::DiskSleep => 'D',
+            ProcessState::Stopped => 'T',
+            ProcessState::TracingStop => 't',
+            ProcessState::Zombie => 'Z',
+            ProcessState::Dead => 'X',
+            ProcessState::Idle => 'I',
+            ProcessState::Other(state) => state,
+        }
+    }
+}
+
+/// `ProcessStatus` holds the most commonly used fields of
+/// `/proc/<pid>/status` along with every field as read in `fields`.
+///
+/// `uids` and `gids` are the real, effective, saved and file-system
+/// ids in that order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcessStatus {
+    pub name: String,
+    pub state: ProcessState,
+    pub pid: u32,
+    pub ppid: u32,
+    pub uids: [u32; 4],
+    pub gids: [u32; 4],
+    pub threads: u64,
+    pub fields: BTreeMap<String, String>,
+}
+impl ProcessStatus {
+    pub fn parse(contents: &str) -> Result<ProcessStatus, Error> {
+        let fields = contents
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect::<BTreeMap<String, String>>();
+        let field = |key: &str| -> Result<&str, Error> {
+            fields
+                .get(key)
+                .map(|value| value.as_str())
+                .ok_or_else(|| traceback!(ParseError, "missing {:#?} in process status", key))
+        };
+        let number = |key: &str| -> Result<u64, Error> {
+            let value = field(key)?;
+            u64::from_str(value).map_err(|error| {
+                traceback!(ParseError, "{} parsing {} {:#?} of process status", error, key, value)
+            })
+        };
+        let ids = |key: &str| -> Result<[u32; 4], Error> {
+            let value = field(key)?;
+            let ids = value
+                .split_whitespace()
+                .map(|id| {
+                    u32::from_str(id).map_err(|error| {
+                        traceback!(
+                            ParseError,
+                            "{} parsing {} {:#?} of process status",
+                            error,
+                            key,
+                            id
+                        )
+                    })
+                })
+                .collect::<Result<Vec<u32>, Error>>()?;
+            ids.try_into().map_err(|ids: Vec<u32>| {
+                traceback!(
+                    ParseError,
+                    "expected 4 ids in {} of process status, got {}",
+                    key,
+                    ids.len()
+                )
+            })
+        };
+        Ok(ProcessStatus {
+            name: field("Name")?.to_string(),
+            state: ProcessState::from(field("State")?.chars().next().unwrap_or('?')),
+            pid: number("Pid")? as u32,
+            ppid: number("PPid")? as u32,
+            uids: ids("Uid")?,
+            gids: ids("Gid")?,
+            threads: number("Threads").unwrap_or(1),
+            fields,
+        })
+    }
+}
+
+/// `ProcessFd` represents an open file descriptor of a process and
+/// the target of its symlink in `/proc/<pid>/fd`, e.g.:
+/// `"/dev/null"`, `"pipe:[1234]"` or `"socket:[5678]"`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProcessFd {
+    pub fd: u32,
+    pub target: Path,
+}
+
+/// `Process` provides access to the information of a running process
+/// through the `proc(5)` file-system, which is only available on
+/// Linux-like systems.
+///
+/// Example
+///
+/// ```
+/// use iocore::Process;
+/// # if !iocore::Path::raw("/proc/self").exists() { return; }
+/// let process = Process::current().unwrap();
+/// assert_eq!(process.pid, std::process::id());
+/// assert_eq!(process.exe().unwrap(), iocore::Path::from(std::env::current_exe().unwrap()));
+/// assert!(Process::list().unwrap().contains(&process));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Process {
+    pub pid: u32,
+}
+impl Process {
+    pub fn new(pid: u32) -> Result<Process, Error> {
+        let process = Process { pid };
+        if !process.path().is_dir() {
+            return Err(traceback!(SystemError, "no such process: {}", pid));
+        }
+        Ok(process)
+    }
+
+    pub fn current() -> Result<Process, Error> {
+        Process::new(std::process::id())
+    }
+
+    /// `list` returns every process visible in `/proc` ordered by pid
+    pub fn list() -> Result<Vec<Process>, Error> {
+        let mut processes = std::fs::read_dir(PROC_PATH)
+            .map_err(|error| traceback!(SystemError, "listing {:#?}: {}", PROC_PATH, error))?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| u32::from_str(&entry.file_name().to_string_lossy()).ok())
+            .map(|pid| Process { pid })
+            .collect::<Vec<Process>>();
+        processes.sort();
+        Ok(processes)
+    }
+
+    /// `path` returns the path of the process within `/proc`
+    pub fn path(&self) -> Path {
+        Path::raw(PROC_PATH).join(self.pid.to_string())
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.path().is_dir()
+    }
+
+    /// `cmdline` returns the arguments of the process including the
+    /// program name, kernel threads have no arguments
+    pub fn cmdline(&self) -> Result<Vec<String>, Error> {
+        Ok(split_nul(&self.read_bytes("cmdline")?))
+    }
+
+    /// `environ` returns the initial environment of the process
+    pub fn environ(&self) -> Result<BTreeMap<String, String>, Error> {
+        Ok(split_nul(&self.read_bytes("environ")?)
+            .into_iter()
+            .filter_map(|entry| {
+                entry.split_once('=').map(|(key, value)| (key.to_string(), value.to_string()))
+            })
+            .collect())
+    }
+
+    pub fn cwd(&self) -> Result<Path, Error> {
+        self.path().join("cwd").read_symlink()
+    }
+
+    pub fn exe(&self) -> Result<Path, Error> {
+        self.path().join("exe").read_symlink()
+    }
+
+    pub fn status(&self) -> Result<ProcessStatus, Error> {
+        ProcessStatus::parse(&String::from_utf8_lossy(&self.read_bytes("status")?))
+    }
+
+    /// `name` returns the command name of the process, which is
+    /// truncated to 15 bytes by the kernel
+    pub fn name(&self) -> Result<String, Error> {
+        Ok(String::from_utf8_lossy(&self.read_bytes("comm")?).trim_end().to_string())
+    }
+
+    pub fn ppid(&self) -> Result<u32, Error> {
+        Ok(self.status()?.ppid)
+    }
+
+    pub fn parent(&self) -> Result<Option<Process>, Error> {
+        match self.ppid()? {
+            0 => Ok(None),
+            ppid => Ok(Some(Process { pid: ppid })),
+        }
+    }
+
+    /// `uid` returns the real user id of the process
+    pub fn uid(&self) -> Result<u32, Error> {
+        Ok(self.status()?.uids[0])
+    }
+
+    /// `gid` returns the real group id of the process
+    pub fn gid(&self) -> Result<u32, Error> {
+        Ok(self.status()?.gids[0])
+    }
+
+    /// `start_time` returns the time at which the process started
+    /// based on the boot time in `/proc/stat`
+    pub fn start_time(&self) -> Result<PathDateTime, Error> {
+        let stat = String::from_utf8_lossy(&self.read_bytes("stat")?).to_string();
+        let fields = stat
+            .rsplit_once(')')
+            .map(|(_, fields)| fields.split_whitespace().collect::<Vec<_>>())
+            .unwrap_or_default();
+        // fields after the command name start at field 3 of proc_pid_stat(5),
+        // `starttime` is field 22
+        let ticks = fields.get(19).ok_or_else(|| {
+            traceback!(ParseError, "missing starttime in stat of process {}", self.pid)
+        })?;
+        let ticks = u64::from_str(ticks).map_err(|error| {
+            traceback!(
+                ParseError,
+                "{} parsing starttime {:#?} of process {}",
+                error,
+                ticks,
+                self.pid
+            )
+        })?;
+        let ticks_per_second = match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+            ticks if ticks > 0 => ticks as u64,
+            _ => 100,
+        };
+        let boot_time = boot_time()?;
+        let nanoseconds = (ticks % ticks_per_second) * 1_000_000_000 / ticks_per_second;
+        Ok(PathDateTime::from_timestamp(
+            boot_time + (ticks / ticks_per_second) as i64,
+            nanoseconds as u32,
+        ))
+    }
+
+    /// `fds` returns the open file descriptors of the process ordered
+    /// by number, which usually requires the same uid as the process
+    pub fn fds(&self) -> Result<Vec<ProcessFd>, Error> {
+        let path = self.path().join("fd");
+        let mut fds = std::fs::read_dir(&path)
+            .map_err(|error| traceback!(SystemError, "listing {:#?}: {}", path.to_string(), error))?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let fd = u32::from_str(&entry.file_name().to_string_lossy()).ok()?;
+                let target = std::fs::read_link(entry.path()).ok()?;
+                Some(ProcessFd {
+                    fd,
+                    target: Path::from(target),
+                })
+            })
+            .collect::<Vec<ProcessFd>>();
+        fds.sort();
+        Ok(fds)
+    }
+
+    /// `children` returns the direct children of the process ordered
+    /// by pid
+    pub fn children(&self) -> Result<Vec<Process>, Error> {
+        let mut children = Vec::<Process>::new();
+        for process in Process::list()? {
+            if let Ok(status) = process.status() {
+                if status.ppid == self.pid {
+                    children.push(process);
+                }
+            }
+        }
+        Ok(children)
+    }
+
+    fn read_bytes(&self, name: &str) -> Result<Vec<u8>, Error> {
+        let path = self.path().join(name);
+        std::fs::read(&path)
+            .map_err(|error| traceback!(SystemError, "reading {:#?}: {}", path.to_string(), error))
+    }
+}
+
+fn boot_time() -> Result<i64, Error> {
+    let path = Path::raw(PROC_PATH).join("stat");
+    let stat = std::fs::read_to_string(&path)
+        .map_err(|error| traceback!(SystemError, "reading {:#?}: {}", path.to_string(), error))?;
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|btime| i64::from_str(btime.trim()).ok())
+        .ok_or_else(|| traceback!(ParseError, "missing btime in {:#?}", path.to_string()))
+}
+
+/// `split_nul` splits NUL-terminated strings keeping empty ones,
+/// e.g.: the empty arguments in `cmdline`
+fn split_nul(bytes: &[u8]) -> Vec<String> {
+    if bytes.is_empty() {
+        return Vec::new();
+    }
+    bytes
+        .strip_suffix(&[0])
+        .unwrap_or(bytes)
+        .split(|byte| *byte == 0)
+        .map(|part| String::from_utf8_lossy(part).to_string())
+        .collect()
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+use std::os::fd::IntoRawFd;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::{traceback, Error};
+
+/// `SHUTDOWN_SIGNALS` are the signals which set the flag returned by
+/// [`shutdown_requested`] while a [`Signals::shutdown`] listens to
+/// them
+pub const SHUTDOWN_SIGNALS: [i32; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+static PIPE_WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+
+struct Listener {
+    id: usize,
+    signals: Vec<i32>,
+    sender: Sender<i32>,
+    forward: Vec<i32>,
+}
+
+struct Handler {
+    listeners: usize,
+    /// `shutdown` counts the listeners created by
+    /// [`Signals::shutdown`], the signal sets the shutdown flag while
+    /// it is non-zero
+    shutdown: usize,
+    previous: libc::sigaction,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: usize,
+    listeners: Vec<Listener>,
+    handlers: BTreeMap<i32, Handler>,
+}
+
+lazy_static! {
+    static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry::default());
+}
+
+/// `shutdown_requested` returns true once any of the
+/// [`SHUTDOWN_SIGNALS`] was received by a [`Signals::shutdown`] or
+/// [`request_shutdown`] was called
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
+}
+
+pub fn request_shutdown() {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+pub fn clear_shutdown_request() {
+    SHUTDOWN.store(false, Ordering::SeqCst);
+}
+
+/// `check_shutdown` returns [`Error::ShutdownRequested`] if
+/// [`shutdown_requested`], long running operations such as
+/// [`crate::walk_dir`] call it to stop early
+pub fn check_shutdown() -> Result<(), Error> {
+    if shutdown_requested() {
+        return Err(traceback!(ShutdownRequested, "shutdown requested"));
+    }
+    Ok(())
+}
+
+/// `Signals` receives the given signals through a self-pipe written
+/// by the signal handler and read by a dispatcher thread, such that
+/// signals are delivered as an iterator rather than in a handler.
+///
+/// The previous disposition of each signal is restored once every
+/// `Signals` listening to it is dropped.
+///
+/// Example
+///
+/// ```
+/// use std::time::Duration;
+/// use iocore::Signals;
+/// let mut signals = Signals::new([libc::SIGUSR1]).unwrap();
+/// unsafe { libc::kill(libc::getpid(), libc::SIGUSR1) };
+/// assert_eq!(signals.next_timeout(Duration::from_secs(5)), Some(libc::SIGUSR1));
+/// ```
+#[derive(Debug)]
+pub struct Signals {
+    id: usize,
+    signals: Vec<i32>,
+    shutdown: bool,
+    receiver: Receiver<i32>,
+}
+impl Signals {
+    /// `new` listens to the given signals without affecting
+    /// [`shutdown_requested`], see [`Signals::shutdown`]
+    pub fn new(signals: impl IntoIterator<Item = i32>) -> Result<Signals, Error> {
+        Signals::listen(signals, false)
+    }
+
+    /// `shutdown` listens to the [`SHUTDOWN_SIGNALS`] which then set
+    /// the flag returned by [`shutdown_requested`]
+    pub fn shutdown() -> Result<Signals, Error> {
+        Signals::listen(SHUTDOWN_SIGNALS, true)
+    }
+
+    fn listen(signals: impl IntoIterator<Item = i32>, shutdown: bool) -> Result<Signals, Error> {
+        let mut signals = signals.into_iter().collect::<Vec<i32>>();
+        signals.sort();
+        signals.dedup();
+        start_dispatcher()?;
+        let mut registry = lock_registry();
+        let mut installed = Vec::<i32>::new();
+        for signal in signals.iter().copied() {
+            if let Some(handler) = registry.handlers.get_mut(&signal) {
+                handler.listeners += 1;
+                handler.shutdown += usize::from(shutdown);
+                installed.push(signal);
+                continue;
+            }
+            match install(signal) {
+                Ok(previous) => {
+                    registry.handlers.insert(
+                        signal,
+                        Handler {
+                            listeners: 1,
+                            shutdown: usize::from(shutdown),
+                            previous,
+                        },
+                    );
+                    installed.push(signal);
+                },
+                Err(error) => {
+                    release(&mut registry, &installed, shutdown);
+                    return Err(error);
+                },
+            }
+        }
+        let (sender, receiver) = mpsc::channel::<i32>();
+        let id = registry.next_id;
+        registry.next_id += 1;
+        registry.listeners.push(Listener {
+            id,
+            signals: signals.clone(),
+            sender,
+            forward: Vec::new(),
+        });
+        Ok(Signals {
+            id,
+            signals,
+            shutdown,
+            receiver,
+        })
+    }
+
+    pub fn signals(&self) -> &[i32] {
+        &self.signals
+    }
+
+    /// `forward` sends every signal subsequently received to the
+    /// process group `pgid`, e.g.: that of a [`crate::Cmd`] with
+    /// [`crate::Cmd::process_group`] once spawned, which is the pid
+    /// of its child.
+    ///
+    /// A `pgid` of `0` or less is rejected as it would signal the
+    /// process group of the current process or a single process.
+    pub fn forward(&self, pgid: i32) -> Result<(), Error> {
+        if pgid <= 0 {
+            return Err(traceback!(
+                SystemError,
+                "invalid process group {} to forward signals to",
+                pgid
+            ));
+        }
+        let mut registry = lock_registry();
+        if let Some(listener) =
+            registry.listeners.iter_mut().find(|listener| listener.id == self.id)
+        {
+            listener.forward.push(pgid);
+        }
+        Ok(())
+    }
+
+    /// `try_next` returns a pending signal without blocking
+    pub fn try_next(&mut self) -> Option<i32> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// `next_timeout` waits up to `timeout` for a signal
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<i32> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+}
+impl Iterator for Signals {
+    type Item = i32;
+
+    /// `next` blocks until a signal is received
+    fn next(&mut self) -> Option<i32> {
+        self.receiver.recv().ok()
+    }
+}
+impl Drop for Signals {
+    fn drop(&mut self) {
+        let mut registry = lock_registry();
+        registry.listeners.retain(|listener| listener.id != self.id);
+        release(&mut registry, &self.signals, self.shutdown);
+    }
+}
+
+fn lock_registry() -> std::sync::MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    // the interrupted code may inspect errno right after the handler
+    // returns, hence `write` must not clobber it
+    let errno = unsafe { *errno_location() };
+    let fd = PIPE_WRITE_FD.load(Ordering::SeqCst);
+    if fd >= 0 {
+        let byte = signal as u8;
+        unsafe { libc::write(fd, &byte as *const u8 as *const libc::c_void, 1) };
+    }
+    unsafe { *errno_location() = errno };
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn errno_location() -> *mut libc::c_int {
+    unsafe { libc::__errno_location() }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+unsafe fn errno_location() -> *mut libc::c_int {
+    unsafe { libc::__error() }
+}
+
+fn install(signal: i32) -> Result<libc::sigaction, Error> {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle_signal as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(signal, &action, &mut previous) != 0 {
+            return Err(traceback!(
+                SystemError,
+                "installing handler for signal {}: {}",
+                signal,
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(previous)
+    }
+}
+
+/// `release` restores the previous disposition of the signals no
+/// longer listened to
+fn release(registry: &mut Registry, signals: &[i32], shutdown: bool) {
+    for signal in signals {
+        if let Some(handler) = registry.handlers.get_mut(signal) {
+            handler.listeners -= 1;
+            handler.shutdown -= usize::from(shutdown);
+            if handler.listeners == 0 {
+                if let Some(handler) = registry.handlers.remove(signal) {
+                    unsafe { libc::sigaction(*signal, &handler.previous, std::ptr::null_mut()) };
+                }
+            }
+        }
+    }
+}
+
+/// `start_dispatcher` creates the self-pipe and the thread which
+/// dispatches the signals read from it, once
+fn start_dispatcher() -> Result<(), Error> {
+    let _registry = lock_registry();
+    if PIPE_WRITE_FD.load(Ordering::SeqCst) >= 0 {
+        return Ok(());
+    }
+    let (reader, writer) = crate::sh::cmd::pipe()
+        .map_err(|error| traceback!(SystemError, "creating signal pipe: {}", error))?;
+    let (read_fd, write_fd) = (reader.into_raw_fd(), writer.into_raw_fd());
+    // a full pipe drops signals rather than blocking the handler
+    unsafe { libc::fcntl(write_fd, libc::F_SETFL, libc::O_NONBLOCK) };
+    std::thread::Builder::new()
+        .name("iocore-signals".to_string())
+        .spawn(move || dispatch(read_fd))
+        .map_err(|error| traceback!(SystemError, "spawning signal dispatcher: {}", error))?;
+    PIPE_WRITE_FD.store(write_fd, Ordering::SeqCst);
+    Ok(())
+}
+
+fn dispatch(read_fd: i32) {
+    let mut byte = 0u8;
+    loop {
+        let count = unsafe { libc::read(read_fd, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+        if count < 0 && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+            continue;
+        }
+        if count <= 0 {
+            break;
+        }
+        let signal = byte as i32;
+        let registry = lock_registry();
+        // set before notifying listeners such that they observe it
+        if registry.handlers.get(&signal).is_some_and(|handler| handler.shutdown > 0) {
+            SHUTDOWN.store(true, Ordering::SeqCst);
+        }
+        for listener in
+            registry.listeners.iter().filter(|listener| listener.signals.contains(&signal))
+        {
+            for pgid in &listener.forward {
+                unsafe { libc::kill(-pgid, signal) };
+            }
+            let _ = listener.sender.send(signal);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::ffi::{CStr, CString};
+
+use crate::sys::passwd::{GroupEntry, GroupFile, PasswdEntry, PasswdFile};
+
+const PASSWD_PATH: &str = "/etc/passwd";
+const GROUP_PATH: &str = "/etc/group";
+
+const MAX_BUFFER_SIZE: usize = 1 << 20;
+
+pub(crate) fn passwd_by_uid(uid: u32) -> Option<PasswdEntry> {
+    native_passwd(|passwd, buffer, size, result| unsafe {
+        libc::getpwuid_r(uid, passwd, buffer, size, result)
+    })
+    .or_else(|| passwd_file_entries()?.into_iter().find(|entry| entry.uid == uid))
+}
+
+pub(crate) fn passwd_by_name(name: &str) -> Option<PasswdEntry> {
+    let cname = CString::new(name).ok()?;
+    native_passwd(|passwd, buffer, size, result| unsafe {
+        libc::getpwnam_r(cname.as_ptr(), passwd, buffer, size, result)
+    })
+    .or_else(|| passwd_file_entries()?.into_iter().find(|entry| entry.name == name))
+}
+
+pub(crate) fn group_by_gid(gid: u32) -> Option<GroupEntry> {
+    native_group(|group, buffer, size, result| unsafe {
+        libc::getgrgid_r(gid, group, buffer, size, result)
+    })
+    .or_else(|| group_file_entries()?.into_iter().find(|entry| entry.gid == gid))
+}
+
+pub(crate) fn group_by_name(name: &str) -> Option<GroupEntry> {
+    let cname = CString::new(name).ok()?;
+    native_group(|group, buffer, size, result| unsafe {
+        libc::getgrnam_r(cname.as_ptr(), group, buffer, size, result)
+    })
+    .or_else(|| group_file_entries()?.into_iter().find(|entry| entry.name == name))
+}
+
+/// `group_list` returns the gids of the groups which the user
+/// `name` belongs to, including the primary group `gid`
+pub(crate) fn group_list(name: &str, gid: u32) -> Vec<u32> {
+    native_group_list(name, gid).unwrap_or_else(|| {
+        let mut gids = vec![gid];
+        for entry in group_file_entries().unwrap_or_default() {
+            if entry.members.iter().any(|member| member == name) && !gids.contains(&entry.gid) {
+                gids.push(entry.gid);
+            }
+        }
+        gids
+    })
+}
+
+fn native_passwd(
+    lookup: impl Fn(
+        *mut libc::passwd,
+        *mut libc::c_char,
+        libc::size_t,
+        *mut *mut libc::passwd,
+    ) -> libc::c_int,
+) -> Option<PasswdEntry> {
+    let mut size = 1024;
+    loop {
+        let mut buffer = vec![0 as libc::c_char; size];
+        let mut passwd = unsafe { std::mem::zeroed::<libc::passwd>() };
+        let mut result = std::ptr::null_mut::<libc::passwd>();
+        match lookup(&mut passwd, buffer.as_mut_ptr(), size, &mut result) {
+            libc::ERANGE if size < MAX_BUFFER_SIZE => size *= 2,
+            0 if !result.is_null() =>
+                return Some(PasswdEntry {
+                    name: c_string(passwd.pw_name),
+                    password: c_string(passwd.pw_passwd),
+                    uid: passwd.pw_uid,
+                    gid: passwd.pw_gid,
+                    class: None,
+                    change: None,
+                    expire: None,
+                    gecos: c_string(passwd.pw_gecos),
+                    home: c_string(passwd.pw_dir),
+                    shell: c_string(passwd.pw_shell),
+                }),
+            _ => return None,
+        }
+    }
+}
+
+fn native_group(
+    lookup: impl Fn(
+        *mut libc::group,
+        *mut libc::c_char,
+        libc::size_t,
+        *mut *mut libc::group,
+    ) -> libc::c_int,
+) -> Option<GroupEntry> {
+    let mut size = 1024;
+    loop {
+        let mut buffer = vec![0 as libc::c_char; size];
+        let mut group = unsafe { std::mem::zeroed::<libc::group>() };
+        let mut result = std::ptr::null_mut::<libc::group>();
+        match lookup(&mut group, buffer.as_mut_ptr(), size, &mut result) {
+            libc::ERANGE if size < MAX_BUFFER_SIZE => size *= 2,
+            0 if !result.is_null() => {
+                let mut members = Vec::<String>::new();
+                if !group.gr_mem.is_null() {
+                    let mut index = 0;
+                    loop {
+                        let member = unsafe { *group.gr_mem.add(index) };
+                        if member.is_null() {
+                            break;
+                        }
+                        members.push(c_string(member));
+                        index += 1;
+                    }
+                }
+                return Some(GroupEntry {
+                    name: c_string(group.gr_name),
+                    password: c_string(group.gr_passwd),
+                    gid: group.gr_gid,
+                    members,
+                });
+            },
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+type GroupListId = libc::c_int;
+#[cfg(not(target_vendor = "apple"))]
+type GroupListId = libc::gid_t;
+
+#[allow(clippy::unnecessary_cast)]
+fn native_group_list(name: &str, gid: u32) -> Option<Vec<u32>> {
+    let cname = CString::new(name).ok()?;
+    let mut count: libc::c_int = 64;
+    loop {
+        let mut groups = vec![0 as GroupListId; count as usize];
+        let mut found = count;
+        let code = unsafe {
+            libc::getgrouplist(cname.as_ptr(), gid as GroupListId, groups.as_mut_ptr(), &mut found)
+        };
+        if code >= 0 {
+            groups.truncate(found.max(0) as usize);
+            let mut gids = Vec::<u32>::new();
+            for gid in groups.into_iter().map(|gid| gid as u32) {
+                if !gids.contains(&gid) {
+                    gids.push(gid);
+                }
+            }
+            return Some(gids);
+        }
+        if count >= 65536 {
+            return None;
+        }
+        count = if found > count { found } else { count * 2 };
+    }
+}
+
+fn c_string(pointer: *const libc::c_char) -> String {
+    if pointer.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(pointer) }.to_string_lossy().to_string()
+    }
+}
+
+fn passwd_file_entries() -> Option<Vec<PasswdEntry>> {
+    Some(PasswdFile::read(PASSWD_PATH).ok()?.entries().into_iter().cloned().collect())
+}
+
+fn group_file_entries() -> Option<Vec<GroupEntry>> {
+    Some(GroupFile::read(GROUP_PATH).ok()?.entries().into_iter().cloned().collect())
+}
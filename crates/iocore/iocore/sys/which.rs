@@ -0,0 +1,128 @@
+use std::os::unix::fs::MetadataExt;
+
+use crate::{traceback, Error, Path, PathPermissions};
+
+/// `DEFAULT_SEARCH_PATH` is the search path used by `execvp` when
+/// `PATH` is unset or empty
+pub const DEFAULT_SEARCH_PATH: &str = "/bin:/usr/bin";
+
+/// `search_path` returns the directories listed in the `PATH`
+/// environment variable of the current process or
+/// [`DEFAULT_SEARCH_PATH`] if it is unset or empty
+pub fn search_path() -> Vec<Path> {
+    split_search_path(
+        &crate::env::try_var("PATH")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .unwrap_or(DEFAULT_SEARCH_PATH.to_string()),
+    )
+}
+
+/// `split_search_path` splits a colon-separated search path into
+/// directories, empty entries denote the current directory.
+pub fn split_search_path(search_path: &str) -> Vec<Path> {
+    if search_path.is_empty() {
+        return Vec::new();
+    }
+    search_path
+        .split(':')
+        .map(|dir| if dir.is_empty() { Path::raw(".") } else { Path::raw(dir) })
+        .collect()
+}
+
+/// `which` returns the first executable named `name` in `$PATH`
+///
+/// Example
+///
+/// ```
+/// use iocore::which;
+/// assert!(which("sh").unwrap().to_string().ends_with("/sh"));
+/// assert!(which("iocore-command-which-does-not-exist").is_err());
+/// ```
+pub fn which(name: impl std::fmt::Display) -> Result<Path, Error> {
+    which_in(name, &search_path())
+}
+
+/// `which_all` returns every executable named `name` in `$PATH` in
+/// order of precedence
+pub fn which_all(name: impl std::fmt::Display) -> Vec<Path> {
+    which_all_in(name, &search_path())
+}
+
+/// `which_in` returns the first executable named `name` within the
+/// given directories or an error listing the directories searched.
+///
+/// Names containing a `/` are not searched but checked directly.
+pub fn which_in(name: impl std::fmt::Display, dirs: &[Path]) -> Result<Path, Error> {
+    let name = name.to_string();
+    match which_all_in(&name, dirs).into_iter().next() {
+        Some(path) => Ok(path),
+        None if name.contains('/') =>
+            Err(traceback!(ShellCommandError, "command not found: {:#?}", name)),
+        None => Err(traceback!(
+            ShellCommandError,
+            "command not found: {:#?} (searched: {})",
+            name,
+            dirs.iter().map(|dir| dir.to_string()).collect::<Vec<String>>().join(":")
+        )),
+    }
+}
+
+/// `which_all_in` returns every executable named `name` within the
+/// given directories in order of precedence
+pub fn which_all_in(name: impl std::fmt::Display, dirs: &[Path]) -> Vec<Path> {
+    let name = name.to_string();
+    if name.is_empty() {
+        return Vec::new();
+    }
+    if name.contains('/') {
+        let path = Path::raw(&name);
+        return if is_executable(&path) { vec![path] } else { Vec::new() };
+    }
+    let mut found = Vec::<Path>::new();
+    for path in dirs.iter().map(|dir| dir.join(&name)) {
+        if is_executable(&path) && !found.contains(&path) {
+            found.push(path);
+        }
+    }
+    found
+}
+
+/// `is_executable` returns true if `path` is a regular file (following
+/// symlinks) which the effective user of the current process may
+/// execute according to its permissions, uid and gid.
+pub fn is_executable(path: &Path) -> bool {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return false,
+    };
+    if !metadata.is_file() {
+        return false;
+    }
+    let permissions = PathPermissions::from_u32(metadata.mode()).unwrap_or_default();
+    let euid = unsafe { libc::geteuid() };
+    if euid == 0 {
+        return permissions.user().executable()
+            || permissions.group().executable()
+            || permissions.other().executable();
+    }
+    if metadata.uid() == euid {
+        permissions.user().executable()
+    } else if effective_gids().contains(&metadata.gid()) {
+        permissions.group().executable()
+    } else {
+        permissions.other().executable()
+    }
+}
+
+fn effective_gids() -> Vec<u32> {
+    let mut gids = vec![unsafe { libc::getegid() }];
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    if count > 0 {
+        let mut groups = vec![0 as libc::gid_t; count as usize];
+        let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+        groups.truncate(count.max(0) as usize);
+        gids.extend(groups);
+    }
+    gids
+}
@@ -1,116 +1,154 @@
 use dumbeq::DumbEq;
-use thread_groups::ThreadGroup;
 
-use crate::{traceback, Error, Path};
+use crate::{check_shutdown, traceback, Error, Path};
 
 pub type MaxDepth = usize;
 pub type Depth = usize;
 
-fn iocore_walk_dir(
-    path: &Path,
-    mut handler: impl WalkProgressHandler,
-    max_depth: Option<MaxDepth>,
-    depth: Option<Depth>,
-) -> Result<Vec<Path>, Error> {
-    let path = Into::<Path>::into(path);
-    let max_depth = max_depth.unwrap_or(usize::MAX);
-    let depth = depth.unwrap_or(0) + 1;
-    if !path.exists() {
-        return Err(traceback!(
-            WalkDirError,
-            "path {:#?} does not exist [depth: {}]",
-            path.to_string(),
-            depth
-        ));
+/// `WalkDir` walks a directory depth-first, lazily, obeying the
+/// protocol of a [`WalkProgressHandler`]. It yields each entry of the
+/// directory which `path_matching` accepts followed by the paths
+/// yielded by the walk of that entry, when it is a directory to scan,
+/// which `progress_out` and `path_matching` accept in turn.
+///
+/// Errors of a subdirectory end the walk of that subdirectory only,
+/// errors of the directory itself are yielded once and end the walk.
+pub(crate) struct WalkDir<H: WalkProgressHandler> {
+    handler: H,
+    max_depth: MaxDepth,
+    depth: Depth,
+    entries: std::vec::IntoIter<Path>,
+    subdirectory: Option<Box<WalkDir<H>>>,
+    done: bool,
+}
+impl<H: WalkProgressHandler> WalkDir<H> {
+    pub(crate) fn new(
+        path: &Path,
+        handler: H,
+        max_depth: Option<MaxDepth>,
+    ) -> Result<WalkDir<H>, Error> {
+        WalkDir::directory(path, handler, max_depth.unwrap_or(usize::MAX), 1)
     }
-    if !path.is_directory() {
-        return Err(traceback!(
-            WalkDirError,
-            "path {:#?} not a directory [depth: {}]",
-            path.to_string(),
-            depth
-        ));
+
+    fn directory(
+        path: &Path,
+        handler: H,
+        max_depth: MaxDepth,
+        depth: Depth,
+    ) -> Result<WalkDir<H>, Error> {
+        if !path.exists() {
+            return Err(traceback!(
+                WalkDirError,
+                "path {:#?} does not exist [depth: {}]",
+                path.to_string(),
+                depth
+            ));
+        }
+        if !path.is_directory() {
+            return Err(traceback!(
+                WalkDirError,
+                "path {:#?} not a directory [depth: {}]",
+                path.to_string(),
+                depth
+            ));
+        }
+        let entries = if depth > max_depth {
+            Vec::new()
+        } else {
+            let path = path.absolute()?;
+            check_shutdown().and_then(|_| path.list())?
+        };
+        Ok(WalkDir {
+            handler,
+            max_depth,
+            depth,
+            entries: entries.into_iter(),
+            subdirectory: None,
+            done: false,
+        })
     }
-    let mut result = Vec::<Path>::new();
-    let mut threads: ThreadGroup<Result<Vec<Path>, Error>> =
-        ThreadGroup::with_id(format!("walk_dir:{}", path));
 
-    if depth > max_depth {
-        return Ok(result);
+    fn fail(&mut self, error: Error) -> Option<Result<Path, Error>> {
+        self.done = true;
+        Some(Err(error))
     }
-    if !path.exists() {
-        match handler.error(&path, traceback!(PathDoesNotExist, path.to_string())) {
-            Some(e) => Err(traceback!(WalkDirError, "{} [depth:{}]", e, depth))?,
-            None => {},
+
+    /// `aggregate` returns whether `path_matching` accepts `path`,
+    /// or the error which ends the walk
+    fn aggregate(&mut self, path: &Path) -> Result<bool, Error> {
+        match self.handler.path_matching(path) {
+            Ok(should_aggregate_result) => Ok(should_aggregate_result),
+            Err(error) => match self.handler.error(path, error) {
+                Some(e) => Err(traceback!(WalkDirError, "{} [depth:{}]", e, self.depth)),
+                None => Ok(false),
+            },
         }
     }
-    let path = path.absolute()?;
-    for path in path.list()? {
-        handler
-            .progress_in(&path, depth)
-            .map_err(|e| traceback!(WalkDirError, "{} [depth:{}]", e, depth))?;
-        if path.is_directory() {
-            let mut handler = handler.clone();
-            match handler.should_scan_directory(&path) {
-                Ok(should_scan_path) =>
-                    if should_scan_path {
-                        let sub_path = path.clone();
-                        let handler = handler.clone();
-                        threads.spawn(move || {
-                            iocore_walk_dir(
-                                &sub_path,
-                                handler,
-                                Some(max_depth.clone()),
-                                Some(depth.clone()),
-                            )
-                        })?;
-                    },
-                Err(error) => match handler.error(&path, error) {
-                    Some(e) => Err(traceback!(WalkDirError, "{} [depth:{}]", e, depth))?,
-                    None => {},
+
+    /// `scan` starts the walk of the directory `path` unless
+    /// `should_scan_directory` declines it
+    fn scan(&mut self, path: &Path) -> Result<(), Error> {
+        let mut handler = self.handler.clone();
+        match handler.should_scan_directory(path) {
+            Ok(should_scan_path) =>
+                if should_scan_path {
+                    self.subdirectory =
+                        WalkDir::directory(path, handler, self.max_depth, self.depth + 1)
+                            .ok()
+                            .map(Box::new);
                 },
-            }
-        }
-        match handler.path_matching(&path) {
-            Ok(should_aggregate_result) =>
-                if should_aggregate_result {
-                    if !result.contains(&path) {
-                        result.push(path);
-                    }
+            Err(error) =>
+                if let Some(e) = handler.error(path, error) {
+                    return Err(traceback!(WalkDirError, "{} [depth:{}]", e, self.depth));
                 },
-            Err(error) => match handler.error(&path, error) {
-                Some(e) => Err(traceback!(WalkDirError, "{} [depth:{}]", e, depth))?,
-                None => {},
-            },
         }
+        Ok(())
     }
-    for paths in threads
-        .results()
-        .iter()
-        .filter(|path| path.is_ok())
-        .map(|path| path.clone().unwrap())
-        .flatten()
-    {
-        for path in paths.iter() {
-            handler
-                .progress_out(path)
-                .map_err(|e| traceback!(WalkDirError, "{} [depth:{}]", e, depth))?;
-            match handler.path_matching(path) {
-                Ok(should_aggregate_result) =>
-                    if should_aggregate_result {
-                        if !result.contains(&path) {
-                            result.push(path.clone());
+}
+impl<H: WalkProgressHandler> Iterator for WalkDir<H> {
+    type Item = Result<Path, Error>;
+
+    fn next(&mut self) -> Option<Result<Path, Error>> {
+        while !self.done {
+            if let Err(error) = check_shutdown() {
+                return self.fail(error);
+            }
+            if let Some(subdirectory) = self.subdirectory.as_mut() {
+                match subdirectory.next() {
+                    Some(Ok(path)) => {
+                        if let Err(e) = self.handler.progress_out(&path) {
+                            return self
+                                .fail(traceback!(WalkDirError, "{} [depth:{}]", e, self.depth));
+                        }
+                        match self.aggregate(&path) {
+                            Ok(true) => return Some(Ok(path)),
+                            Ok(false) => {},
+                            Err(error) => return self.fail(error),
                         }
                     },
-                Err(error) => match handler.error(&path, error) {
-                    Some(e) => Err(traceback!(WalkDirError, "{} [depth:{}]", e, depth))?,
-                    None => {},
-                },
+                    Some(Err(_)) | None => self.subdirectory = None,
+                }
+                continue;
+            }
+            let path = self.entries.next()?;
+            if let Err(e) = self.handler.progress_in(&path, self.depth) {
+                return self.fail(traceback!(WalkDirError, "{} [depth:{}]", e, self.depth));
+            }
+            if path.is_directory() {
+                if let Err(error) = self.scan(&path) {
+                    return self.fail(error);
+                }
+            }
+            match self.aggregate(&path) {
+                Ok(true) => return Some(Ok(path)),
+                Ok(false) => {},
+                Err(error) => return self.fail(error),
             }
         }
+        None
     }
-    Ok(result)
 }
+
 /// `walk_dir` traverses the directory referenced in the `path`
 /// argument recursively obeying the protocol by the `handler`
 /// argument.
@@ -123,11 +161,13 @@ pub fn walk_dir(
     max_depth: Option<usize>,
 ) -> Result<Vec<Path>, Error> {
     let path = Into::<Path>::into(path);
-    let mut result = Vec::<Path>::from_iter(
-        iocore_walk_dir(&path, handler, max_depth, None)?
-            .iter()
-            .map(|path| path.clone()),
-    );
+    let mut result = Vec::<Path>::new();
+    for path in WalkDir::new(&path, handler, max_depth)? {
+        let path = path?;
+        if !result.contains(&path) {
+            result.push(path);
+        }
+    }
     if result.len() > 2 {
         result.sort();
     }
@@ -208,13 +248,9 @@ pub trait WalkProgressHandler: Send + Sync + 'static + Clone {
     /// Default implementation always returns [`Ok(true)`].
     ///
     ///
-    /// > NOTE: [`walk_dir`] spawns (i.e.:
-    /// > [`thread_groups::ThreadGroup::spawn`]) a sub thread calling
-    /// > [`walk_dir`] (in assynchronously recursively
-    /// > fashion) with the directory referenced in the `path` argument
-    /// > which is then "joined" (via
-    /// > [`thread_groups::ThreadGroup::results`]) at the end of each
-    /// > `walk_dir` function.
+    /// > NOTE: [`walk_dir`] walks the directory referenced in the
+    /// > `path` argument depth-first, right after `path` itself, with
+    /// > a clone of the handler.
     fn should_scan_directory(&mut self, path: &Path) -> std::result::Result<bool, Error> {
         Ok(path.is_directory())
     }
@@ -227,7 +263,7 @@ pub trait WalkProgressHandler: Send + Sync + 'static + Clone {
         Some(error)
     }
     /// `progress_in` is called after scanning each path and before
-    /// heuristics from which to spawn more threads.
+    /// heuristics from which to scan more directories.
     ///
     /// This callback is suitable for tracking general progress *before*
     /// any heuristics because, unlike [`path_matching`],
@@ -235,20 +271,22 @@ pub trait WalkProgressHandler: Send + Sync + 'static + Clone {
     /// affecting heuristics.
     ///
     /// Because it runs *before* any heuristics and new possible
-    /// threads, this callback is called with the current depth of
-    /// search. The depth is synonymous to the amount of nested
-    /// threads.
+    /// directory scans, this callback is called with the current
+    /// depth of search. The depth is synonymous to the amount of
+    /// nested directories.
     fn progress_in(&mut self, _path_: &Path, _depth_: Depth) -> std::result::Result<(), Error> {
         Ok(())
     }
 
-    /// `progress_out` is called for each path scanned after all threads finish running.
+    /// `progress_out` is called for each path found in a
+    /// subdirectory, by each of its parent directories, as it is
+    /// yielded by the walk of that subdirectory.
     ///
     /// This callback is suitable for tracking general progress, not
     /// unlike [`progress_in`], but unlike [`progress_in`] it is
     /// executed *after* all heuristics.
     ///
-    /// Because it runs *after* all heuristics and threads, this
+    /// Because it runs *after* all heuristics of subdirectories, this
     /// callback does not have access to the depth of search.
     fn progress_out(&mut self, _path_: &Path) -> std::result::Result<(), Error> {
         Ok(())
@@ -270,3 +308,31 @@ impl WalkProgressHandler for NoopProgressHandler {
         Ok(path.is_directory())
     }
 }
+
+/// `hard_link_clusters` groups the given paths (e.g.: the results of
+/// [`walk_dir`]) by device and inode numbers and returns only the
+/// groups of two or more paths which are hard links to the same
+/// file.
+///
+/// Directories and symlinks are not taken into account and paths
+/// whose metadata cannot be obtained are ignored.
+pub fn hard_link_clusters(paths: &[Path]) -> Vec<Vec<Path>> {
+    use std::collections::BTreeMap;
+    use std::os::unix::fs::MetadataExt;
+
+    let mut clusters = BTreeMap::<(u64, u64), Vec<Path>>::new();
+    for path in paths {
+        let meta = match path.symlink_meta() {
+            Ok(meta) => meta,
+            Err(_) => continue,
+        };
+        if meta.is_dir() || meta.is_symlink() {
+            continue;
+        }
+        let cluster = clusters.entry((meta.dev(), meta.ino())).or_default();
+        if !cluster.contains(path) {
+            cluster.push(path.clone());
+        }
+    }
+    clusters.into_values().filter(|cluster| cluster.len() > 1).collect()
+}
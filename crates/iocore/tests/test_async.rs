@@ -0,0 +1,240 @@
+#![cfg(feature = "async")]
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Waker};
+use std::time::{Duration, Instant};
+
+use iocore::asynchronous::{
+    shell_command_stdout_async, shell_command_vec_output_async, spawn_blocking, walk_dir_stream,
+    Stream,
+};
+use iocore::{walk_dir, Cmd, Error, NoopProgressHandler, Path, WalkProgressHandler};
+use iocore_test::{folder_path, path_to_test_folder};
+use pollster::block_on;
+
+fn tree(dir: Path) -> Path {
+    for file in ["a/b/c.txt", "a/d.txt", "e/f/g/h.txt", "i.txt"] {
+        let path = dir.join(file);
+        path.parent().unwrap().mkdir_parents().unwrap();
+        path.write(file.as_bytes()).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_path_async_io_matches_sync_io() {
+    let dir = path_to_test_folder!();
+    dir.list().unwrap().iter().for_each(|path| {
+        path.delete_unchecked();
+    });
+    let file = dir.join("file.txt");
+    block_on(file.write_async("one\n")).unwrap();
+    assert_eq!(block_on(file.append_async(b"two\n")).unwrap(), 4);
+    assert_eq!(block_on(file.read_async()).unwrap(), "one\ntwo\n");
+    assert_eq!(block_on(file.read_bytes_async()).unwrap(), file.read_bytes().unwrap());
+    assert_eq!(block_on(file.read_lines_async()).unwrap(), file.read_lines().unwrap());
+    assert_eq!(block_on(dir.list_async()).unwrap(), vec![file.clone()]);
+    block_on(file.delete_async()).unwrap();
+    assert_eq!(block_on(file.read_async()).unwrap_err(), file.read().unwrap_err());
+}
+
+#[test]
+fn test_sh_async_matches_sync() {
+    assert_eq!(
+        block_on(shell_command_stdout_async("echo hello", Path::cwd())).unwrap(),
+        "hello\n"
+    );
+    let (code, stdout, stderr) =
+        block_on(shell_command_vec_output_async("sh -c 'echo out; echo err >&2; exit 2'", "."))
+            .unwrap();
+    assert_eq!((code, stdout, stderr), (2, b"out\n".to_vec(), b"err\n".to_vec()));
+    let cmd = Cmd::new("sh").args(["-c", "exit 5"]);
+    assert_eq!(block_on(cmd.status_async()).unwrap(), 5);
+    let error = block_on(cmd.check_async()).unwrap_err();
+    assert!(matches!(
+        &error,
+        Error::SubprocessError(failure)
+            if failure.output.as_ref().and_then(|output| output.code) == Some(5)
+    ));
+    // the same error as the sync variant but for the duration
+    let sync_error = cmd.check().unwrap_err().to_string();
+    assert_eq!(error.to_string().split(" after ").next(), sync_error.split(" after ").next());
+}
+
+#[test]
+fn test_spawn_blocking_does_not_block_the_poller() {
+    let mut context = Context::from_waker(Waker::noop());
+    let mut future = pin!(Cmd::new("sleep").arg("0.2").status_async());
+    assert!(future.as_mut().poll(&mut context).is_pending());
+    assert_eq!(block_on(future).unwrap(), 0);
+    let panicked = block_on(spawn_blocking(|| -> Result<(), Error> { panic!("boom") }));
+    assert!(panicked.is_err());
+}
+
+#[test]
+fn test_walk_dir_stream_matches_walk_dir() {
+    let fixtures = folder_path!("fixtures");
+    assert_eq!(
+        block_on(walk_dir_stream(&fixtures, NoopProgressHandler, None).collect()).unwrap(),
+        walk_dir(&fixtures, NoopProgressHandler, None).unwrap()
+    );
+
+    let dir = tree(path_to_test_folder!());
+    let expected = walk_dir(&dir, NoopProgressHandler, None).unwrap();
+    assert_eq!(
+        block_on(walk_dir_stream(&dir, NoopProgressHandler, None).collect()).unwrap(),
+        expected
+    );
+    assert_eq!(
+        block_on(walk_dir_stream(&dir, NoopProgressHandler, Some(2)).collect()).unwrap(),
+        walk_dir(&dir, NoopProgressHandler, Some(2)).unwrap()
+    );
+
+    let mut stream = walk_dir_stream(&dir, NoopProgressHandler, None);
+    let mut found = Vec::<Path>::new();
+    while let Some(path) =
+        block_on(std::future::poll_fn(|cx| std::pin::Pin::new(&mut stream).poll_next(cx)))
+    {
+        found.push(path.unwrap());
+    }
+    // depth-first, as they are found
+    assert_eq!(
+        found.iter().map(|path| path.relative_to(&dir).to_string()).collect::<Vec<String>>(),
+        vec![
+            "a",
+            "a/b",
+            "a/b/c.txt",
+            "a/d.txt",
+            "e",
+            "e/f",
+            "e/f/g",
+            "e/f/g/h.txt",
+            "i.txt"
+        ]
+    );
+    found.sort();
+    assert_eq!(found, expected);
+
+    let mut stream = walk_dir_stream(&dir, NoopProgressHandler, None);
+    assert!(block_on(stream.next()).unwrap().is_ok());
+    drop(stream);
+
+    let error = block_on(walk_dir_stream(dir.join("i.txt"), NoopProgressHandler, None).collect())
+        .unwrap_err();
+    assert!(error.to_string().contains("not a directory"), "{}", error);
+}
+
+#[derive(Clone, Default)]
+struct RecordingHandler {
+    progress_out: Arc<Mutex<Vec<Path>>>,
+}
+impl WalkProgressHandler for RecordingHandler {
+    fn path_matching(&mut self, path: &Path) -> std::result::Result<bool, Error> {
+        Ok(path.exists())
+    }
+
+    fn progress_out(&mut self, path: &Path) -> std::result::Result<(), Error> {
+        self.progress_out.lock().unwrap().push(path.clone());
+        Ok(())
+    }
+}
+impl RecordingHandler {
+    fn recorded(&self) -> Vec<Path> {
+        let mut paths = self.progress_out.lock().unwrap().clone();
+        paths.sort();
+        paths
+    }
+}
+
+#[test]
+fn test_walk_dir_stream_unreadable_subdirectory() {
+    let dir = tree(path_to_test_folder!());
+    let mut locked = dir.join("e/f").set_mode(0o000).unwrap();
+
+    let handler = RecordingHandler::default();
+    let expected = walk_dir(&dir, handler.clone(), None).unwrap();
+    let stream = RecordingHandler::default();
+    assert_eq!(
+        block_on(walk_dir_stream(&dir, stream.clone(), None).collect()).unwrap(),
+        expected
+    );
+    // `progress_out` is called for paths found in subdirectories only
+    assert!(!stream.recorded().contains(&dir.join("a")));
+    assert!(stream.recorded().contains(&dir.join("a/b/c.txt")));
+    assert_eq!(stream.recorded(), handler.recorded());
+
+    locked.set_mode(0o755).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// `GatedHandler` holds the walk at the path named `hold` until
+/// `released`, or for 10 seconds, counting the calls to `progress_in`
+#[derive(Clone)]
+struct GatedHandler {
+    hold: &'static str,
+    released: Arc<AtomicBool>,
+    entered: Arc<AtomicUsize>,
+}
+impl GatedHandler {
+    fn new(hold: &'static str) -> GatedHandler {
+        GatedHandler {
+            hold,
+            released: Arc::new(AtomicBool::new(false)),
+            entered: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+impl WalkProgressHandler for GatedHandler {
+    fn path_matching(&mut self, path: &Path) -> std::result::Result<bool, Error> {
+        Ok(path.exists())
+    }
+
+    fn progress_in(&mut self, path: &Path, _depth: usize) -> std::result::Result<(), Error> {
+        self.entered.fetch_add(1, Ordering::SeqCst);
+        let started = Instant::now();
+        while path.name() == self.hold
+            && !self.released.load(Ordering::SeqCst)
+            && started.elapsed() < Duration::from_secs(10)
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_walk_dir_stream_yields_before_the_walk_finishes() {
+    let dir = tree(path_to_test_folder!());
+    let handler = GatedHandler::new("i.txt");
+    let started = Instant::now();
+    let mut stream = walk_dir_stream(&dir, handler.clone(), None);
+    let first = block_on(stream.next()).unwrap().unwrap();
+    assert_eq!(first.relative_to(&dir).to_string(), "a");
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(!handler.released.load(Ordering::SeqCst));
+
+    handler.released.store(true, Ordering::SeqCst);
+    let rest = block_on(stream.collect()).unwrap();
+    assert_eq!(rest.len() + 1, walk_dir(&dir, NoopProgressHandler, None).unwrap().len());
+}
+
+#[test]
+fn test_walk_dir_stream_stops_once_dropped() {
+    let dir = path_to_test_folder!();
+    dir.join("a.txt").write(b"a").unwrap();
+    for index in 0..200 {
+        dir.join(format!("b{:03}.txt", index)).write(b"b").unwrap();
+    }
+    let handler = GatedHandler::new("b000.txt");
+    let mut stream = walk_dir_stream(&dir, handler.clone(), None);
+    let first = block_on(stream.next()).unwrap().unwrap();
+    assert_eq!(first.relative_to(&dir).to_string(), "a.txt");
+    drop(stream);
+    handler.released.store(true, Ordering::SeqCst);
+
+    std::thread::sleep(Duration::from_millis(500));
+    // `a.txt` and `b000.txt`, whose path could not be sent
+    assert_eq!(handler.entered.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,235 @@
+use std::sync::{Mutex, MutexGuard};
+
+use iocore::env::{
+    load_dotenv, load_dotenv_override, parse_dotenv, try_var, var_as, var_bool, var_datetime,
+    var_list, var_or, var_path, var_paths, var_size, vars, EnvSnapshot,
+};
+use iocore::{
+    try_env_var, ByteUnit, EnvVarError, EnvVarErrorKind, Error, Path, PathDateTime, Result, Size,
+};
+use iocore_test::path_to_test_file;
+
+static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+/// `lock_environment` serializes tests which modify the environment
+/// since `EnvSnapshot` restores it as a whole
+fn lock_environment() -> MutexGuard<'static, ()> {
+    ENVIRONMENT.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[test]
+fn test_try_var() {
+    let _lock = lock_environment();
+    std::env::set_var("IOCORE_TEST_TRY_VAR", "value");
+    assert_eq!(try_var("IOCORE_TEST_TRY_VAR"), Ok("value".to_string()));
+    assert_eq!(
+        try_var("IOCORE_TEST_TRY_VAR_UNSET"),
+        Err(EnvVarError {
+            key: "IOCORE_TEST_TRY_VAR_UNSET".to_string(),
+            value: None,
+            kind: EnvVarErrorKind::NotPresent,
+        })
+    );
+    assert_eq!(
+        try_env_var!("IOCORE_TEST_TRY_VAR_UNSET").map_err(Error::from),
+        Err(Error::EnvironmentVarError(
+            "environment variable \"IOCORE_TEST_TRY_VAR_UNSET\" is not set".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_var_as_and_var_or() -> Result<()> {
+    let _lock = lock_environment();
+    std::env::set_var("IOCORE_TEST_VAR_AS", " 8080 ");
+    std::env::set_var("IOCORE_TEST_VAR_AS_INVALID", "eighty");
+    assert_eq!(var_as::<u16>("IOCORE_TEST_VAR_AS")?, 8080);
+    assert_eq!(try_env_var!("IOCORE_TEST_VAR_AS", u32)?, 8080);
+    let error = var_as::<u16>("IOCORE_TEST_VAR_AS_INVALID").unwrap_err();
+    assert_eq!(error.value, Some("eighty".to_string()));
+    assert_eq!(
+        error.kind,
+        EnvVarErrorKind::Invalid("invalid digit found in string".to_string())
+    );
+    assert_eq!(var_or("IOCORE_TEST_VAR_AS", 80u16)?, 8080);
+    assert_eq!(
+        var_or("IOCORE_TEST_VAR_AS_INVALID", 80u16).unwrap_err().kind,
+        EnvVarErrorKind::Invalid("invalid digit found in string".to_string())
+    );
+    assert_eq!(var_or("IOCORE_TEST_VAR_AS_UNSET", 80u16)?, 80);
+    Ok(())
+}
+
+#[test]
+fn test_var_bool() -> Result<()> {
+    let _lock = lock_environment();
+    for (value, expected) in [
+        ("1", true),
+        ("TRUE", true),
+        ("yes", true),
+        ("On", true),
+        ("0", false),
+        ("false", false),
+        ("no", false),
+        ("off", false),
+        ("", false),
+    ] {
+        std::env::set_var("IOCORE_TEST_VAR_BOOL", value);
+        assert_eq!(var_bool("IOCORE_TEST_VAR_BOOL")?, expected, "{:#?}", value);
+    }
+    std::env::set_var("IOCORE_TEST_VAR_BOOL_INVALID", "maybe");
+    assert!(var_bool("IOCORE_TEST_VAR_BOOL_INVALID").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_var_list_and_paths() -> Result<()> {
+    let _lock = lock_environment();
+    std::env::set_var("IOCORE_TEST_VAR_LIST", "/usr/local/bin::/usr/bin:");
+    assert_eq!(var_list("IOCORE_TEST_VAR_LIST")?, vec!["/usr/local/bin", "/usr/bin"]);
+    assert_eq!(
+        var_paths("IOCORE_TEST_VAR_LIST")?,
+        vec![Path::raw("/usr/local/bin"), Path::raw("/usr/bin")]
+    );
+    std::env::set_var("IOCORE_TEST_VAR_PATH", "/tmp");
+    assert_eq!(var_path("IOCORE_TEST_VAR_PATH")?, Path::raw("/tmp"));
+    Ok(())
+}
+
+#[test]
+fn test_var_size() -> Result<()> {
+    let _lock = lock_environment();
+    for (value, expected) in [
+        ("512", 512),
+        ("64K", 64 * 1024),
+        ("10Mb", 10 * ByteUnit::Mega.as_u64()),
+        ("1 g", ByteUnit::Giga.as_u64()),
+    ] {
+        std::env::set_var("IOCORE_TEST_VAR_SIZE", value);
+        assert_eq!(var_size("IOCORE_TEST_VAR_SIZE")?, Size::from(expected), "{:#?}", value);
+    }
+    std::env::set_var("IOCORE_TEST_VAR_SIZE_INVALID", "12 parsecs");
+    assert!(var_size("IOCORE_TEST_VAR_SIZE_INVALID").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_var_datetime() -> Result<()> {
+    let _lock = lock_environment();
+    std::env::set_var("IOCORE_TEST_VAR_DATETIME", "1000000000");
+    assert_eq!(
+        var_datetime("IOCORE_TEST_VAR_DATETIME")?,
+        PathDateTime::from_timestamp(1_000_000_000, 0)
+    );
+    std::env::set_var("IOCORE_TEST_VAR_DATETIME_RFC3339", "2001-09-09T01:46:40Z");
+    assert_eq!(
+        var_datetime("IOCORE_TEST_VAR_DATETIME_RFC3339")?,
+        PathDateTime::from_timestamp(1_000_000_000, 0)
+    );
+    std::env::set_var("IOCORE_TEST_VAR_DATETIME_INVALID", "yesterday");
+    assert!(var_datetime("IOCORE_TEST_VAR_DATETIME_INVALID").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_dotenv() -> Result<()> {
+    let _lock = lock_environment();
+    std::env::set_var("IOCORE_TEST_DOTENV_HOME", "/home/user");
+    let vars = parse_dotenv(
+        r#"
+# database settings
+export DB_HOST = localhost
+DB_PORT=5432 # inline comment
+DB_URL="postgres://${DB_HOST}:$DB_PORT/app"
+LITERAL='${DB_HOST} stays #literal'
+ESCAPED="line\nbreak \"quoted\" \$DB_HOST"
+MULTILINE="first
+second"
+DEFAULTED=${IOCORE_TEST_DOTENV_UNSET:-fallback}
+FROM_ENV=$IOCORE_TEST_DOTENV_HOME/.config
+HASH=a#b
+EMPTY=
+"#,
+    )?;
+    assert_eq!(
+        vars,
+        vec![
+            ("DB_HOST", "localhost"),
+            ("DB_PORT", "5432"),
+            ("DB_URL", "postgres://localhost:5432/app"),
+            ("LITERAL", "${DB_HOST} stays #literal"),
+            ("ESCAPED", "line\nbreak \"quoted\" $DB_HOST"),
+            ("MULTILINE", "first\nsecond"),
+            ("DEFAULTED", "fallback"),
+            ("FROM_ENV", "/home/user/.config"),
+            ("HASH", "a#b"),
+            ("EMPTY", ""),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<Vec<(String, String)>>()
+    );
+    assert!(parse_dotenv("NOT A VARIABLE").is_err());
+    assert!(parse_dotenv("1KEY=value").is_err());
+    assert!(parse_dotenv("KEY='unterminated").is_err());
+    assert!(parse_dotenv("KEY=\"unterminated").is_err());
+    assert!(parse_dotenv("KEY=\"value\" trailing").is_err());
+    assert!(parse_dotenv("KEY=$IOCORE_TEST_DOTENV_UNDEFINED/app").is_err());
+    assert!(parse_dotenv("KEY=\"${IOCORE_TEST_DOTENV_UNDEFINED}\"").is_err());
+    assert!(parse_dotenv("KEY=\"${IOCORE_TEST_DOTENV_HOME\"").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_load_dotenv_and_snapshot() -> Result<()> {
+    let _lock = lock_environment();
+    let dotenv = path_to_test_file!(".env.test_load_dotenv");
+    dotenv.write(b"IOCORE_TEST_DOTENV_NEW=new\nIOCORE_TEST_DOTENV_EXISTING=dotenv\n")?;
+    std::env::set_var("IOCORE_TEST_DOTENV_EXISTING", "existing");
+    {
+        let snapshot = EnvSnapshot::capture();
+        assert_eq!(load_dotenv(&dotenv)?.len(), 2);
+        assert_eq!(try_var("IOCORE_TEST_DOTENV_NEW"), Ok("new".to_string()));
+        assert_eq!(try_var("IOCORE_TEST_DOTENV_EXISTING"), Ok("existing".to_string()));
+        load_dotenv_override(&dotenv)?;
+        assert_eq!(try_var("IOCORE_TEST_DOTENV_EXISTING"), Ok("dotenv".to_string()));
+        assert_eq!(vars().get("IOCORE_TEST_DOTENV_NEW"), Some(&"new".to_string()));
+        snapshot.restore();
+        assert!(try_var("IOCORE_TEST_DOTENV_NEW").is_err());
+        std::env::set_var("IOCORE_TEST_DOTENV_EXISTING", "changed");
+    }
+    assert_eq!(try_var("IOCORE_TEST_DOTENV_EXISTING"), Ok("existing".to_string()));
+    {
+        let _snapshot = EnvSnapshot::capture();
+        let dotenv = path_to_test_file!(".env.test_load_dotenv_interpolation");
+        dotenv.write(
+            b"IOCORE_TEST_DOTENV_EXISTING=dotenv\nIOCORE_TEST_DOTENV_URL=http://${IOCORE_TEST_DOTENV_EXISTING}\n",
+        )?;
+        load_dotenv(&dotenv)?;
+        assert_eq!(try_var("IOCORE_TEST_DOTENV_EXISTING"), Ok("existing".to_string()));
+        assert_eq!(try_var("IOCORE_TEST_DOTENV_URL"), Ok("http://existing".to_string()));
+        load_dotenv_override(&dotenv)?;
+        assert_eq!(try_var("IOCORE_TEST_DOTENV_EXISTING"), Ok("dotenv".to_string()));
+        assert_eq!(try_var("IOCORE_TEST_DOTENV_URL"), Ok("http://dotenv".to_string()));
+        dotenv.delete()?;
+    }
+    assert!(vars().keys().zip(vars().keys().skip(1)).all(|(a, b)| a < b));
+    dotenv.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_args_from_string_quoted() {
+    assert_eq!(
+        iocore::args_from_string_quoted("cmd --name 'John Doe' \"a\\\"b\"").unwrap(),
+        vec!["cmd", "--name", "John Doe", "a\"b"]
+    );
+    assert_eq!(
+        iocore::args_from_string("cmd --name 'John Doe'"),
+        vec!["cmd", "--name", "'John", "Doe'"]
+    );
+    assert!(matches!(
+        iocore::args_from_string_quoted("cmd 'John"),
+        Err(iocore::Error::ShellCommandError(_))
+    ));
+}
@@ -1,7 +1,8 @@
 use iocore::{
-    expand_home_regex, path_str_to_relative_subpath, remove_duplicate_separators, remove_end,
-    remove_equal_prefix_from_path_strings, remove_redundant_current_path, remove_start,
-    remove_trailing_slash, repl_beg, repl_end, split_str_into_relative_subpath_parts,
+    expand_home_regex, expand_user_home, expand_variables, path_str_to_relative_subpath,
+    remove_duplicate_separators, remove_end, remove_equal_prefix_from_path_strings,
+    remove_redundant_current_path, remove_start, remove_trailing_slash, repl_beg, repl_end,
+    split_str_into_relative_subpath_parts,
 };
 
 #[test]
@@ -83,3 +84,44 @@ fn test_repl_end() {
 fn test_remove_redundant_current_path() {
     assert_eq!(remove_redundant_current_path("a/./b/./c/"), "a/b/c/");
 }
+
+#[test]
+fn test_expand_variables() {
+    let var = |name: &str| match name {
+        "XDG_CACHE_HOME" => Some("/home/user/.cache".to_string()),
+        "EMPTY" => Some(String::new()),
+        _ => None,
+    };
+    assert_eq!(expand_variables("$XDG_CACHE_HOME/app", var).unwrap(), "/home/user/.cache/app");
+    assert_eq!(expand_variables("${XDG_CACHE_HOME}app", var).unwrap(), "/home/user/.cacheapp");
+    assert_eq!(expand_variables("${MISSING:-/tmp}/app", var).unwrap(), "/tmp/app");
+    assert_eq!(expand_variables("${EMPTY:-default}", var).unwrap(), "default");
+    assert_eq!(expand_variables("a${EMPTY}b", var).unwrap(), "ab");
+    assert_eq!(expand_variables("price$/$1", var).unwrap(), "price$/$1");
+    assert_eq!(expand_variables("no/variables", var).unwrap(), "no/variables");
+    assert!(expand_variables("$MISSING/app", var).is_err());
+    assert!(expand_variables("${XDG_CACHE_HOME/app", var).is_err());
+}
+
+#[test]
+fn test_expand_user_home() {
+    let root_home = iocore::User::by_name("root").unwrap().home_dir.unwrap();
+    assert_eq!(expand_user_home("~root/.ssh").unwrap(), format!("{}/.ssh", root_home));
+    assert_eq!(expand_user_home("~root").unwrap(), root_home);
+    assert_eq!(expand_user_home("/absolute/~root").unwrap(), "/absolute/~root");
+    assert_eq!(expand_user_home("~/file").unwrap(), format!("{}file", iocore::tilde().unwrap()));
+    assert!(expand_user_home("~no-such-user-in-passwd/file").is_err());
+}
+
+#[test]
+fn test_expand_user_home_of_user_whose_home_is_root() {
+    let Ok(passwd) = iocore::PasswdFile::read("/etc/passwd") else {
+        return;
+    };
+    let Some(entry) = passwd.entries().into_iter().find(|entry| entry.home == "/") else {
+        return;
+    };
+    let user = format!("~{}", entry.name);
+    assert_eq!(expand_user_home(&user).unwrap(), "/");
+    assert_eq!(expand_user_home(&format!("{}/etc", user)).unwrap(), "/etc");
+}
@@ -1,8 +1,10 @@
 use std::io::Write;
-use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
 use std::path::MAIN_SEPARATOR_STR;
 
-use iocore::{Error, Path, PathDateTime, PathPermissions, PathStatus, PathType, Result};
+use iocore::{
+    Error, LsEntry, LsPathType, Path, PathDateTime, PathPermissions, PathStatus, PathType, Result,
+};
 use iocore_test::{
     current_source_file, folder_path, path_to_test_directory, path_to_test_file,
     path_to_test_folder, seq_bytes,
@@ -72,7 +74,7 @@ fn test_path_safe() -> Result<()> {
     let long_name = (0..64).map(|_| "noon".to_string()).collect::<String>();
     assert_eq!(
         Path::safe(long_name),
-        Err(Error::FileSystemError("path too long in macos: \"noonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoon\" [iocore::fs::Path::safe:[crates/iocore/iocore/fs.rs:89]]\n".to_string()))
+        Err(Error::FileSystemError("path too long in macos: \"noonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoonnoon\" [iocore::fs::Path::safe:[crates/iocore/iocore/fs.rs:90]]\n".to_string()))
     );
     let long_path_with_short_names = (0..256)
         .map(|_| "noon".to_string())
@@ -376,11 +378,11 @@ fn test_path_permissions() -> Result<()> {
     assert_eq!(format!("{:o}", metadata.mode()), "100640");
     assert_eq!(
         PathPermissions::from_u32(metadata.mode())?,
-        PathPermissions {
-            user: TriloByte::from(0b0110),
-            group: TriloByte::from(0b100),
-            other: TriloByte::from(0b00),
-        }
+        PathPermissions::new(
+            TriloByte::from(0b0110),
+            TriloByte::from(0b100),
+            TriloByte::from(0b00)
+        )
     );
 
     assert_eq!(file_mode_640.mode(), 0o640);
@@ -418,7 +420,7 @@ fn test_path_set_mode() -> Result<()> {
 fn test_path_set_permissions() -> Result<()> {
     let mut file = Path::tmp_file();
 
-    file.set_permissions(&PathPermissions::from_u32(0o777)?)?;
+    file.set_permissions(PathPermissions::from_u32(0o777)?)?;
     assert_eq!(format!("{:o}", file.mode()), "777");
     Ok(())
 }
@@ -443,6 +445,315 @@ fn test_path_rename() -> Result<()> {
 }
 
 
+#[test]
+fn test_path_hard_link_to() -> Result<()> {
+    let path = path_to_test_file!("test_path_hard_link_to").write(b"data")?;
+    let link = path_to_test_file!("test_path_hard_link_to.link");
+    assert_eq!(path.nlink()?, 1);
+
+    assert_eq!(path.hard_link_to(&link)?, link);
+    assert_eq!(link.read()?, "data");
+    assert_eq!(path.nlink()?, 2);
+    assert_eq!(link.nlink()?, 2);
+    assert_eq!(path.inode()?, link.inode()?);
+    assert_eq!(path.device()?, link.device()?);
+    assert!(path.hard_link_to(&link).is_err());
+
+    link.delete()?;
+    assert_eq!(path.nlink()?, 1);
+    Ok(())
+}
+
+#[test]
+fn test_path_same_file_as() -> Result<()> {
+    let path = path_to_test_file!("test_path_same_file_as").write(b"data")?;
+    let link = path_to_test_file!("test_path_same_file_as.link");
+    let copy = path_to_test_file!("test_path_same_file_as.copy").write(b"data")?;
+    path.hard_link_to(&link)?;
+
+    assert!(path.same_file_as(&link));
+    assert!(link.same_file_as(&path));
+    assert!(!path.same_file_as(&copy));
+    assert!(!path.same_file_as(&Path::raw("/non-existing/path")));
+    Ok(())
+}
+
+#[test]
+fn test_path_type() -> Result<()> {
+    let file = path_to_test_file!("test_path_type_file").write(&[])?;
+    let folder = path_to_test_folder!("test_path_type_folder");
+    let symlink = path_to_test_file!("test_path_type_symlink");
+    file.create_symlink(&symlink)?;
+    let fifo = path_to_test_file!("test_path_type_fifo");
+    assert_eq!(iocore::shell_command(format!("mkfifo {}", fifo), ".")?, 0);
+    let socket = path_to_test_file!("test_path_type_socket");
+    let _listener = std::os::unix::net::UnixListener::bind(socket.path())?;
+
+    assert_eq!(file.path_type(), PathType::File);
+    assert_eq!(folder.path_type(), PathType::Directory);
+    assert_eq!(symlink.path_type(), PathType::Symlink);
+    assert_eq!(fifo.path_type(), PathType::Fifo);
+    assert_eq!(socket.path_type(), PathType::Socket);
+    assert_eq!(Path::raw("/dev/null").path_type(), PathType::CharDevice);
+    assert_eq!(Path::raw("/non-existing/path").path_type(), PathType::None);
+    assert_eq!(symlink.kind(), PathType::Symlink);
+    assert_eq!(fifo.kind(), PathType::Fifo);
+
+    assert!(symlink.is_symlink());
+    assert!(!file.is_symlink());
+    assert_eq!(symlink.read_symlink()?, file);
+    assert_eq!(fifo.status(), PathStatus::WritableFifo);
+    assert_eq!(socket.status(), PathStatus::WritableSocket);
+    Ok(())
+}
+
+#[test]
+fn test_path_type_setuid() -> Result<()> {
+    let file = path_to_test_file!("test_path_type_setuid").write(&[])?;
+    std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o4755))?;
+    assert_eq!(file.path_type(), PathType::Setuid);
+    assert_eq!(file.status(), PathStatus::WritableSetuid);
+    assert!(file.is_setuid());
+    assert!(!file.is_setgid());
+    assert!(!file.is_sticky());
+
+    std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o2755))?;
+    assert_eq!(file.path_type(), PathType::File);
+    assert!(!file.is_setuid());
+    assert!(file.is_setgid());
+
+    let folder = path_to_test_folder!("test_path_type_sticky");
+    std::fs::set_permissions(folder.path(), std::fs::Permissions::from_mode(0o1777))?;
+    assert_eq!(folder.path_type(), PathType::Directory);
+    assert!(folder.is_sticky());
+    assert!(!folder.is_setuid());
+    Ok(())
+}
+
+#[test]
+fn test_path_set_permissions_symbolic() -> Result<()> {
+    let mut path = path_to_test_file!("test_path_set_permissions_symbolic.txt");
+    path.write(b"")?;
+    path.set_permissions("640")?;
+    assert_eq!(path.permissions().to_string_octal(), "640");
+    path.set_permissions("u+x,g-r,o+r")?;
+    assert_eq!(path.permissions().to_string_octal(), "704");
+    path.set_permissions("a=r")?;
+    assert_eq!(path.permissions().to_string_octal(), "444");
+    path.set_permissions("u+ws")?;
+    assert_eq!(path.permissions().to_string_octal(), "4644");
+    assert!(path.permissions().setuid());
+    path.set_mode(0o600)?;
+    assert_eq!(path.permissions().to_string_octal(), "600");
+    assert!(path.set_permissions("u+q").is_err());
+    path.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_path_set_permissions_recursive() -> Result<()> {
+    let mut folder = path_to_test_folder!("test_path_set_permissions_recursive").mkdir_unchecked();
+    let mut nested = folder.join("nested").mkdir()?;
+    let mut script = nested.join("script.sh").write(b"")?;
+    let mut data = nested.join("data.txt").write(b"")?;
+    script.set_mode(0o700)?;
+    data.set_mode(0o600)?;
+    nested.set_mode(0o700)?;
+    folder.set_permissions_recursive("go+rX")?;
+    assert_eq!(folder.permissions().to_string_octal(), "755");
+    assert_eq!(nested.permissions().to_string_octal(), "755");
+    assert_eq!(script.permissions().to_string_octal(), "755");
+    assert_eq!(data.permissions().to_string_octal(), "644");
+    folder.set_permissions_recursive("go=")?;
+    assert_eq!(nested.permissions().to_string_octal(), "700");
+    assert_eq!(data.permissions().to_string_octal(), "600");
+    folder.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_path_set_permissions_recursive_grants_access_to_unreadable_directories() -> Result<()> {
+    let folder = path_to_test_folder!();
+    std::fs::remove_dir_all(&folder).unwrap_or_default();
+    let mut tree = folder.join("tree").mkdir()?;
+    let mut locked = tree.join("locked").mkdir()?;
+    let mut data = locked.join("data.txt").write(b"")?;
+    data.set_mode(0o000)?;
+    locked.set_mode(0o000)?;
+    tree.set_permissions_recursive("u+rwX")?;
+    assert_eq!(locked.permissions().to_string_octal(), "700");
+    assert_eq!(data.permissions().to_string_octal(), "600");
+    tree.set_permissions_recursive("u-rwx")?;
+    tree.set_mode(0o700)?;
+    assert_eq!(locked.permissions().to_string_octal(), "000");
+    tree.set_permissions_recursive("u+rwX")?;
+    assert_eq!(locked.permissions().to_string_octal(), "700");
+    assert_eq!(data.permissions().to_string_octal(), "600");
+    std::fs::remove_dir_all(&folder)?;
+    Ok(())
+}
+
+#[test]
+fn test_path_set_permissions_recursive_does_not_follow_symlinks() -> Result<()> {
+    let folder = path_to_test_folder!();
+    std::fs::remove_dir_all(&folder).unwrap_or_default();
+    let outside = folder.join("outside").mkdir()?;
+    let mut secret = outside.join("secret").write(b"")?;
+    secret.set_mode(0o600)?;
+    let mut tree = folder.join("tree").mkdir()?;
+    let mut data = tree.join("data.txt").write(b"")?;
+    data.set_mode(0o600)?;
+    outside.create_symlink(tree.join("link"))?;
+    tree.set_permissions_recursive("a+r")?;
+    assert_eq!(data.permissions().to_string_octal(), "644");
+    assert_eq!(secret.permissions().to_string_octal(), "600");
+    std::fs::remove_dir_all(&folder)?;
+    Ok(())
+}
+
+#[test]
+fn test_path_long_listing() -> Result<()> {
+    let mut file = path_to_test_file!("test_path_long_listing.txt");
+    file.write(b"long listing")?;
+    file.set_mode(0o640)?;
+    let symlink = path_to_test_file!("test_path_long_listing_symlink");
+    file.create_symlink(&symlink)?;
+
+    let entry = file.ls_entry()?;
+    assert_eq!(entry.name, "test_path_long_listing.txt");
+    assert_eq!(entry.mode_string(), "-rw-r-----");
+    assert_eq!(entry.size, 12);
+    assert_eq!(entry.nlink, 1);
+    assert_eq!(entry.uid, file.uid()?);
+    assert_eq!(
+        file.long_listing()?,
+        format!(
+            "-rw-r----- 1 {} {} 12 {} test_path_long_listing.txt",
+            entry.owner,
+            entry.group,
+            file.modified().unwrap().human_friendly(None)
+        )
+    );
+    let symlink_entry = symlink.ls_entry()?;
+    assert_eq!(symlink_entry.path_type, LsPathType::Symlink);
+    assert_eq!(symlink_entry.target, Some(file.clone()));
+    assert!(symlink
+        .long_listing()?
+        .ends_with(&format!("test_path_long_listing_symlink -> {}", file.canonicalize()?)));
+
+    let json = serde_json::to_string(&entry).unwrap();
+    assert_eq!(serde_json::from_str::<LsEntry>(&json).unwrap(), entry);
+
+    symlink.delete()?;
+    file.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_path_owner_and_group() -> Result<()> {
+    let file = path_to_test_file!("test_path_owner_and_group.txt");
+    file.write(b"owner")?;
+    let owner = file.owner()?;
+    let group = file.group()?;
+    assert_eq!(owner.uid, file.uid()?);
+    assert_eq!(group.gid, file.gid()?);
+    assert_eq!(iocore::User::by_name(&owner.name)?.uid, owner.uid);
+    assert_eq!(iocore::Group::by_name(&group.name)?.gid, group.gid);
+    file.delete()?;
+    assert!(file.owner().is_err());
+    assert!(file.group().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_path_chown() -> Result<()> {
+    let file = path_to_test_file!("test_path_chown.txt");
+    file.write(b"chown")?;
+    let symlink = path_to_test_file!("test_path_chown_symlink");
+    file.create_symlink(&symlink)?;
+    let (uid, gid) = (file.uid()?, file.gid()?);
+    let (owner, group) = (file.owner()?.name, file.group()?.name);
+
+    file.chown(Some(uid), Some(gid))?;
+    file.chown(None, None)?;
+    symlink.lchown(Some(uid), None)?;
+    file.chown_by_name(Some(&owner), Some(&group))?;
+    file.chown_by_name(None, Some(&group))?;
+    assert_eq!((file.uid()?, file.gid()?), (uid, gid));
+    assert!(file.chown_by_name(Some("no-such-user-in-passwd"), None).is_err());
+    assert!(file.chown_by_name(None, Some("no-such-group-in-group")).is_err());
+
+    symlink.delete()?;
+    file.delete()?;
+    assert!(file.chown(Some(uid), Some(gid)).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_path_chown_recursive() -> Result<()> {
+    let folder = path_to_test_folder!("test_path_chown_recursive").mkdir_unchecked();
+    let nested = folder.join("nested").mkdir()?;
+    let file = nested.join("file.txt").write(b"")?;
+    let (uid, gid) = (folder.uid()?, folder.gid()?);
+    folder.chown_recursive(Some(uid), Some(gid))?;
+    folder.chown_by_name_recursive(Some(&folder.owner()?.name), None)?;
+    assert_eq!((file.uid()?, file.gid()?), (uid, gid));
+    folder.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_path_chown_recursive_does_not_follow_symlinks() -> Result<()> {
+    let folder = path_to_test_folder!();
+    std::fs::remove_dir_all(&folder).unwrap_or_default();
+    let outside = folder.join("outside").mkdir()?;
+    let secret = outside.join("secret").write(b"")?;
+    let tree = folder.join("tree").mkdir()?;
+    let data = tree.join("data.txt").write(b"")?;
+    let link = outside.create_symlink(tree.join("link"))?;
+    let ctime = |path: &Path| -> Result<(i64, i64)> {
+        let meta = path.symlink_meta()?;
+        Ok((meta.ctime(), meta.ctime_nsec()))
+    };
+    let (secret_ctime, data_ctime, link_ctime) = (ctime(&secret)?, ctime(&data)?, ctime(&link)?);
+    // chown(2) updates the ctime even when the owner is unchanged
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    tree.chown_recursive(Some(tree.uid()?), Some(tree.gid()?))?;
+    assert_ne!(ctime(&data)?, data_ctime);
+    assert_ne!(ctime(&link)?, link_ctime);
+    assert_eq!(ctime(&secret)?, secret_ctime);
+    std::fs::remove_dir_all(&folder)?;
+    Ok(())
+}
+
+#[test]
+fn test_path_expand_full() -> Result<()> {
+    let var = |name: &str| match name {
+        "XDG_CACHE_HOME" => Some("/home/user/.cache".to_string()),
+        _ => None,
+    };
+    assert_eq!(
+        Path::raw("$XDG_CACHE_HOME/app").expand_full_with(var)?.to_string(),
+        "/home/user/.cache/app"
+    );
+    assert_eq!(
+        Path::raw("${XDG_STATE_HOME:-/var/lib}/app").expand_full_with(var)?.to_string(),
+        "/var/lib/app"
+    );
+    assert!(Path::raw("$XDG_STATE_HOME/app").expand_full_with(var).is_err());
+    let root_home = iocore::User::by_name("root")?.home_dir.unwrap();
+    assert_eq!(
+        Path::raw("~root/cache").expand_full_with(var)?.to_string(),
+        format!("{}/cache", root_home)
+    );
+    std::env::set_var("IOCORE_TEST_PATH_EXPAND_FULL", "/opt");
+    assert_eq!(
+        Path::raw("${IOCORE_TEST_PATH_EXPAND_FULL}/bin").expand_full()?.to_string(),
+        "/opt/bin"
+    );
+    Ok(())
+}
+
 #[test]
 fn test_path_default() -> Result<()> {
     assert_eq!(Path::default(), Path::cwd());
@@ -1,5 +1,6 @@
 use iocore::{
-    Path, shell_command, shell_command_stdout, shell_command_string_output,
+    Cmd, CmdInput, CmdRedirect, Connector, Error, OutputStream, Path, Pipeline, Script,
+    SubprocessFailure, WindowSize, shell_command, shell_command_stdout, shell_command_string_output,
     shell_command_vec_output,
 };
 use sanitation::SString;
@@ -46,3 +47,604 @@ fn test_shell_command_stdout() {
     assert_eq!(Path::raw(stdout.trim()).exists(), true);
     assert_eq!(Path::raw(stdout.trim()).is_directory(), true);
 }
+
+#[test]
+fn test_shell_command_not_found() {
+    let error = shell_command_vec_output("iocore-command-which-does-not-exist --help", ".")
+        .expect_err("command should not be found");
+    match error {
+        Error::ShellCommandError(message) => {
+            assert!(message.contains("command not found: \"iocore-command-which-does-not-exist\""));
+            assert!(message.contains("searched: "));
+        },
+        error => panic!("unexpected error: {:#?}", error),
+    }
+}
+
+#[test]
+fn test_cmd_env_and_stdin() -> iocore::Result<()> {
+    let output = Cmd::new("sh")
+        .args(["-c", "echo \"$A:${B:-unset}:${HOME:-cleared}\"; cat"])
+        .env_clear()
+        .env("A", "a")
+        .env("PATH", "/bin:/usr/bin")
+        .stdin_bytes("from stdin")
+        .output()?;
+    assert_eq!(SString::new(&output.stdout).unchecked_safe(), "a:unset:cleared\nfrom stdin");
+
+    std::env::set_var("IOCORE_TEST_CMD_ENV_REMOVE", "present");
+    let output = Cmd::new("sh")
+        .args(["-c", "echo ${IOCORE_TEST_CMD_ENV_REMOVE:-removed}"])
+        .env_remove("IOCORE_TEST_CMD_ENV_REMOVE")
+        .output()?;
+    assert_eq!(SString::new(&output.stdout).unchecked_safe(), "removed\n");
+    Ok(())
+}
+
+#[test]
+fn test_cmd_redirects_and_umask() -> iocore::Result<()> {
+    let dir = Path::tmp().join("iocore-test-cmd-redirects").mkdir()?;
+    let input = dir.join("input").write(b"line\n")?;
+    let output = dir.join("output");
+    Cmd::new("cat").stdin_path(&input).stdout_path(&output, false).status()?;
+    Cmd::new("cat").stdin_path(&input).stdout_path(&output, true).status()?;
+    assert_eq!(output.read()?, "line\nline\n");
+    Cmd::new("cat")
+        .stdin(CmdInput::Bytes(b"new\n".to_vec()))
+        .stdout_path(&output, false)
+        .status()?;
+    assert_eq!(output.read()?, "new\n");
+
+    let stderr = dir.join("stderr");
+    let exit_code = Cmd::new("sh")
+        .args(["-c", "echo err >&2; touch created; exit 3"])
+        .current_dir(&dir)
+        .stderr(CmdRedirect::Path {
+            path: stderr.clone(),
+            append: false,
+        })
+        .umask(0o077)
+        .status()?;
+    assert_eq!(exit_code, 3);
+    assert_eq!(stderr.read()?, "err\n");
+    assert_eq!(dir.join("created").mode() & 0o777, 0o600);
+    dir.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_cmd_program_resolution() -> iocore::Result<()> {
+    let output = Cmd::new("sh").args(["-c", "echo $0"]).output()?;
+    assert_eq!(SString::new(&output.stdout).unchecked_safe(), "sh\n");
+
+    for cmd in [
+        Cmd::new("sh").env("PATH", ""),
+        Cmd::new("sh").env_remove("PATH"),
+        Cmd::new("sh").env_clear(),
+    ] {
+        let output = cmd.args(["-c", "echo default"]).output()?;
+        assert_eq!(SString::new(&output.stdout).unchecked_safe(), "default\n");
+    }
+
+    let dir = Path::tmp().join("iocore-test-cmd-program-resolution");
+    dir.delete_unchecked();
+    let mut script = dir.join("bin/iocore-test-relative").write(b"#!/bin/sh\necho relative\n")?;
+    script.set_mode(0o755)?;
+    let output =
+        Cmd::new("iocore-test-relative").current_dir(&dir).env("PATH", "bin:/usr/bin").output()?;
+    assert_eq!(SString::new(&output.stdout).unchecked_safe(), "relative\n");
+    let output = Cmd::new("iocore-test-relative")
+        .current_dir(dir.join("bin"))
+        .env("PATH", ":/usr/bin")
+        .output()?;
+    assert_eq!(SString::new(&output.stdout).unchecked_safe(), "relative\n");
+    assert!(matches!(
+        Cmd::new("iocore-test-relative").env("PATH", "bin").output(),
+        Err(Error::ShellCommandError(_))
+    ));
+    dir.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_cmd_parse_and_display() -> iocore::Result<()> {
+    let cmd = Cmd::parse("grep -r 'some text' .")?;
+    assert_eq!(cmd.get_program(), "grep");
+    assert_eq!(cmd.get_args(), ["-r", "some text", "."]);
+    assert_eq!(cmd.to_string(), "grep -r 'some text' .");
+    assert!(matches!(Cmd::parse(""), Err(Error::ShellCommandError(_))));
+    assert!(matches!(Cmd::parse("echo 'unterminated"), Err(Error::ShellCommandError(_))));
+
+    let output = Cmd::new("sh")
+        .args(["-c", "echo $$ $(ps -o pgid= -p $$ 2>/dev/null)"])
+        .process_group(0)
+        .output()?;
+    let ids = SString::new(&output.stdout).unchecked_safe();
+    let ids = ids.split_whitespace().collect::<Vec<_>>();
+    if ids.len() == 2 {
+        assert_eq!(ids[0], ids[1]);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_cmd_timeout_carries_partial_output() {
+    let started = std::time::Instant::now();
+    let error = Cmd::new("sh")
+        .args(["-c", "echo out; echo err >&2; sleep 10; echo never"])
+        .timeout(std::time::Duration::from_millis(300))
+        .output()
+        .expect_err("command should time out");
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    match error {
+        Error::SubprocessTimeout(timeout) => {
+            assert_eq!(timeout.stdout, b"out\n");
+            assert_eq!(timeout.stderr, b"err\n");
+            assert!(!timeout.killed);
+            assert!(timeout.command.starts_with("sh -c"));
+        },
+        error => panic!("unexpected error: {:#?}", error),
+    }
+}
+
+#[test]
+fn test_cmd_timeout_escalates_to_sigkill() {
+    let error = Cmd::new("sh")
+        .args(["-c", "trap '' TERM; echo ignoring; sleep 10"])
+        .timeout(std::time::Duration::from_millis(300))
+        .kill_after(std::time::Duration::from_millis(300))
+        .output()
+        .expect_err("command should time out");
+    match error {
+        Error::SubprocessTimeout(timeout) => {
+            assert!(timeout.killed);
+            assert_eq!(timeout.stdout, b"ignoring\n");
+        },
+        error => panic!("unexpected error: {:#?}", error),
+    }
+
+    let output = Cmd::new("echo")
+        .arg("fast")
+        .timeout(std::time::Duration::from_secs(10))
+        .output()
+        .unwrap();
+    assert_eq!(output.stdout, b"fast\n");
+}
+
+#[test]
+fn test_cmd_timeout_spares_the_rest_of_a_joined_process_group() {
+    use std::os::unix::process::CommandExt;
+    let mut leader = std::process::Command::new("sleep")
+        .arg("10")
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let error = Cmd::new("sleep")
+        .arg("10")
+        .process_group(leader.id() as i32)
+        .timeout(std::time::Duration::from_millis(200))
+        .output()
+        .expect_err("command should time out");
+    assert!(matches!(error, Error::SubprocessTimeout(_)));
+    assert!(leader.try_wait().unwrap().is_none());
+    leader.kill().unwrap();
+    leader.wait().unwrap();
+}
+
+#[test]
+fn test_pipeline_pipefail() -> iocore::Result<()> {
+    let output =
+        Pipeline::parse("sh -c 'echo out; exit 3' | cat | sh -c 'cat; exit 0'")?.output()?;
+    assert_eq!(output.stdout, b"out\n");
+    assert_eq!(output.codes(), vec![3, 0, 0]);
+    assert_eq!(output.code(), 3);
+    assert!(!output.success());
+
+    let output = Pipeline::parse("sh -c 'kill -9 $$' | false | true")?.output()?;
+    assert_eq!(output.codes(), vec![137, 1, 0]);
+    assert_eq!(output.code(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_stage_not_spawned() -> iocore::Result<()> {
+    let output = Pipeline::new()
+        .pipe(Cmd::new("true"))
+        .pipe(Cmd::new("iocore-test-no-such-command"))
+        .pipe(Cmd::new("cat"))
+        .output()?;
+    assert_eq!(output.codes(), vec![0, 127, 0]);
+    assert_eq!(output.code(), 127);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("iocore-test-no-such-command"));
+
+    let output = Pipeline::new()
+        .pipe(Cmd::new("sh").args(["-c", "echo out"]))
+        .pipe(Cmd::new("iocore-test-no-such-command"))
+        .output()?;
+    assert_eq!(output.codes()[1], 127);
+    assert_eq!(output.stdout, b"");
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_stage_timeout() -> iocore::Result<()> {
+    let started = std::time::Instant::now();
+    let output = Pipeline::new()
+        .pipe(Cmd::new("sh").args(["-c", "echo out"]))
+        .pipe(Cmd::new("sleep").arg("10").timeout(std::time::Duration::from_millis(200)))
+        .pipe(Cmd::new("cat"))
+        .output()?;
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    assert_eq!(output.codes(), vec![0, 128 + libc::SIGTERM, 0]);
+    assert_eq!(output.code(), 128 + libc::SIGTERM);
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_redirections() -> iocore::Result<()> {
+    let dir = Path::tmp().join("iocore-test-pipeline-redirections").mkdir()?;
+    dir.join("input").write(b"b\na\n")?;
+    let script = Script::parse(
+        "sort < input > sorted; echo c >> sorted; sh -c 'echo err >&2; echo out' 2>&1 | cat > both",
+    )?
+    .current_dir(&dir);
+    let output = script.output()?;
+    assert!(output.success());
+    assert_eq!(output.stdout(), b"");
+    assert_eq!(dir.join("sorted").read()?, "a\nb\nc\n");
+    assert_eq!(dir.join("both").read()?, "err\nout\n");
+
+    let output = Pipeline::parse("sh -c 'echo err >&2' 2>&1")?.output()?;
+    assert_eq!(output.stdout, b"err\n");
+    assert_eq!(output.stderr, b"");
+
+    let output = Script::parse("echo hi | cat < input")?.current_dir(&dir).output()?;
+    assert_eq!(output.stdout(), b"b\na\n");
+    dir.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_pipeline_redirections_apply_left_to_right() -> iocore::Result<()> {
+    let dir = Path::tmp().join("iocore-test-pipeline-redirections-order").mkdir()?;
+    let both = "sh -c 'echo err >&2; echo out'";
+    let output = Pipeline::parse(format!("{} > file 2>&1", both))?.current_dir(&dir).output()?;
+    assert_eq!(output.stdout, b"");
+    assert_eq!(dir.join("file").read()?, "err\nout\n");
+
+    let output = Pipeline::parse(format!("{} 2>&1 > file | cat", both))?
+        .current_dir(&dir)
+        .output()?;
+    assert_eq!(output.stdout, b"err\n");
+    assert_eq!(output.stderr, b"");
+    assert_eq!(dir.join("file").read()?, "out\n");
+
+    let output = Pipeline::parse(format!("{} > first 2>&1 > second", both))?
+        .current_dir(&dir)
+        .output()?;
+    assert_eq!(output.stdout, b"");
+    assert_eq!(dir.join("first").read()?, "err\n");
+    assert_eq!(dir.join("second").read()?, "out\n");
+    dir.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_script_connectors() -> iocore::Result<()> {
+    let script = Script::parse("false && echo and; false || echo or; true && echo 'a | b; c' ;")?;
+    assert_eq!(
+        script.items().iter().map(|(connector, _)| *connector).collect::<Vec<_>>(),
+        vec![
+            Connector::Always,
+            Connector::And,
+            Connector::Always,
+            Connector::Or,
+            Connector::Always,
+            Connector::And
+        ]
+    );
+    let output = script.output()?;
+    assert_eq!(SString::new(&output.stdout()).unchecked_safe(), "or\na | b; c\n");
+    assert_eq!(output.pipelines.len(), 5);
+    assert_eq!(
+        script.to_string(),
+        "false && echo and; false || echo or; true && echo 'a | b; c'"
+    );
+
+    let output = Script::parse("echo \"$HOME\" ~ *")?.output()?;
+    assert_eq!(output.stdout(), b"$HOME ~ *\n");
+    Ok(())
+}
+
+#[test]
+fn test_script_comments_and_newlines() -> iocore::Result<()> {
+    let script = Script::parse(
+        "# leading comment\necho one # trailing comment; echo skipped\n\necho '#two' a#b |\n  cat\n",
+    )?;
+    assert_eq!(script.items().len(), 2);
+    assert_eq!(script.to_string(), "echo one; echo '#two' 'a#b' | cat");
+    let output = script.output()?;
+    assert_eq!(output.stdout(), b"one\n#two a#b\n");
+    assert!(Script::parse("# only a comment").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_script_rejects_unsupported_syntax() {
+    for script in [
+        "sleep 1 &",
+        "echo $(id)",
+        "echo `id`",
+        "(cd /; ls)",
+        "cat <<EOF",
+        "echo 2> /dev/null",
+        "ls >&2",
+        "echo 'unterminated",
+        "| cat",
+        "echo a &&",
+        "echo a | | cat",
+        "cat <",
+        "",
+    ] {
+        assert!(Script::parse(script).is_err(), "{:#?} should be rejected", script);
+    }
+}
+
+#[test]
+fn test_cmd_stream_lines_in_order_of_arrival() {
+    let mut lines = Vec::<(OutputStream, String)>::new();
+    let output = Cmd::new("sh")
+        .args(["-c", "echo one; sleep 0.1; echo two >&2; sleep 0.1; printf three"])
+        .stream(|line| lines.push((line.stream, line.line.to_string())))
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        lines,
+        vec![
+            (OutputStream::Stdout, "one".to_string()),
+            (OutputStream::Stderr, "two".to_string()),
+            (OutputStream::Stdout, "three".to_string()),
+        ]
+    );
+    assert_eq!(output.stdout_lines(), vec!["one", "three"]);
+    assert_eq!(output.stderr_lines(), vec!["two"]);
+    assert!(output.log.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    let merged = output.merged_log();
+    assert_eq!(merged.lines().count(), 3);
+    assert!(merged.lines().nth(1).unwrap().ends_with(" stderr two"));
+}
+
+#[test]
+fn test_cmd_spawn_streaming_iterates_lines_before_exit() {
+    let mut child = Cmd::new("sh")
+        .args(["-c", "echo ready; sleep 0.3; echo done"])
+        .spawn_streaming()
+        .unwrap();
+    let first = child.next().unwrap();
+    assert_eq!(first.line, "ready");
+    assert!(child.child().try_wait().unwrap().is_none());
+    let output = child.wait().unwrap();
+    assert_eq!(output.stdout_lines(), vec!["ready", "done"]);
+}
+
+#[test]
+fn test_cmd_stream_timeout() {
+    let started = std::time::Instant::now();
+    let mut lines = Vec::<String>::new();
+    let error = Cmd::new("sh")
+        .args(["-c", "echo started; echo warning >&2; sleep 10; echo finished"])
+        .timeout(std::time::Duration::from_millis(300))
+        .stream(|line| lines.push(line.line.to_string()))
+        .expect_err("command should time out");
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    assert_eq!(lines.len(), 2);
+    match error {
+        Error::SubprocessTimeout(timeout) => {
+            assert!(!timeout.killed);
+            assert_eq!(timeout.stdout, b"started\n");
+            assert_eq!(timeout.stderr, b"warning\n");
+        },
+        error => panic!("unexpected error: {:#?}", error),
+    }
+
+    let mut child = Cmd::new("sleep")
+        .arg("10")
+        .timeout(std::time::Duration::from_millis(200))
+        .spawn_streaming()
+        .unwrap();
+    assert_eq!(child.next(), None);
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    assert!(matches!(child.wait(), Err(Error::SubprocessTimeout(_))));
+}
+
+#[test]
+fn test_cmd_stream_sanitizes_invalid_utf8() {
+    let output = Cmd::new("printf").arg("ok\\n\\377bad\\n").stream(|_| {}).unwrap();
+    assert_eq!(output.log.len(), 2);
+    assert!(output.log[0].safe);
+    assert_eq!(output.log[0].line, "ok");
+    assert!(!output.log[1].safe);
+    assert_eq!(output.log[1].line, SString::new(b"\xffbad").unchecked_safe());
+}
+
+#[test]
+fn test_cmd_run_reports_signal_deaths() {
+    let output = Cmd::new("sh").args(["-c", "kill -SEGV $$"]).run().unwrap();
+    assert_eq!(output.code, None);
+    assert_eq!(output.signal, Some(libc::SIGSEGV));
+    assert_eq!(output.exit_code(), 128 + libc::SIGSEGV);
+    assert!(!output.success());
+    assert_eq!(Cmd::new("sh").args(["-c", "kill -SEGV $$"]).status().unwrap(), 139);
+    let error = shell_command_stdout("sh -c 'kill -SEGV $$'", Path::cwd()).unwrap_err();
+    match error {
+        Error::SubprocessError(SubprocessFailure {
+            output: Some(output), ..
+        }) => {
+            assert_eq!(output.signal, Some(libc::SIGSEGV));
+            assert_eq!(output.command, "sh -c 'kill -SEGV $$'");
+        },
+        error => panic!("expected SubprocessError with output, got {}", error),
+    }
+}
+
+#[test]
+fn test_cmd_check_carries_command_output() {
+    let cwd = Path::tmp();
+    let output = Cmd::new("sh")
+        .args(["-c", "sleep 0.1; echo out; echo err >&2; exit 3"])
+        .current_dir(&cwd)
+        .run()
+        .unwrap();
+    assert_eq!(output.code, Some(3));
+    assert_eq!(output.signal, None);
+    assert!(!output.core_dumped);
+    assert!(output.duration >= std::time::Duration::from_millis(100));
+    assert_eq!(output.current_dir, Some(cwd.clone()));
+    assert_eq!(output.stdout, b"out\n");
+    assert_eq!(output.stderr, b"err\n");
+    let error = output.clone().check().unwrap_err();
+    assert_eq!(error, Error::SubprocessError(SubprocessFailure::from(output)));
+    assert!(matches!(
+        Cmd::new("iocore-command-which-does-not-exist").check(),
+        Err(Error::ShellCommandError(_))
+    ));
+    match Cmd::new("true").current_dir("/iocore/non-existing/dir").check() {
+        Err(Error::SubprocessError(failure)) => assert_eq!(failure.output, None),
+        result => panic!("expected SubprocessError without output, got {:#?}", result),
+    }
+    assert!(error.to_string().contains("exited with code 3"));
+    assert!(Cmd::new("true").check().unwrap().success());
+}
+
+#[test]
+fn test_cmd_spawn_pty_provides_a_terminal() {
+    let timeout = std::time::Duration::from_secs(5);
+    let mut child = Cmd::new("sh")
+        .args([
+            "-c",
+            "[ -t 0 ] && [ -t 1 ] && echo tty; stty size; read line; echo \"got $line\"",
+        ])
+        .spawn_pty(WindowSize::new(30, 100))
+        .unwrap();
+    child.expect("tty\r\n", timeout).unwrap();
+    let size = child.expect(r"(\d+) (\d+)\r\n", timeout).unwrap();
+    assert_eq!(size.groups, vec![Some("30".to_string()), Some("100".to_string())]);
+    assert_eq!(child.window_size().unwrap(), WindowSize::new(30, 100));
+    child.send_line("input").unwrap();
+    let found = child.expect(r"got (\w+)", timeout).unwrap();
+    assert_eq!(found.before, "input\r\n");
+    assert_eq!(found.groups, vec![Some("input".to_string())]);
+    let output = child.wait().unwrap();
+    assert!(output.success());
+    assert_eq!(output.stdout, b"\r\n");
+}
+
+#[test]
+fn test_pty_set_window_size() {
+    let timeout = std::time::Duration::from_secs(5);
+    let mut child = Cmd::new("sh").spawn_pty(WindowSize::default()).unwrap();
+    child.set_window_size(WindowSize::new(50, 132)).unwrap();
+    assert_eq!(child.window_size().unwrap(), WindowSize::new(50, 132));
+    child.send_line("stty size; exit 7").unwrap();
+    child.expect(r"\b50 132\r\n", timeout).unwrap();
+    assert_eq!(child.wait().unwrap().code, Some(7));
+}
+
+#[test]
+fn test_pty_expect_times_out_and_reports_eof() {
+    let mut child = Cmd::new("cat").spawn_pty(WindowSize::default()).unwrap();
+    let started = std::time::Instant::now();
+    let error = child.expect("never", std::time::Duration::from_millis(200)).unwrap_err();
+    assert!(started.elapsed() >= std::time::Duration::from_millis(200));
+    assert!(error.to_string().contains("timed out"), "{}", error);
+    child.send_eof().unwrap();
+    let error = child.expect("never", std::time::Duration::from_secs(5)).unwrap_err();
+    assert!(error.to_string().contains("end of output"), "{}", error);
+    assert!(child.wait().unwrap().success());
+}
+
+/// `Words` generates pseudo-random words, deterministically, from
+/// characters which are significant to shell quoting
+struct Words(u64);
+impl Words {
+    const ALPHABET: &'static [char] = &[
+        'a', 'Z', '0', ' ', '\t', '\n', '\'', '"', '\\', '$', '`', '*', '?', '~', '#', '!', '&',
+        ';', '|', '<', '>', '(', ')', '{', '}', '[', ']', '=', '%', '@', ',', '.', '/', '-', '_',
+        'é', '日',
+    ];
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn word(&mut self) -> String {
+        let len = (self.next_u64() % 8) as usize;
+        (0..len)
+            .map(|_| Words::ALPHABET[(self.next_u64() % Words::ALPHABET.len() as u64) as usize])
+            .collect()
+    }
+
+    fn words(&mut self) -> Vec<String> {
+        let len = 1 + (self.next_u64() % 5) as usize;
+        (0..len).map(|_| self.word()).collect()
+    }
+}
+
+#[test]
+fn test_sh_join_split_round_trip() {
+    let mut words = Words(0x5EED_1DEA_CAFE_F00D);
+    for _ in 0..5000 {
+        let args = words.words();
+        let joined = iocore::sh::join(&args);
+        assert_eq!(iocore::sh::split_strict(&joined).unwrap(), args, "{}", joined);
+        assert_eq!(iocore::sh::split(&joined), args, "{}", joined);
+        for arg in &args {
+            assert_eq!(
+                iocore::sh::split_strict(iocore::sh::quote(arg)).unwrap(),
+                vec![arg.to_string()]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_sh_join_round_trips_through_a_real_shell() {
+    let mut words = Words(0xDEAD_BEEF_0BAD_CAFE);
+    for _ in 0..20 {
+        let args = words.words();
+        let script =
+            format!("for arg in {}; do printf '%s\\0' \"$arg\"; done", iocore::sh::join(&args));
+        let output = Cmd::new("sh").args(["-c", &script]).output().unwrap();
+        let printed = output
+            .stdout
+            .split(|byte| *byte == 0)
+            .map(|arg| String::from_utf8(arg.to_vec()).unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(printed[..printed.len() - 1], args[..], "{}", script);
+    }
+}
+
+#[test]
+fn test_sh_split_strict_rejects_malformed_command_lines() {
+    for command in ["echo 'unbalanced", "echo \"unbalanced", "echo trailing\\"] {
+        match iocore::sh::split_strict(command) {
+            Err(Error::ShellCommandError(_)) => {},
+            result => panic!("{:#?} should be rejected, got {:#?}", command, result),
+        }
+    }
+    assert_eq!(iocore::sh::split("echo 'unbalanced quote"), vec!["echo 'unbalanced quote"]);
+    assert!(matches!(
+        shell_command_stdout("echo 'unbalanced", Path::cwd()),
+        Err(Error::ShellCommandError(_))
+    ));
+    assert!(matches!(
+        shell_command_stdout("", Path::cwd()),
+        Err(Error::ShellCommandError(_))
+    ));
+    let cmd = Cmd::new("printf").args(["%s\\n", "it's", "", "a b"]);
+    assert_eq!(cmd.to_string(), "printf '%s\\n' 'it'\\''s' '' 'a b'");
+    assert_eq!(shell_command_stdout(cmd, Path::cwd()).unwrap(), "it's\n\na b\n");
+}
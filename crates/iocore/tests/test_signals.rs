@@ -0,0 +1,86 @@
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+use iocore::{
+    check_shutdown, clear_shutdown_request, request_shutdown, shutdown_requested, walk_dir, Cmd,
+    Error, NoopProgressHandler, Path, Signals,
+};
+
+fn disposition(signal: i32) -> libc::sighandler_t {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        libc::sigaction(signal, std::ptr::null(), &mut action);
+        action.sa_sigaction
+    }
+}
+
+#[test]
+fn test_signals_receives_signals() {
+    let mut signals = Signals::new([libc::SIGUSR1, libc::SIGUSR1]).unwrap();
+    assert_eq!(signals.signals(), &[libc::SIGUSR1]);
+    assert_eq!(signals.try_next(), None);
+    unsafe { libc::kill(libc::getpid(), libc::SIGUSR1) };
+    assert_eq!(signals.next_timeout(Duration::from_secs(5)), Some(libc::SIGUSR1));
+    assert_eq!(signals.next_timeout(Duration::from_millis(50)), None);
+    assert!(Signals::new([libc::SIGKILL]).is_err());
+}
+
+#[test]
+fn test_signals_shutdown_flag_stops_long_operations() {
+    // listening to a shutdown signal, e.g.: to reload configuration,
+    // does not request a shutdown
+    let mut reload = Signals::new([libc::SIGHUP]).unwrap();
+    unsafe { libc::kill(libc::getpid(), libc::SIGHUP) };
+    assert_eq!(reload.next_timeout(Duration::from_secs(5)), Some(libc::SIGHUP));
+    assert!(!shutdown_requested());
+
+    assert_eq!(disposition(libc::SIGTERM), libc::SIG_DFL);
+    let mut signals = Signals::shutdown().unwrap();
+    assert_ne!(disposition(libc::SIGTERM), libc::SIG_DFL);
+    unsafe { libc::kill(libc::getpid(), libc::SIGTERM) };
+    assert_eq!(signals.next_timeout(Duration::from_secs(5)), Some(libc::SIGTERM));
+    assert!(shutdown_requested());
+    assert!(matches!(check_shutdown(), Err(Error::ShutdownRequested(_))));
+    let error = walk_dir(Path::cwd(), NoopProgressHandler, None).unwrap_err();
+    assert!(error.to_string().contains("shutdown requested"), "{}", error);
+    clear_shutdown_request();
+    assert!(check_shutdown().is_ok());
+    drop(signals);
+    assert_eq!(disposition(libc::SIGTERM), libc::SIG_DFL);
+    unsafe { libc::kill(libc::getpid(), libc::SIGHUP) };
+    assert_eq!(reload.next_timeout(Duration::from_secs(5)), Some(libc::SIGHUP));
+    assert!(!shutdown_requested());
+    drop(reload);
+
+    let cmd = Cmd::new("sleep").arg("10").timeout(Duration::from_secs(10));
+    let waiting = std::thread::spawn(move || cmd.output());
+    std::thread::sleep(Duration::from_millis(200));
+    request_shutdown();
+    let started = std::time::Instant::now();
+    let error = waiting.join().unwrap().unwrap_err();
+    clear_shutdown_request();
+    assert!(matches!(error, Error::ShutdownRequested(_)), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_signals_forward_to_process_group() {
+    let signals = Signals::new([libc::SIGUSR2]).unwrap();
+    let mut child = Cmd::new("sh")
+        .args(["-c", "trap 'echo forwarded; exit 3' USR2; echo ready; sleep 10 & wait"])
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    assert!(signals.forward(0).is_err());
+    assert!(signals.forward(-(child.id() as i32)).is_err());
+    signals.forward(child.id() as i32).unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert_eq!(line, "ready\n");
+    unsafe { libc::kill(libc::getpid(), libc::SIGUSR2) };
+    line.clear();
+    stdout.read_line(&mut line).unwrap();
+    assert_eq!(line, "forwarded\n");
+    assert_eq!(child.wait().unwrap().code(), Some(3));
+}
@@ -120,3 +120,567 @@ fn test_unix_user_info_home() -> Result<()> {
     assert_eq!(unix_user_info_home(passwd.to_string().as_str(), "root", 0)?, "/var/root");
     Ok(())
 }
+
+#[test]
+fn test_user_by_uid_and_name() -> Result<()> {
+    let root = User::by_uid(0)?;
+    assert_eq!(root.uid, 0);
+    assert_eq!(root.name, "root");
+    assert_eq!(root.gid, Some(0));
+    assert!(root.shell.is_some());
+    assert!(root.home_dir.is_some());
+    assert!(root.groups.iter().any(|group| group.gid == 0));
+    assert_eq!(User::by_name("root")?, root);
+    assert!(User::by_name("no-such-user-in-passwd").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_user_id() -> Result<()> {
+    let user = User::id()?;
+    assert_eq!(User::by_uid(user.uid)?.name, user.name);
+    assert_eq!(user.groups.first().map(|group| group.gid), user.gid);
+    Ok(())
+}
+
+#[test]
+fn test_group_by_gid_and_name() -> Result<()> {
+    let group = Group::by_gid(0)?;
+    assert_eq!(group.gid, 0);
+    assert_eq!(Group::by_name(&group.name)?, group);
+    assert!(group.members().is_ok());
+    assert!(Group::by_name("no-such-group-in-group").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_passwd_file_round_trip() -> Result<()> {
+    let contents = [
+        "# managed by provisioning",
+        "root:x:0:0:root:/root:/bin/bash",
+        "",
+        "_bsd:*:7:7::0:0:BSD user:/var/empty:/usr/bin/false",
+        "+@netgroup",
+        "broken:x:1",
+        "",
+    ]
+    .join("\n");
+    let passwd = PasswdFile::parse(&contents);
+    assert_eq!(passwd.to_string(), contents);
+    assert_eq!(passwd.entries().len(), 2);
+    assert_eq!(
+        passwd.get("root"),
+        Some(&PasswdEntry {
+            name: "root".to_string(),
+            password: "x".to_string(),
+            uid: 0,
+            gid: 0,
+            class: None,
+            change: None,
+            expire: None,
+            gecos: "root".to_string(),
+            home: "/root".to_string(),
+            shell: "/bin/bash".to_string(),
+        })
+    );
+    let bsd = passwd.get("_bsd").unwrap();
+    assert_eq!(bsd.class, Some(String::new()));
+    assert_eq!(bsd.gecos, "BSD user");
+    assert_eq!(bsd.home, "/var/empty");
+    assert_eq!(bsd.to_line(), "_bsd:*:7:7::0:0:BSD user:/var/empty:/usr/bin/false");
+    assert_eq!(passwd.lines()[5], DatabaseLine::Other("broken:x:1".to_string()));
+    Ok(())
+}
+
+#[test]
+fn test_passwd_file_edit_in_root() -> Result<()> {
+    let root = path_to_test_file!("test_passwd_file_edit_in_root");
+    root.join("etc").mkdir_parents()?;
+    root.join("etc/passwd").write(
+        b"root:x:0:0:root:/root:/bin/sh\n# users\ndeploy:x:1000:1000::/home/deploy:/bin/sh\n",
+    )?;
+    root.join("etc/group").write(b"root:x:0:\nwheel:x:10:root\n")?;
+
+    let mut passwd = PasswdFile::read_in(&root)?;
+    passwd.get_mut("deploy").unwrap().shell = "/bin/bash".to_string();
+    passwd.upsert(PasswdEntry {
+        name: "backup".to_string(),
+        password: "x".to_string(),
+        uid: 1001,
+        gid: 1001,
+        home: "/var/backups".to_string(),
+        shell: "/usr/sbin/nologin".to_string(),
+        ..Default::default()
+    });
+    assert_eq!(passwd.remove("root").map(|entry| entry.uid), Some(0));
+    assert_eq!(passwd.remove("root"), None);
+    passwd.write_in(&root)?;
+    assert_eq!(
+        root.join("etc/passwd").read()?,
+        "# users\ndeploy:x:1000:1000::/home/deploy:/bin/bash\nbackup:x:1001:1001::/var/backups:/usr/sbin/nologin\n"
+    );
+
+    let mut group = GroupFile::read_in(&root)?;
+    group.get_mut("wheel").unwrap().members.push("deploy".to_string());
+    assert_eq!(group.get("root").unwrap().members, Vec::<String>::new());
+    assert_eq!(group.to_string(), "root:x:0:\nwheel:x:10:root,deploy\n");
+    root.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_database_file_write_replaces_atomically() -> Result<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    let root = path_to_test_file!("test_database_file_write_replaces_atomically");
+    root.delete_unchecked();
+    let mut shadow = ShadowFile::new();
+    shadow.upsert(ShadowEntry::parse("deploy:!:19000:0:99999:7:::")?);
+    let path = shadow.write_in(&root)?;
+    assert_eq!(path.read()?, "deploy:!:19000:0:99999:7:::\n");
+    assert_eq!(path.meta()?.permissions().mode() & 0o7777, 0o640);
+
+    let mut passwd = root.join("etc/passwd").write(b"root:x:0:0:root:/root:/bin/sh\n")?;
+    passwd.set_mode(0o600)?;
+    let inode = passwd.meta()?.ino();
+    PasswdFile::read(&passwd)?.write(&passwd)?;
+    assert_eq!(passwd.read()?, "root:x:0:0:root:/root:/bin/sh\n");
+    assert_eq!(passwd.meta()?.permissions().mode() & 0o7777, 0o600);
+    assert_ne!(passwd.meta()?.ino(), inode);
+    assert_eq!(
+        root.join("etc")
+            .list()?
+            .iter()
+            .map(|path| path.name())
+            .collect::<Vec<String>>(),
+        vec!["passwd", "shadow"]
+    );
+    root.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_database_file_write_from_concurrent_threads() -> Result<()> {
+    let root = path_to_test_file!("test_database_file_write_from_concurrent_threads");
+    root.delete_unchecked();
+    let passwd = PasswdFile::parse("root:x:0:0:root:/root:/bin/sh\n");
+    let path = root.join("etc/passwd");
+    std::thread::scope(|scope| {
+        let handles = (0..16)
+            .map(|_| scope.spawn(|| (0..8).map(|_| passwd.write(&path)).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        for handle in handles {
+            for result in handle.join().unwrap() {
+                assert_eq!(result, Ok(path.clone()));
+            }
+        }
+    });
+    assert_eq!(path.read()?, "root:x:0:0:root:/root:/bin/sh\n");
+    assert_eq!(
+        root.join("etc").list()?.iter().map(|path| path.name()).collect::<Vec<String>>(),
+        vec!["passwd"]
+    );
+    root.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_shadow_entry() -> Result<()> {
+    let line = "deploy:$6$salt$hash:19000:0:99999:7:::";
+    let entry = ShadowEntry::parse(line)?;
+    assert_eq!(entry.last_change, Some(19000));
+    assert_eq!(entry.max_age, Some(99999));
+    assert_eq!(entry.inactivity_period, None);
+    assert_eq!(entry.to_line(), line);
+    assert!(ShadowEntry::parse("deploy:x:soon:0:99999:7:::").is_err());
+    assert!(GroupEntry::parse("wheel:x:ten:").is_err());
+    assert!(PasswdEntry::parse("root:x:0:0").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_xdg_dirs_defaults() {
+    let xdg = XdgDirs::from_vars("/home/user", |_| None);
+    assert_eq!(xdg.config_home.to_string(), "/home/user/.config");
+    assert_eq!(xdg.data_home.to_string(), "/home/user/.local/share");
+    assert_eq!(xdg.cache_home.to_string(), "/home/user/.cache");
+    assert_eq!(xdg.state_home.to_string(), "/home/user/.local/state");
+    assert_eq!(xdg.runtime_dir, None);
+    assert_eq!(xdg.config_dirs, vec![Path::raw("/etc/xdg")]);
+    assert_eq!(
+        xdg.data_dirs.iter().map(|path| path.to_string()).collect::<Vec<String>>(),
+        vec!["/usr/local/share", "/usr/share"]
+    );
+}
+
+#[test]
+fn test_xdg_dirs_from_vars_ignores_empty_and_relative_paths() {
+    let xdg = XdgDirs::from_vars("/home/user", |key| match key {
+        "XDG_CONFIG_HOME" => Some("relative/config".to_string()),
+        "XDG_CACHE_HOME" => Some("/var/cache/user".to_string()),
+        "XDG_RUNTIME_DIR" => Some("/run/user/1000".to_string()),
+        "XDG_CONFIG_DIRS" => Some("/opt/xdg:relative:/etc/xdg".to_string()),
+        "XDG_DATA_HOME" => Some(String::new()),
+        "XDG_DATA_DIRS" => Some(String::new()),
+        _ => None,
+    });
+    assert_eq!(xdg.config_home.to_string(), "/home/user/.config");
+    assert_eq!(xdg.data_home.to_string(), "/home/user/.local/share");
+    assert_eq!(
+        xdg.data_dirs.iter().map(|path| path.to_string()).collect::<Vec<String>>(),
+        vec!["/usr/local/share", "/usr/share"]
+    );
+    assert_eq!(xdg.cache_home.to_string(), "/var/cache/user");
+    assert_eq!(
+        xdg.runtime_dir.map(|path| path.to_string()),
+        Some("/run/user/1000".to_string())
+    );
+    assert_eq!(
+        xdg.config_dirs.iter().map(|path| path.to_string()).collect::<Vec<String>>(),
+        vec!["/opt/xdg", "/etc/xdg"]
+    );
+}
+
+#[test]
+fn test_app_dirs() -> Result<()> {
+    let root = path_to_test_file!("test_app_dirs").canonicalize()?;
+    let system = root.join("etc/xdg/tool").mkdir_parents()?;
+    system.join("tool.toml").write(b"system")?;
+    let root_string = root.to_string();
+    let xdg = XdgDirs::from_vars(root.join("home"), |key| match key {
+        "XDG_CONFIG_DIRS" => Some(format!("{}/etc/xdg", root_string)),
+        "XDG_RUNTIME_DIR" => Some(format!("{}/run", root_string)),
+        _ => None,
+    });
+    let dirs = AppDirs::with_xdg("tool", xdg);
+    assert_eq!(dirs.config_dir(), root.join("home/.config/tool"));
+    assert!(!dirs.config_dir().exists());
+    assert_eq!(dirs.find_config_file("tool.toml"), Some(system.join("tool.toml")));
+
+    let config_dir = dirs.ensure_config_dir()?;
+    assert!(config_dir.is_dir());
+    assert_eq!(config_dir.permissions().to_string_octal(), "700");
+    assert_eq!(root.join("home/.config").permissions().to_string_octal(), "700");
+    config_dir.join("tool.toml").write(b"user")?;
+    assert_eq!(dirs.find_config_file("tool.toml"), Some(config_dir.join("tool.toml")));
+    assert_eq!(dirs.find_config_files("tool.toml").len(), 2);
+    assert_eq!(dirs.find_config_file("missing.toml"), None);
+
+    assert_eq!(dirs.ensure_state_dir()?, root.join("home/.local/state/tool"));
+    assert_eq!(dirs.ensure_runtime_dir()?, root.join("run/tool"));
+    assert_eq!(dirs.find_data_file("data.db"), None);
+    root.delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_process_status_parse() -> Result<()> {
+    let status = ProcessStatus::parse(
+        "Name:\tbash\nState:\tS (sleeping)\nPid:\t42\nPPid:\t1\nUid:\t1000\t1000\t1000\t1000\nGid:\t100\t100\t100\t100\nThreads:\t1\n",
+    )?;
+    assert_eq!(status.name, "bash");
+    assert_eq!(status.state, ProcessState::Sleeping);
+    assert_eq!(status.pid, 42);
+    assert_eq!(status.ppid, 1);
+    assert_eq!(status.uids, [1000, 1000, 1000, 1000]);
+    assert_eq!(status.gids, [100, 100, 100, 100]);
+    assert_eq!(status.fields.get("State").map(String::as_str), Some("S (sleeping)"));
+    assert!(ProcessStatus::parse("Name:\tbash\n").is_err());
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_process_current() -> Result<()> {
+    let process = Process::current()?;
+    assert_eq!(process.pid, std::process::id());
+    assert_eq!(process.cwd()?, Path::from(std::env::current_dir().unwrap()));
+    assert_eq!(process.cmdline()?[0], std::env::args().next().unwrap());
+    assert_eq!(process.environ()?.get("PATH"), std::env::var("PATH").ok().as_ref());
+    assert_eq!(process.uid()?, unsafe { libc::getuid() });
+    assert!(process.start_time()? <= PathDateTime::from(std::time::SystemTime::now()));
+    assert!(process.fds()?.iter().any(|fd| fd.fd == 0));
+
+    let parent = process.parent()?.unwrap();
+    assert!(parent.children()?.contains(&process));
+    assert!(Process::new(u32::MAX).is_err());
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_process_cmdline_keeps_empty_arguments() -> Result<()> {
+    let mut child = std::process::Command::new("sh")
+        .args(["-c", "sleep 5; true", "", "x"])
+        .env("IOCORE_EMPTY", "")
+        .spawn()?;
+    let process = Process::new(child.id())?;
+    // the arguments are those of the parent until `exec`
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while process.cmdline()?.last().map(String::as_str) != Some("x")
+        && std::time::Instant::now() < deadline
+    {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let cmdline = process.cmdline();
+    let environ = process.environ();
+    child.kill()?;
+    child.wait()?;
+    assert_eq!(cmdline?, vec!["sh", "-c", "sleep 5; true", "", "x"]);
+    assert_eq!(environ?.get("IOCORE_EMPTY"), Some(&String::new()));
+    Ok(())
+}
+
+#[test]
+fn test_which_in() -> Result<()> {
+    let bin = Path::tmp().join("iocore-test-which-in/bin").mkdir()?;
+    let sbin = Path::tmp().join("iocore-test-which-in/sbin").mkdir()?;
+    let mut tool = bin.join("tool").write(b"#!/bin/sh\n")?;
+    tool.set_mode(0o755)?;
+    let mut not_executable = sbin.join("tool").write(b"#!/bin/sh\n")?;
+    not_executable.set_mode(0o644)?;
+    let mut other_tool = sbin.join("other-tool").write(b"#!/bin/sh\n")?;
+    other_tool.set_mode(0o700)?;
+    sbin.join("directory").mkdir()?;
+
+    let dirs = vec![sbin.clone(), bin.clone(), bin.clone()];
+    assert_eq!(which_in("tool", &dirs)?, tool);
+    assert_eq!(which_all_in("tool", &dirs), vec![tool.clone()]);
+    assert_eq!(which_in("other-tool", &dirs)?, other_tool);
+    assert_eq!(which_in(tool.to_string(), &[])?, tool);
+    assert!(which_in("directory", &dirs).is_err());
+    assert!(!is_executable(&not_executable));
+
+    let error = which_in("missing-tool", &dirs).unwrap_err().to_string();
+    assert!(error.contains(&format!("searched: {}:{}:{}", sbin, bin, bin)));
+    assert_eq!(
+        split_search_path("/bin::/usr/bin"),
+        vec![Path::raw("/bin"), Path::raw("."), Path::raw("/usr/bin")]
+    );
+    assert_eq!(which_all("sh").first(), Some(&which("sh")?));
+    Path::tmp().join("iocore-test-which-in").delete()?;
+    Ok(())
+}
+
+#[test]
+fn test_user_resolve_home_policy() -> Result<()> {
+    let user = |home: &str| User {
+        uid: 54321,
+        gid: None,
+        name: "iocore-test-resolve-home".to_string(),
+        group: None,
+        groups: Vec::new(),
+        gecos: None,
+        home_dir: Some(home.to_string()),
+        shell: None,
+    };
+    let error = user("/tmp").resolve_home(HomePolicy::Strict).unwrap_err().to_string();
+    assert!(error.starts_with("HomePathError"));
+    assert!(error.contains("user database: \"/tmp\" is owned by uid"));
+    assert_eq!(
+        user("/nonexistent").resolve_home(HomePolicy::PasswdOnly)?,
+        Path::raw("/nonexistent")
+    );
+    if env_var("HOME").is_err() {
+        assert_eq!(user("/tmp").resolve_home(HomePolicy::TrustEnv)?, Path::raw("/tmp"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_home_override() -> Result<()> {
+    // the override is process-wide hence set in a subprocess such
+    // that tests resolving `~` concurrently are not affected
+    if std::env::var("IOCORE_TEST_HOME_OVERRIDE").is_err() {
+        let status = std::process::Command::new(std::env::current_exe()?)
+            .args(["--exact", "test_home_override", "--test-threads=1"])
+            .env("IOCORE_TEST_HOME_OVERRIDE", "1")
+            .status()?;
+        assert!(status.success());
+        return Ok(());
+    }
+    set_home_override(Some(Path::raw("/tmp/iocore-test-home-override")));
+    assert_eq!(home_dir()?, Path::raw("/tmp/iocore-test-home-override"));
+    assert_eq!(tilde()?, "/tmp/iocore-test-home-override/");
+    assert_eq!(Path::raw("~/file").expand(), Path::raw("/tmp/iocore-test-home-override/file"));
+    assert_eq!(Path::raw("/tmp/iocore-test-home-override/file").tildify(), Path::raw("~/file"));
+    set_home_override(None);
+    assert_eq!(home_override(), None);
+    assert_eq!(home_policy(), HomePolicy::Strict);
+    Ok(())
+}
+
+#[test]
+fn test_os_release_parse() {
+    let os_release = OsRelease::parse(
+        "# comment\nNAME=\"Debian GNU/Linux\"\nID=debian\nVERSION_ID='12'\nPRETTY_NAME=\"Debian \\\"bookworm\\\"\"\n",
+    );
+    assert_eq!(os_release.id, "debian");
+    assert_eq!(os_release.name, "Debian GNU/Linux");
+    assert_eq!(os_release.version_id.as_deref(), Some("12"));
+    assert_eq!(os_release.pretty_name.as_deref(), Some("Debian \"bookworm\""));
+    assert_eq!(os_release.version, None);
+    assert_eq!(OsRelease::parse("").id, "linux");
+}
+
+#[test]
+fn test_memory_info_and_load_average_parse() -> Result<()> {
+    let memory = MemoryInfo::parse(
+        "MemTotal:        4096 kB\nMemFree:         1024 kB\nMemAvailable:    2048 kB\nSwapTotal:       0 kB\nHugePages_Total:       0\n",
+    )?;
+    assert_eq!(memory.total, Size::from(4096 * 1024));
+    assert_eq!(memory.available, Size::from(2048 * 1024));
+    assert_eq!(memory.used(), Size::from(2048 * 1024));
+    assert_eq!(memory.cached, Size::default());
+    assert!(MemoryInfo::parse("MemFree: 1 kB\n").is_err());
+
+    let load = LoadAverage::parse("0.76 0.72 0.61 2/73 10084\n")?;
+    assert_eq!(
+        load,
+        LoadAverage {
+            one: 0.76,
+            five: 0.72,
+            fifteen: 0.61
+        }
+    );
+    assert!(LoadAverage::parse("0.76").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cpu_info_topology() {
+    let cpu = |id, core_id, online| Cpu {
+        id,
+        core_id: Some(core_id),
+        package_id: Some(0),
+        online,
+    };
+    let info = CpuInfo {
+        model_name: None,
+        cpus: vec![cpu(0, 0, true), cpu(1, 0, true), cpu(2, 1, true), cpu(3, 1, false)],
+    };
+    assert_eq!(info.logical(), 3);
+    assert_eq!(info.physical_cores(), 2);
+    assert_eq!(info.packages(), 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_system_info_gather() -> Result<()> {
+    let info = SystemInfo::gather()?;
+    assert_eq!(info.hostname, hostname()?);
+    assert_eq!(info.uname.sysname, "Linux");
+    assert!(info.uptime > 0.0);
+    assert!(info.memory.total >= info.memory.available);
+    assert!(info.cpu.logical() >= info.cpu.physical_cores());
+    let json = serde_json::to_string(&info).unwrap();
+    assert!(json.contains("\"hostname\""));
+    Ok(())
+}
+
+#[test]
+fn test_pid_file_replaces_stale_pid() {
+    let path = Path::tmp_file().with_extension(".pid");
+    let mut exited = std::process::Command::new("true").spawn().unwrap();
+    let stale = exited.id();
+    exited.wait().unwrap();
+    path.write(format!("{}\n", stale).as_bytes()).unwrap();
+    let pid_file = PidFile::create(&path).unwrap();
+    assert_eq!(pid_file.pid(), std::process::id());
+    assert_eq!(PidFile::read(&path).unwrap(), Some(std::process::id()));
+    drop(pid_file);
+    assert!(!path.exists());
+    assert_eq!(PidFile::read(&path).unwrap(), None);
+}
+
+#[test]
+fn test_pid_file_race_on_stale_pid() {
+    let path = Path::tmp_file().with_extension(".pid");
+    let mut exited = std::process::Command::new("true").spawn().unwrap();
+    let stale = exited.id();
+    exited.wait().unwrap();
+    for _ in 0..1000 {
+        path.write(format!("{}\n", stale).as_bytes()).unwrap();
+        let barrier = std::sync::Barrier::new(8);
+        let pid_files = std::thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        PidFile::create(&path)
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>()
+        });
+        assert_eq!(pid_files.iter().filter(|pid_file| pid_file.is_ok()).count(), 1);
+        drop(pid_files);
+        assert!(!path.exists());
+    }
+}
+
+#[test]
+fn test_pid_file_refuses_running_pid() {
+    let path = Path::tmp_file().with_extension(".pid");
+    let mut running = std::process::Command::new("sleep").arg("10").spawn().unwrap();
+    path.write(format!("{}\n", running.id()).as_bytes()).unwrap();
+    let error = PidFile::create(&path).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains(&format!("already running with pid {}", running.id()))
+    );
+    assert_eq!(PidFile::read(&path).unwrap(), Some(running.id()));
+    running.kill().unwrap();
+    running.wait().unwrap();
+    path.delete().unwrap();
+}
+
+#[test]
+fn test_daemon_fork_detaches_and_holds_pid_file() {
+    use std::io::Write;
+    let dir = Path::tmp();
+    let pid_path = dir.join("daemon.pid");
+    let log_path = dir.join("daemon.log");
+    let daemon = Daemon::new()
+        .current_dir(&dir)
+        .umask(0o077)
+        .stdout(DaemonRedirect::Path {
+            path: log_path.clone(),
+            append: true,
+        })
+        .pid_file(&pid_path);
+    let parent_sid = unsafe { libc::getsid(0) };
+    match daemon.clone().fork().unwrap() {
+        Forked::Daemon(pid_file) => {
+            let sid = unsafe { libc::getsid(0) };
+            let cwd = std::env::current_dir().unwrap();
+            let _ = writeln!(
+                std::io::stdout(),
+                "{} {} {:o} {}",
+                sid != parent_sid && sid != std::process::id() as i32,
+                cwd.display(),
+                unsafe { libc::umask(0) },
+                pid_file.as_ref().map(PidFile::pid).unwrap_or_default()
+            );
+            let _ = std::io::stdout().flush();
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            drop(pid_file);
+            unsafe { libc::_exit(0) }
+        },
+        Forked::Parent(pid) => {
+            assert_ne!(pid, std::process::id());
+            assert_eq!(PidFile::read(&pid_path).unwrap(), Some(pid));
+            let error = daemon.fork().unwrap_err();
+            assert!(error.to_string().contains("already running"), "{}", error);
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+            while log_path.read().unwrap_or_default().is_empty() {
+                assert!(std::time::Instant::now() < deadline);
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            assert_eq!(
+                log_path.read().unwrap(),
+                format!("true {} 77 {}\n", dir.canonicalize().unwrap(), pid)
+            );
+        },
+    }
+}
@@ -1,4 +1,7 @@
-use iocore::{glob, walk_dir, walk_globs, Error, NoopProgressHandler, Path, WalkProgressHandler};
+use iocore::{
+    glob, hard_link_clusters, walk_dir, walk_globs, Error, NoopProgressHandler, Path,
+    WalkProgressHandler,
+};
 use iocore_test::{folder_path, path_to_test_folder};
 
 #[test]
@@ -9,7 +12,16 @@ fn test_walk_globs_lib_folder() -> Result<(), Error> {
             .filter(|path| !path.name().starts_with("."))
             .map(|path| path.name())
             .collect::<Vec<String>>(),
-        vec!["env.rs", "errors.rs", "fs.rs", "lib.rs", "sh.rs", "sys.rs", "walk.rs",]
+        vec![
+            "asynchronous.rs",
+            "env.rs",
+            "errors.rs",
+            "fs.rs",
+            "lib.rs",
+            "sh.rs",
+            "sys.rs",
+            "walk.rs",
+        ]
     );
     Ok(())
 }
@@ -75,6 +87,7 @@ fn test_walk_dir() -> Result<(), Error> {
     assert_eq!(
         results,
         vec![
+            "asynchronous.rs",
             "env.rs",
             "errors.rs",
             "fs.rs",
@@ -83,6 +96,7 @@ fn test_walk_dir() -> Result<(), Error> {
             "sys.rs",
             "walk.rs",
             "fs/filename.rs",
+            "fs/ls_entry.rs",
             "fs/ls_path_type.rs",
             "fs/opts.rs",
             "fs/path_cmp.rs",
@@ -93,6 +107,21 @@ fn test_walk_dir() -> Result<(), Error> {
             "fs/path_utils.rs",
             "fs/perms.rs",
             "fs/size.rs",
+            "sh/cmd.rs",
+            "sh/output.rs",
+            "sh/pipeline.rs",
+            "sh/pty.rs",
+            "sh/stream.rs",
+            "sh/timeout.rs",
+            "sys/app_dirs.rs",
+            "sys/daemon.rs",
+            "sys/home.rs",
+            "sys/info.rs",
+            "sys/passwd.rs",
+            "sys/process.rs",
+            "sys/signals.rs",
+            "sys/userdb.rs",
+            "sys/which.rs",
         ]
     );
     Ok(())
@@ -118,6 +147,7 @@ fn test_walk_dir_no_aggregating_specific_directory() -> Result<(), Error> {
     assert_eq!(
         results,
         vec![
+            "asynchronous.rs",
             "env.rs",
             "errors.rs",
             "fs.rs",
@@ -126,6 +156,7 @@ fn test_walk_dir_no_aggregating_specific_directory() -> Result<(), Error> {
             "sys.rs",
             "walk.rs",
             "fs/filename.rs",
+            "fs/ls_entry.rs",
             "fs/ls_path_type.rs",
             "fs/opts.rs",
             "fs/path_cmp.rs",
@@ -136,6 +167,21 @@ fn test_walk_dir_no_aggregating_specific_directory() -> Result<(), Error> {
             "fs/path_utils.rs",
             "fs/perms.rs",
             "fs/size.rs",
+            "sh/cmd.rs",
+            "sh/output.rs",
+            "sh/pipeline.rs",
+            "sh/pty.rs",
+            "sh/stream.rs",
+            "sh/timeout.rs",
+            "sys/app_dirs.rs",
+            "sys/daemon.rs",
+            "sys/home.rs",
+            "sys/info.rs",
+            "sys/passwd.rs",
+            "sys/process.rs",
+            "sys/signals.rs",
+            "sys/userdb.rs",
+            "sys/which.rs",
         ]
     );
     Ok(())
@@ -164,6 +210,7 @@ fn test_walk_dir_skip_scanning_specific_directories() -> Result<(), Error> {
     assert_eq!(
         results,
         vec![
+            "asynchronous.rs",
             "env.rs",
             "errors.rs",
             "fs.rs",
@@ -172,6 +219,7 @@ fn test_walk_dir_skip_scanning_specific_directories() -> Result<(), Error> {
             "sys.rs",
             "walk.rs",
             "fs/filename.rs",
+            "fs/ls_entry.rs",
             "fs/ls_path_type.rs",
             "fs/opts.rs",
             "fs/path_cmp.rs",
@@ -182,6 +230,21 @@ fn test_walk_dir_skip_scanning_specific_directories() -> Result<(), Error> {
             "fs/path_utils.rs",
             "fs/perms.rs",
             "fs/size.rs",
+            "sh/cmd.rs",
+            "sh/output.rs",
+            "sh/pipeline.rs",
+            "sh/pty.rs",
+            "sh/stream.rs",
+            "sh/timeout.rs",
+            "sys/app_dirs.rs",
+            "sys/daemon.rs",
+            "sys/home.rs",
+            "sys/info.rs",
+            "sys/passwd.rs",
+            "sys/process.rs",
+            "sys/signals.rs",
+            "sys/userdb.rs",
+            "sys/which.rs",
         ]
     );
     Ok(())
@@ -206,7 +269,7 @@ fn test_walk_dir_error_handling_() -> Result<(), Error> {
     }
     assert_eq!(
         walk_dir(&path, ErrorOnWalkHandler, None),
-        Err(Error::WalkDirError("PathScanningError: path shall not be scanned [depth:1] [iocore::walk::iocore_walk_dir:[crates/iocore/iocore/walk.rs:69]]\n".to_string())),
+        Err(Error::WalkDirError("PathScanningError: path shall not be scanned [depth:1] [iocore::walk::WalkDir<_>::scan:[crates/iocore/iocore/walk.rs:102]]\n".to_string())),
     );
     Ok(())
 }
@@ -218,3 +281,31 @@ fn test_walk_dir_fixtures() -> Result<(), Error> {
     assert_eq!(entries.len(), 146);
     Ok(())
 }
+
+#[test]
+fn test_hard_link_clusters() -> Result<(), Error> {
+    let path = path_to_test_folder!();
+    path.list()?.iter().for_each(|path| {
+        path.delete_unchecked();
+    });
+    let a = path.join("a").write(b"a")?;
+    let b = path.join("b").write(b"b")?;
+    path.join("c").write(b"a")?;
+    a.hard_link_to(path.join("a.1"))?;
+    a.hard_link_to(path.join("sub/a.2").mkdir_parents()?)?;
+    b.hard_link_to(path.join("b.1"))?;
+
+    let clusters = hard_link_clusters(&walk_dir(&path, NoopProgressHandler, None)?)
+        .iter()
+        .map(|cluster| {
+            cluster
+                .iter()
+                .map(|link| link.relative_to(&path).to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<Vec<String>>>();
+    assert_eq!(clusters.len(), 2);
+    assert!(clusters.contains(&vec!["a".to_string(), "a.1".to_string(), "sub/a.2".to_string()]));
+    assert!(clusters.contains(&vec!["b".to_string(), "b.1".to_string()]));
+    Ok(())
+}